@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+
+use arrow::array::{Array, AsArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type, Int64Type};
+use arrow::error::ArrowError;
+
+use crate::woe_binning_proc::ValueCounts;
+
+
+// 64-bit integers and floats are encoded into usize values of the 
+// binning without loss only on 64-bit targets
+#[cfg(not(target_pointer_width = "64"))]
+compile_error!("The arrow feature requires a 64-bit target");
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrowValue {
+    Null,
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+
+fn encode_i64(x: i64) -> usize {
+    // Order-preserving map to unsigned values
+    ((x as u64) ^ (1 << 63)) as usize
+}
+
+
+fn decode_i64(value: usize) -> i64 {
+    ((value as u64) ^ (1 << 63)) as i64
+}
+
+
+fn encode_f64(x: f64) -> usize {
+    // Order-preserving map of the bits: negative numbers are inverted,
+    // positive ones get the sign bit
+    let bits = x.to_bits();
+    (if bits >> 63 == 1 { !bits } else { bits | (1 << 63) }) as usize
+}
+
+
+fn decode_f64(value: usize) -> f64 {
+    let bits = value as u64;
+    f64::from_bits(if bits >> 63 == 1 { bits & !(1 << 63) } else { !bits })
+}
+
+
+pub struct ArrowColumnCounts {
+    is_numeric: bool,
+    // Int64 or Float64 for numeric columns, fixed by the first chunk
+    numeric_type: Option<DataType>,
+    counts: ValueCounts,
+    // Categories by value, 0 is null
+    categories: Vec<Option<String>>,
+    category_map: HashMap<String, usize>,
+    // Target 1 and 0 counts of nulls in numeric columns
+    null_counts: (usize, usize),
+}
+
+
+impl ArrowColumnCounts {
+    pub fn new(is_numeric: bool) -> Self {
+        // Numeric columns are integers or floats, nulls (and NaN) are
+        // counted aside because numeric bins have no place for them.
+        // Categorial columns are strings (dictionary-encoded or not) or
+        // anything castable to strings, null is a category.
+        Self {
+            is_numeric,
+            numeric_type: None,
+            counts: ValueCounts::new(),
+            categories: vec![None],
+            category_map: HashMap::new(),
+            null_counts: (0, 0),
+        }
+    }
+
+    pub fn add_chunk(&mut self, column: &dyn Array,
+                     target: &dyn Array) -> Result<(), ArrowError> {
+        // Counts the rows of a chunk (such as a record batch), chunks of
+        // the same column can go one by one
+        if column.len() != target.len() {
+            return Err(ArrowError::InvalidArgumentError(
+                "Column and target have different lengths".to_string()
+            ));
+        }
+
+        let target = Self::_read_target(target)?;
+
+        if self.is_numeric {
+            self._add_numeric(column, &target)
+        } else {
+            self._add_categorial(column, &target)
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_numeric
+    }
+
+    pub fn get_counts(&self) -> &ValueCounts {
+        &self.counts
+    }
+
+    pub fn get_null_counts(&self) -> (usize, usize) {
+        // Rows of target 1 and 0 skipped in a numeric column
+        self.null_counts
+    }
+
+    pub fn get_value(&self, value: usize) -> ArrowValue {
+        // Original value of a value in bins
+        match &self.numeric_type {
+            Some(DataType::Int64) => ArrowValue::Int(decode_i64(value)),
+            Some(_) => ArrowValue::Float(decode_f64(value)),
+            None => match &self.categories[value] {
+                Some(text) => ArrowValue::Text(text.clone()),
+                None => ArrowValue::Null,
+            },
+        }
+    }
+
+    fn _read_target(target: &dyn Array) -> Result<Vec<bool>, ArrowError> {
+        // Booleans or numbers (non-zero is 1), nulls are not allowed
+        if target.null_count() > 0 {
+            return Err(ArrowError::InvalidArgumentError(
+                "Target has nulls".to_string()
+            ));
+        }
+        let target = cast(target, &DataType::Boolean)?;
+        Ok(target.as_boolean().values().iter().collect())
+    }
+
+    fn _add_numeric(&mut self, column: &dyn Array,
+                    target: &[bool]) -> Result<(), ArrowError> {
+        let data_type = if column.data_type().is_integer() {
+            DataType::Int64
+        } else if column.data_type().is_floating() {
+            DataType::Float64
+        } else {
+            return Err(ArrowError::InvalidArgumentError(format!(
+                "Numeric column of type {} is not supported",
+                column.data_type()
+            )));
+        };
+
+        if *self.numeric_type.get_or_insert(data_type.clone()) != data_type {
+            return Err(ArrowError::InvalidArgumentError(
+                "Chunks of integers and floats are mixed".to_string()
+            ));
+        }
+
+        let column = cast(column, &data_type)?;
+
+        let values: Vec<Option<usize>> = if data_type == DataType::Int64 {
+            column.as_primitive::<Int64Type>().iter()
+                .map(|x| x.map(encode_i64))
+                .collect()
+        } else {
+            // Adding 0.0 makes -0.0 the same value as 0.0
+            column.as_primitive::<Float64Type>().iter()
+                .map(|x| x.filter(|x| !x.is_nan())
+                    .map(|x| encode_f64(x + 0.0)))
+                .collect()
+        };
+
+        for (value, &trg) in values.into_iter().zip(target) {
+            match value {
+                Some(value) => self.counts.add(value, trg),
+                None if trg => self.null_counts.0 += 1,
+                None => self.null_counts.1 += 1,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn _add_categorial(&mut self, column: &dyn Array,
+                       target: &[bool]) -> Result<(), ArrowError> {
+        // Dictionary values are looked up once per chunk, rows go by keys
+        let (keys, values) = match column.as_any_dictionary_opt() {
+            Some(dictionary) => (
+                Some(dictionary.normalized_keys()),
+                cast(dictionary.values(), &DataType::Utf8)?,
+            ),
+            None => (None, cast(column, &DataType::Utf8)?),
+        };
+
+        let value_ids: Vec<usize> = values.as_string::<i32>().iter()
+            .map(|text| match text {
+                Some(text) => self._get_category(text),
+                None => 0,
+            })
+            .collect();
+
+        for (row, &trg) in target.iter().enumerate() {
+            let value = if column.is_null(row) {
+                0
+            } else {
+                match &keys {
+                    Some(keys) => value_ids[keys[row]],
+                    None => value_ids[row],
+                }
+            };
+            self.counts.add(value, trg);
+        }
+
+        Ok(())
+    }
+
+    fn _get_category(&mut self, text: &str) -> usize {
+        // Values are numbered in order of appearance
+        match self.category_map.get(text) {
+            Some(&value) => value,
+            None => {
+                let value = self.categories.len();
+                self.categories.push(Some(text.to_string()));
+                self.category_map.insert(text.to_string(), value);
+                value
+            },
+        }
+    }
+}
+
+
+#[cfg(feature = "parquet")]
+pub fn read_parquet_counts(path: &std::path::Path, column: &str,
+                           target: &str, is_numeric: bool) ->
+                           Result<ArrowColumnCounts, ArrowError> {
+    // Counts of a column of a Parquet file, only the column and the target
+    // are read, batch by batch
+    use parquet::arrow::ProjectionMask;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let file = std::fs::File::open(path)?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+    let mask = ProjectionMask::columns(builder.parquet_schema(),
+                                       [column, target]);
+    let reader = builder.with_projection(mask).build()?;
+
+    let mut counts = ArrowColumnCounts::new(is_numeric);
+
+    for batch in reader {
+        let batch = batch?;
+        let find = |name: &str| batch.column_by_name(name).cloned()
+            .ok_or(ArrowError::SchemaError(
+                format!("Column {} is not found", name)
+            ));
+        counts.add_chunk(&find(column)?, &find(target)?)?;
+    }
+
+    Ok(counts)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow::array::{BooleanArray, DictionaryArray, Float64Array,
+                       Int32Array, StringArray};
+    use arrow::datatypes::Int8Type;
+
+    use crate::woe_binning_proc::WoeBinningProc;
+
+    #[test]
+    fn test_encoding() {
+        let ints = [i64::MIN, -5, 0, 3, i64::MAX];
+        let floats = [f64::NEG_INFINITY, -2.5, -0.0, 0.0, 1e-300, 7.0];
+
+        for pair in ints.windows(2) {
+            assert!(encode_i64(pair[0]) < encode_i64(pair[1]));
+            assert_eq!(decode_i64(encode_i64(pair[0])), pair[0]);
+        }
+        for pair in floats.windows(2) {
+            assert!(encode_f64(pair[0]) < encode_f64(pair[1]));
+            assert_eq!(decode_f64(encode_f64(pair[1])), pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_numeric() {
+        let column = Float64Array::from_iter(
+            (0..100).map(|i| if i % 10 == 0 { None }
+                             else { Some((i % 7) as f64 - 3.0) })
+        );
+        let target = Int32Array::from_iter_values((0..100).map(|i| i % 7 / 4));
+
+        // Two chunks give the same counts as one
+        let mut counts = ArrowColumnCounts::new(true);
+        counts.add_chunk(&column.slice(0, 40), &target.slice(0, 40)).unwrap();
+        counts.add_chunk(&column.slice(40, 60), &target.slice(40, 60))
+            .unwrap();
+
+        let mut whole = ArrowColumnCounts::new(true);
+        whole.add_chunk(&column, &target).unwrap();
+
+        assert_eq!(counts.get_counts(), whole.get_counts());
+        assert_eq!(counts.get_counts().get_rows_num(), 90);
+        assert_eq!(counts.get_null_counts().0 + counts.get_null_counts().1,
+                   10);
+
+        let mut wbp = WoeBinningProc::new(2, 1.0);
+        wbp.process_numeric_counts(counts.get_counts());
+        let bins = wbp.get_bins_array().unwrap();
+        assert_eq!(counts.get_value(bins[0][0]), ArrowValue::Float(-3.0));
+        assert_eq!(counts.get_value(bins[1][0]), ArrowValue::Float(1.0));
+
+        // Floats after integers are refused
+        let ints = Int32Array::from(vec![1]);
+        let mut counts = ArrowColumnCounts::new(true);
+        counts.add_chunk(&ints, &BooleanArray::from(vec![true])).unwrap();
+        assert!(counts.add_chunk(&Float64Array::from(vec![1.0]),
+                                 &BooleanArray::from(vec![true])).is_err());
+    }
+
+    #[test]
+    fn test_categorial() {
+        let dictionary: DictionaryArray<Int8Type> = vec![
+            Some("a"), Some("b"), None, Some("a"), Some("c"), Some("b"),
+        ].into_iter().collect();
+        let plain = StringArray::from(vec![Some("c"), None, Some("a")]);
+        let target = BooleanArray::from(vec![true, false, false, true,
+                                             false, true]);
+
+        let mut counts = ArrowColumnCounts::new(false);
+        counts.add_chunk(&dictionary, &target).unwrap();
+        counts.add_chunk(&plain, &target.slice(0, 3)).unwrap();
+
+        // Values are the same across chunks, null is a category
+        assert_eq!(counts.get_counts().get_values_num(), 4);
+        assert_eq!(counts.get_counts().get_rows_num(), 9);
+        assert_eq!(counts.get_value(0), ArrowValue::Null);
+        assert_eq!(counts.get_value(1), ArrowValue::Text("a".to_string()));
+
+        let mut wbp = WoeBinningProc::new(2, 1.0);
+        wbp.process_categorial_counts(counts.get_counts());
+        assert_eq!(wbp.get_bins_num(), Some(2));
+
+        assert!(counts.add_chunk(&plain, &target).is_err());
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_read_parquet_counts() {
+        use std::sync::Arc;
+
+        use arrow::array::RecordBatch;
+        use parquet::arrow::ArrowWriter;
+
+        let column = Float64Array::from_iter_values((0..1000)
+            .map(|i| (i % 50) as f64));
+        let other = StringArray::from_iter_values((0..1000)
+            .map(|i| format!("v{}", i % 3)));
+        let target = BooleanArray::from_iter((0..1000)
+            .map(|i| Some(i % 50 < 20)));
+
+        let batch = RecordBatch::try_from_iter([
+            ("x", Arc::new(column.clone()) as Arc<dyn Array>),
+            ("other", Arc::new(other)),
+            ("y", Arc::new(target.clone())),
+        ]).unwrap();
+
+        let path = std::env::temp_dir()
+            .join(format!("woebin-test-{}.parquet", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), None)
+            .unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let counts = read_parquet_counts(&path, "x", "y", true).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut expected = ArrowColumnCounts::new(true);
+        expected.add_chunk(&column, &target).unwrap();
+        assert_eq!(counts.get_counts(), expected.get_counts());
+    }
+}
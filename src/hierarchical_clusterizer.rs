@@ -1,6 +1,8 @@
-use std::collections::{BTreeMap, BinaryHeap};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
 use std::cmp::{Reverse, Ordering};
 
+use crate::neighbourhood::{Neighbourhood, ChainNeighbourhood, GraphNeighbourhood};
+
 
 pub trait ClusterTrait {
     fn merge(cluster1: &Self, cluster2: &Self) -> Self;
@@ -8,7 +10,7 @@ pub trait ClusterTrait {
 }
 
 
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct DistanceHeapNode {
     distance: f64,
     cluster_ids: (usize, usize),
@@ -17,13 +19,18 @@ struct DistanceHeapNode {
 
 impl Ord for DistanceHeapNode {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.distance > other.distance {
-            Ordering::Greater
-        } else if self.distance > other.distance {
-            Ordering::Less
-        } else {
-            Ordering::Equal
-        }
+        // Equal distances are resolved by cluster ids, so the merge order 
+        // does not depend on the order the nodes were pushed in. NaN never 
+        // gets here, see _create_distance_heap_node
+        self.distance.total_cmp(&other.distance)
+            .then(self.cluster_ids.cmp(&other.cluster_ids))
+    }
+}
+
+
+impl PartialOrd for DistanceHeapNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -31,28 +38,167 @@ impl Ord for DistanceHeapNode {
 impl Eq for DistanceHeapNode {}
 
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepInfo {
+    pub cluster_ids: (usize, usize),
+    pub distance: f64,
+    pub cluster_new_id: usize,
+    pub cluster_count: usize,
+}
+
+
+// Called after every merge, returns true to stop the clustering
+pub type Observer = Box<dyn FnMut(&StepInfo) -> bool + Send + Sync>;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linkage {
+    Single,
+    Complete,
+    Average,
+    Ward,
+}
+
+
+impl Linkage {
+    pub fn update(&self, d_ki: f64, d_kj: f64, d_ij: f64, 
+                  n_i: usize, n_j: usize, n_k: usize) -> f64 {
+        // Lance–Williams recurrence: distance from cluster k to the union 
+        // of clusters i and j (Ward is applied to euclidean distances as 
+        // in SciPy)
+        let (n_i, n_j, n_k) = (n_i as f64, n_j as f64, n_k as f64);
+        match self {
+            Linkage::Single => d_ki.min(d_kj),
+            Linkage::Complete => d_ki.max(d_kj),
+            Linkage::Average => (n_i * d_ki + n_j * d_kj) / (n_i + n_j),
+            Linkage::Ward => (
+                ((n_i + n_k) * d_ki * d_ki + (n_j + n_k) * d_kj * d_kj - 
+                 n_k * d_ij * d_ij) / (n_i + n_j + n_k)
+            ).sqrt(),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkageCluster;
+
+
+impl ClusterTrait for LinkageCluster {
+    // Clusters for linkage methods carry nothing, distances come from 
+    // the distance matrix given in set_linkage
+    fn merge(_cluster1: &Self, _cluster2: &Self) -> Self {
+        Self
+    }
+
+    fn distance(_cluster1: &Self, _cluster2: &Self) -> Option<f64> {
+        None
+    }
+}
+
+
+pub fn linkage_matrix(distances: &[f64], linkage: Linkage) -> Vec<[f64; 4]> {
+    // Full clustering of points by a condensed distance matrix 
+    // (as returned by scipy.spatial.distance.pdist)
+    let size = condensed_size(distances.len())
+        .expect("Invalid condensed matrix length");
+
+    let mut clusterizer = HierarchicalClusterizer::<LinkageCluster>::new();
+    clusterizer.set_linkage(linkage, distances);
+    clusterizer.initialize(&vec![LinkageCluster; size]);
+    clusterizer.clusterize(1);
+    clusterizer.get_linkage_matrix()
+}
+
+
+pub(crate) fn condensed_size(len: usize) -> Option<usize> {
+    // Solve size * (size - 1) / 2 = len, None if there is no solution
+    let size = ((1.0 + (1.0 + 8.0 * len as f64).sqrt()) / 2.0).round() as usize;
+    (size * (size - 1) / 2 == len).then_some(size)
+}
+
+
 pub struct HierarchicalClusterizer<T: ClusterTrait> {
-    opt_1d: bool,
+    neighbourhood: Option<Box<dyn Neighbourhood + Send + Sync>>,
+    opt_low_memory: bool,
     cluster_map: BTreeMap<usize, T>,
+    removed_map: BTreeMap<usize, T>,
     cluster_id_next: usize,
     distance_heap: BinaryHeap<Reverse<DistanceHeapNode>>,
-    order_map: BTreeMap<usize, (Option<usize>, Option<usize>)>,
+    best_map: BTreeMap<usize, Option<DistanceHeapNode>>,
+    best_stale: BTreeSet<usize>,
+    merge_tree: BTreeMap<usize, (usize, usize)>,
+    merge_history: Vec<DistanceHeapNode>,
+    size_map: BTreeMap<usize, usize>,
+    linkage: Option<(Linkage, Vec<f64>)>,
+    distance_map: BTreeMap<(usize, usize), f64>,
+    distance_history: Vec<Vec<((usize, usize), f64)>>,
+    observer: Option<Observer>,
+    stopped: bool,
+    locked: BTreeSet<usize>,
 }
 
 
 impl<T: ClusterTrait + Clone> HierarchicalClusterizer<T> {
     pub fn new() -> Self {
         Self {
-            opt_1d: false,
+            neighbourhood: None,
+            opt_low_memory: false,
             cluster_map: BTreeMap::new(),
+            removed_map: BTreeMap::new(),
             cluster_id_next: 0,
             distance_heap: BinaryHeap::new(),
-            order_map: BTreeMap::new(),
+            best_map: BTreeMap::new(),
+            best_stale: BTreeSet::new(),
+            merge_tree: BTreeMap::new(),
+            merge_history: Vec::new(),
+            size_map: BTreeMap::new(),
+            linkage: None,
+            distance_map: BTreeMap::new(),
+            distance_history: Vec::new(),
+            observer: None,
+            stopped: false,
+            locked: BTreeSet::new(),
         }
     }
 
     pub fn set_1d_opt(&mut self, opt_1d: bool) {
-        self.opt_1d = opt_1d;
+        // Only neighbours in the order of initial clusters can be merged
+        self.neighbourhood = if opt_1d {
+            Some(Box::new(ChainNeighbourhood::new()))
+        } else {
+            None
+        };
+    }
+
+    pub fn set_connectivity(&mut self, edges: &[(usize, usize)]) {
+        // Only clusters adjacent in the graph of initial clusters can be 
+        // merged, the merged cluster inherits the adjacency of both
+        self.set_neighbourhood(Box::new(GraphNeighbourhood::new(edges)));
+    }
+
+    pub fn set_neighbourhood(&mut self, 
+                             neighbourhood: Box<dyn Neighbourhood + Send + Sync>) {
+        self.neighbourhood = Some(neighbourhood);
+    }
+
+    pub fn set_low_memory_opt(&mut self, opt_low_memory: bool) {
+        // Keeps only the nearest neighbour of each cluster instead of 
+        // all pairs in distance_heap, so memory is O(n) at the cost of 
+        // extra distance calculations. Merges are the same. 
+        // Ignored if a neighbourhood is set.
+        self.opt_low_memory = opt_low_memory;
+    }
+
+    pub fn set_linkage(&mut self, linkage: Linkage, distances: &[f64]) {
+        // Distances between initial clusters as a condensed matrix, 
+        // they are updated by the linkage method instead of 
+        // ClusterTrait::distance
+        self.linkage = Some((linkage, distances.to_vec()));
+    }
+
+    pub fn set_observer(&mut self, observer: Option<Observer>) {
+        self.observer = observer;
     }
 
     pub fn initialize(&mut self, clusters: &[T]) {
@@ -65,15 +211,54 @@ impl<T: ClusterTrait + Clone> HierarchicalClusterizer<T> {
         // Set next cluster_id
         self.cluster_id_next = self.cluster_map.len();
 
+        // Reset the merge state
+        self.stopped = false;
+        self.locked.clear();
+        self.removed_map.clear();
+        self.merge_tree.clear();
+        self.merge_history.clear();
+        self.size_map.clear();
+        self.size_map.extend((0..self.cluster_id_next).map(|id| (id, 1)));
+
+        // Build distance_map
+        self._build_distance_map();
+
         // Build distance_heap
-        if self.opt_1d {
-            self._build_order_map();
-            self._build_distance_heap_1d();
+        self.distance_heap.clear();
+        self.best_map.clear();
+        self.best_stale.clear();
+
+        if self.neighbourhood.is_some() {
+            self._build_distance_heap_neighbourhood();
+        } else if self.opt_low_memory {
+            self._build_best_map();
         } else {
             self._build_distance_heap();
         }
     }
 
+    fn _build_distance_map(&mut self) {
+        let size = self.cluster_map.len();
+
+        self.distance_map.clear();
+        self.distance_history.clear();
+
+        if let Some((_, distances)) = &self.linkage {
+            assert_eq!(condensed_size(distances.len()), Some(size), 
+                       "Distance matrix does not match clusters");
+
+            let mut idx = 0;
+            for cluster_id1 in 0..size {
+                for cluster_id2 in (cluster_id1 + 1)..size {
+                    self.distance_map.insert(
+                        (cluster_id1, cluster_id2), distances[idx]
+                    );
+                    idx += 1;
+                }
+            }
+        }
+    }
+
     fn _build_distance_heap(&mut self) {
         let size = self.cluster_map.len();
 
@@ -90,14 +275,14 @@ impl<T: ClusterTrait + Clone> HierarchicalClusterizer<T> {
         }
     }
 
-    fn _build_distance_heap_1d(&mut self) {
+    fn _build_distance_heap_neighbourhood(&mut self) {
         let size = self.cluster_map.len();
 
-        self.distance_heap.clear();
-
-        for cluster_id1 in 0..(size - 1) {
-            let cluster_id2 = cluster_id1 + 1;
+        let neighbourhood = self.neighbourhood.as_mut().unwrap();
+        neighbourhood.initialize(size);
+        let pairs = neighbourhood.pairs();
 
+        for (cluster_id1, cluster_id2) in pairs {
             if let Some(node) = self._create_distance_heap_node(
                         cluster_id1, cluster_id2
                     ) {
@@ -106,21 +291,12 @@ impl<T: ClusterTrait + Clone> HierarchicalClusterizer<T> {
         }
     }
 
-    fn _build_order_map(&mut self) {
-        let size = self.cluster_map.len();
-
-        self.order_map.clear();
+    fn _build_best_map(&mut self) {
+        let cluster_ids: Vec<usize> = self.cluster_map.keys().cloned().collect();
 
-        for cluster_id in 0..size {
-            self.order_map.insert(
-                cluster_id,
-                (
-                    if cluster_id > 0 { Some(cluster_id - 1) } 
-                                 else { None },
-                    if cluster_id < size - 1 { Some(cluster_id + 1) } 
-                                        else { None },
-                )
-            );
+        for cluster_id in cluster_ids {
+            let best = self._find_best(cluster_id);
+            self.best_map.insert(cluster_id, best);
         }
     }
 
@@ -132,14 +308,118 @@ impl<T: ClusterTrait + Clone> HierarchicalClusterizer<T> {
         self.cluster_map.values().cloned().collect()
     }
 
-    fn _distance_heap_pop(&mut self) -> Option<(usize, usize)> {
+    pub fn is_stopped(&self) -> bool {
+        // The observer asked to stop
+        self.stopped
+    }
+
+    pub fn get_cluster_ids(&self) -> Vec<usize> {
+        // In the same order as get_clusters
+        self.cluster_map.keys().cloned().collect()
+    }
+
+    pub fn is_locked(&self, cluster_id: usize) -> bool {
+        self.locked.contains(&cluster_id)
+    }
+
+    pub fn set_locked(&mut self, cluster_id: usize, locked: bool) -> bool {
+        // A locked cluster is never merged by step, only by merge. 
+        // Returns false if there is no such cluster.
+        if !self.cluster_map.contains_key(&cluster_id) {
+            return false;
+        }
+
+        if locked {
+            self.locked.insert(cluster_id);
+        } else if self.locked.remove(&cluster_id) {
+            // Candidates of the cluster were dropped while it was locked
+            if let Some(neighbourhood) = &self.neighbourhood {
+                let pairs: Vec<(usize, usize)> = neighbourhood.pairs()
+                    .into_iter()
+                    .filter(|&(cluster_id1, cluster_id2)| 
+                        cluster_id1 == cluster_id || cluster_id2 == cluster_id
+                    )
+                    .collect();
+                for (cluster_id1, cluster_id2) in pairs {
+                    if let Some(node) = self._create_distance_heap_node(
+                                cluster_id1, cluster_id2
+                            ) {
+                        self.distance_heap.push(node);
+                    }
+                }
+            } else if !self.opt_low_memory {
+                let cluster_ids: Vec<usize> = self.cluster_map.keys()
+                    .cloned().collect();
+                for other_id in cluster_ids {
+                    if other_id != cluster_id {
+                        if let Some(node) = self._create_distance_heap_node(
+                                    cluster_id.min(other_id), 
+                                    cluster_id.max(other_id)
+                                ) {
+                            self.distance_heap.push(node);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Nearest neighbours are searched again with the new lock
+        if self.neighbourhood.is_none() && self.opt_low_memory {
+            self.best_map.clear();
+            self.best_stale.clear();
+            self._build_best_map();
+        }
+
+        true
+    }
+
+    pub fn get_members(&self, cluster_id: usize) -> Vec<usize> {
+        // Indices of the initial clusters merged into the cluster, 
+        // they are the leaves of merge_tree
+        let mut members = Vec::new();
+        let mut stack = vec![cluster_id];
+
+        while let Some(cluster_id) = stack.pop() {
+            if let Some(&(cluster_id1, cluster_id2)) = 
+                    self.merge_tree.get(&cluster_id) {
+                stack.push(cluster_id2);
+                stack.push(cluster_id1);
+            } else {
+                members.push(cluster_id);
+            }
+        }
+
+        members
+    }
+
+    pub fn get_linkage_matrix(&self) -> Vec<[f64; 4]> {
+        // Merges in SciPy format: the ids of merged clusters (the smaller 
+        // first), the distance and the number of initial clusters in the 
+        // new one. The new cluster gets the id of the initial cluster 
+        // count plus the row index.
+        self.merge_history.iter().enumerate().map(|(idx, node)| {
+            let (cluster_id1, cluster_id2) = node.cluster_ids;
+            let cluster_new_id = self.cluster_id_next - 
+                                 self.merge_history.len() + idx;
+            [
+                cluster_id1.min(cluster_id2) as f64,
+                cluster_id1.max(cluster_id2) as f64,
+                node.distance,
+                self.size_map[&cluster_new_id] as f64,
+            ]
+        }).collect()
+    }
+
+    fn _distance_heap_pop(&mut self) -> Option<DistanceHeapNode> {
         loop {
             let node = self.distance_heap.pop();
 
-            if let Some(Reverse(DistanceHeapNode { cluster_ids, .. })) = node {
-                if self.cluster_map.contains_key(&cluster_ids.0) && 
-                   self.cluster_map.contains_key(&cluster_ids.1) {
-                    return Some(cluster_ids);
+            if let Some(Reverse(node)) = node {
+                if self.cluster_map.contains_key(&node.cluster_ids.0) && 
+                   self.cluster_map.contains_key(&node.cluster_ids.1) && 
+                   !self.locked.contains(&node.cluster_ids.0) && 
+                   !self.locked.contains(&node.cluster_ids.1) {
+                    return Some(node);
                 }
             } else {
                 return None;
@@ -147,20 +427,129 @@ impl<T: ClusterTrait + Clone> HierarchicalClusterizer<T> {
         }
     }
 
+    fn _best_map_pop(&mut self) -> Option<DistanceHeapNode> {
+        // Stale clusters are searched again only when their lower bound 
+        // reaches the minimum, ties included, so they are resolved the 
+        // same way as in distance_heap
+        loop {
+            let best = self.best_map.iter()
+                .filter(|(cluster_id, _)| !self.best_stale.contains(cluster_id))
+                .filter_map(|(_, best)| *best)
+                .min();
+
+            let stale = self.best_stale.iter()
+                .map(|&cluster_id| (self.best_map[&cluster_id].unwrap(), 
+                                    cluster_id))
+                .min_by(|(node1, _), (node2, _)| node1.cmp(node2));
+
+            match stale {
+                Some((node, cluster_id)) if best.is_none_or(
+                            |best| node.distance <= best.distance) => {
+                    self.best_stale.remove(&cluster_id);
+                    let best = self._find_best(cluster_id);
+                    self.best_map.insert(cluster_id, best);
+                }
+                _ => {
+                    return best;
+                }
+            }
+        }
+    }
+
+    fn _find_best(&self, cluster_id: usize) -> Option<DistanceHeapNode> {
+        // Pairs are ordered by ids the same way as in distance_heap, 
+        // so ties are resolved identically
+        self.cluster_map.keys()
+            .filter(|&&other_id| other_id != cluster_id)
+            .filter_map(|&other_id| self._create_distance_heap_node(
+                cluster_id.min(other_id), cluster_id.max(other_id)
+            ))
+            .map(|Reverse(node)| node)
+            .min()
+    }
+
     fn _create_distance_heap_node(&self, cluster_id1: usize, 
                                   cluster_id2: usize) -> 
                 Option<Reverse<DistanceHeapNode>> {
-        if let Some(distance) = T::distance(
-                    &self.cluster_map[&cluster_id1], 
-                    &self.cluster_map[&cluster_id2]
-                ) {
-            Some(Reverse(DistanceHeapNode {
+        // NaN distance means the clusters cannot be merged, 
+        // the same as None or a locked cluster
+        if self.locked.contains(&cluster_id1) || 
+                self.locked.contains(&cluster_id2) {
+            return None;
+        }
+
+        self._distance(cluster_id1, cluster_id2)
+            .filter(|distance| !distance.is_nan())
+            .map(|distance| Reverse(DistanceHeapNode {
                 distance,
                 cluster_ids: (cluster_id1, cluster_id2),
             }))
+    }
+
+    fn _distance(&self, cluster_id1: usize, cluster_id2: usize) -> 
+                Option<f64> {
+        if self.linkage.is_some() {
+            self.distance_map.get(
+                &(cluster_id1.min(cluster_id2), cluster_id1.max(cluster_id2))
+            ).cloned()
         } else {
-            None
+            T::distance(
+                &self.cluster_map[&cluster_id1], 
+                &self.cluster_map[&cluster_id2]
+            )
+        }
+    }
+
+    fn _distance_map_update(&mut self, cluster_new_id: usize, 
+                            cluster_id1: usize, cluster_id2: usize) {
+        let linkage = match &self.linkage {
+            Some((linkage, _)) => *linkage,
+            None => return,
+        };
+
+        let key = |a: usize, b: usize| (a.min(b), a.max(b));
+
+        let n_i = self.size_map[&cluster_id1];
+        let n_j = self.size_map[&cluster_id2];
+        let d_ij = self.distance_map.remove(&key(cluster_id1, cluster_id2))
+            .unwrap();
+
+        let cluster_ids: Vec<usize> = self.cluster_map.keys().cloned()
+            .filter(|&id| id != cluster_id1 && id != cluster_id2 && 
+                          id != cluster_new_id)
+            .collect();
+
+        // Removed distances are kept for unstep
+        let mut removed = vec![(key(cluster_id1, cluster_id2), d_ij)];
+
+        for cluster_id in cluster_ids {
+            let d_ki = self.distance_map.remove(&key(cluster_id, cluster_id1))
+                .unwrap();
+            let d_kj = self.distance_map.remove(&key(cluster_id, cluster_id2))
+                .unwrap();
+            let n_k = self.size_map[&cluster_id];
+            self.distance_map.insert(
+                key(cluster_id, cluster_new_id), 
+                linkage.update(d_ki, d_kj, d_ij, n_i, n_j, n_k)
+            );
+            removed.push((key(cluster_id, cluster_id1), d_ki));
+            removed.push((key(cluster_id, cluster_id2), d_kj));
+        }
+
+        self.distance_history.push(removed);
+    }
+
+    fn _distance_map_restore(&mut self, cluster_new_id: usize) {
+        if self.linkage.is_none() {
+            return;
+        }
+
+        for &cluster_id in self.cluster_map.keys() {
+            self.distance_map.remove(&(cluster_id, cluster_new_id));
         }
+
+        let removed = self.distance_history.pop().unwrap();
+        self.distance_map.extend(removed);
     }
 
     fn _distance_heap_push_related(&mut self, cluster_new_id: usize) {
@@ -175,87 +564,216 @@ impl<T: ClusterTrait + Clone> HierarchicalClusterizer<T> {
         }
     }
 
-    fn _distance_heap_push_related_1d(&mut self, cluster_new_id: usize) {
-        // Left
-        if let Some(cluster_id) = self.order_map[&cluster_new_id].0 {
-            if let Some(node) = self._create_distance_heap_node(
-                        cluster_id, cluster_new_id
-                    ) {
-                self.distance_heap.push(node);
-            }
-        }
+    fn _distance_heap_push_related_neighbourhood(
+                &mut self, cluster_new_id: usize, 
+                cluster_id1: usize, cluster_id2: usize) {
+        let pairs = self.neighbourhood.as_mut().unwrap().merge(
+            cluster_new_id, cluster_id1, cluster_id2
+        );
 
-        // Right
-        if let Some(cluster_id) = self.order_map[&cluster_new_id].1 {
+        for (cluster_id1, cluster_id2) in pairs {
             if let Some(node) = self._create_distance_heap_node(
-                        cluster_new_id, cluster_id
+                        cluster_id1, cluster_id2
                     ) {
                 self.distance_heap.push(node);
             }
         }
     }
 
-    fn _order_map_insert_new(&mut self, cluster_new_id: usize, 
-                             cluster_id1: usize, cluster_id2: usize) {
-        // cluster_id1 goes before cluster_id2 in the linked list
-        // Insert new node
-        self.order_map.insert(
-            cluster_new_id, 
-            (
-                self.order_map[&cluster_id1].0, 
-                self.order_map[&cluster_id2].1,
-            )
-        );
-
-        // Rebind left node
-        if let Some(cluster_id) = self.order_map[&cluster_new_id].0 {
-            self.order_map.get_mut(&cluster_id).unwrap().1 = 
-                Some(cluster_new_id);
-        } 
+    fn _best_map_update(&mut self, cluster_new_id: usize, 
+                        cluster_id1: usize, cluster_id2: usize) {
+        // A cluster whose nearest neighbour has gone becomes stale: the 
+        // old distance is kept as a lower bound (the distances to the other 
+        // clusters are unchanged) and the O(n) search is deferred to 
+        // _best_map_pop. So a step is O(n) plus O(n) for each stale cluster 
+        // that reaches the minimum, rather than for each stale cluster.
+        self.best_map.remove(&cluster_id1);
+        self.best_map.remove(&cluster_id2);
+        self.best_stale.remove(&cluster_id1);
+        self.best_stale.remove(&cluster_id2);
+
+        let mut best_new: Option<DistanceHeapNode> = None;
+
+        let cluster_ids: Vec<usize> = self.best_map.keys().cloned().collect();
+
+        for cluster_id in cluster_ids {
+            let node = self._create_distance_heap_node(
+                cluster_id, cluster_new_id
+            ).map(|Reverse(node)| node);
+
+            if let Some(node) = node {
+                if best_new.is_none_or(|best| node < best) {
+                    best_new = Some(node);
+                }
+            }
 
-        // Rebind right node
-        if let Some(cluster_id) = self.order_map[&cluster_new_id].1 {
-            self.order_map.get_mut(&cluster_id).unwrap().0 = 
-                Some(cluster_new_id);
-        } 
+            let best = self.best_map.get_mut(&cluster_id).unwrap();
+
+            if self.best_stale.contains(&cluster_id) || best.is_some_and(
+                        |best| {
+                            best.cluster_ids.0 == cluster_id1 || 
+                            best.cluster_ids.0 == cluster_id2 || 
+                            best.cluster_ids.1 == cluster_id1 || 
+                            best.cluster_ids.1 == cluster_id2
+                        }) {
+                // The new cluster is the nearest only if it is strictly 
+                // nearer than the lower bound
+                match node {
+                    Some(node) if node.distance < best.unwrap().distance => {
+                        *best = Some(node);
+                        self.best_stale.remove(&cluster_id);
+                    }
+                    _ => {
+                        self.best_stale.insert(cluster_id);
+                    }
+                }
+            } else if let Some(node) = node {
+                if best.is_none_or(|best| node < best) {
+                    *best = Some(node);
+                }
+            }
+        }
 
-        // Remove old nodes
-        self.order_map.remove(&cluster_id1);
-        self.order_map.remove(&cluster_id2);
+        self.best_map.insert(cluster_new_id, best_new);
     }
 
-    fn _merge_clusters(&mut self, cluster_id1: usize, 
-                       cluster_id2: usize) -> usize {
+    fn _merge_clusters(&mut self, node: DistanceHeapNode) -> usize {
+        let (cluster_id1, cluster_id2) = node.cluster_ids;
+
         let cluster_new = T::merge(&self.cluster_map[&cluster_id1], 
                                    &self.cluster_map[&cluster_id2]);
 
         let cluster_new_id = self.cluster_id_next;
         self.cluster_map.insert(cluster_new_id, cluster_new);
+        self.merge_tree.insert(cluster_new_id, (cluster_id1, cluster_id2));
+        self.merge_history.push(node);
+        self.size_map.insert(
+            cluster_new_id, 
+            self.size_map[&cluster_id1] + self.size_map[&cluster_id2]
+        );
         self.cluster_id_next += 1;
 
+        self._distance_map_update(cluster_new_id, cluster_id1, cluster_id2);
+
         cluster_new_id
     }
 
     pub fn step(&mut self) -> bool {
         // 1. Get closest clusters
-        let cluster_id_pair = self._distance_heap_pop();
+        let node = if self.neighbourhood.is_none() && self.opt_low_memory {
+            self._best_map_pop()
+        } else {
+            self._distance_heap_pop()
+        };
         
-        if let Some((cluster_id1, cluster_id2)) = cluster_id_pair {
-            // 2. Merge clusters
-            let cluster_new_id = self._merge_clusters(cluster_id1, cluster_id2);
+        if let Some(node) = node {
+            // 2. Merge them
+            self._step(node);
+
+            // Return
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn merge(&mut self, cluster_id1: usize, 
+                 cluster_id2: usize) -> Option<usize> {
+        // Merges the given clusters regardless of the distance, returns 
+        // the new cluster id. With opt_1d the clusters must be 
+        // neighbours in the order, None otherwise.
+        if cluster_id1 == cluster_id2 || 
+                !self.cluster_map.contains_key(&cluster_id1) || 
+                !self.cluster_map.contains_key(&cluster_id2) || 
+                self.neighbourhood.as_ref().is_some_and(|neighbourhood| 
+                    !neighbourhood.can_merge(cluster_id1, cluster_id2)) {
+            return None;
+        }
+
+        let distance = self._distance(cluster_id1, cluster_id2)
+            .filter(|distance| !distance.is_nan())
+            .unwrap_or(f64::INFINITY);
+
+        Some(self._step(DistanceHeapNode {
+            distance,
+            cluster_ids: (cluster_id1, cluster_id2),
+        }))
+    }
 
-            // 3. Remove old clusters
-            self.cluster_map.remove(&cluster_id1);
-            self.cluster_map.remove(&cluster_id2);
+    fn _step(&mut self, node: DistanceHeapNode) -> usize {
+        let (cluster_id1, cluster_id2) = node.cluster_ids;
 
-            // 4. Calculate distances to related clusters
-            if self.opt_1d {
-                self._order_map_insert_new(
+        // 1. Merge clusters
+        let cluster_new_id = self._merge_clusters(node);
+
+        // 2. Remove old clusters (they are kept for unstep)
+        let cluster1 = self.cluster_map.remove(&cluster_id1).unwrap();
+        let cluster2 = self.cluster_map.remove(&cluster_id2).unwrap();
+        self.removed_map.insert(cluster_id1, cluster1);
+        self.removed_map.insert(cluster_id2, cluster2);
+
+        // 3. Calculate distances to related clusters
+        if self.neighbourhood.is_some() {
+            self._distance_heap_push_related_neighbourhood(
+                cluster_new_id, cluster_id1, cluster_id2
+            );
+        } else if self.opt_low_memory {
+            self._best_map_update(
+                cluster_new_id, cluster_id1, cluster_id2
+            );
+        } else {
+            self._distance_heap_push_related(cluster_new_id);
+        }
+
+        // 4. Notify the observer
+        if let Some(observer) = self.observer.as_mut() {
+            let info = StepInfo {
+                cluster_ids: node.cluster_ids,
+                distance: node.distance,
+                cluster_new_id,
+                cluster_count: self.cluster_map.len(),
+            };
+            if observer(&info) {
+                self.stopped = true;
+            }
+        }
+
+        cluster_new_id
+    }
+
+    pub fn unstep(&mut self) -> bool {
+        // Reverts the last step
+        if let Some(node) = self.merge_history.pop() {
+            let (cluster_id1, cluster_id2) = node.cluster_ids;
+            let cluster_new_id = self.cluster_id_next - 1;
+
+            // 1. Restore old clusters
+            self.cluster_map.remove(&cluster_new_id);
+            for cluster_id in [cluster_id1, cluster_id2] {
+                let cluster = self.removed_map.remove(&cluster_id).unwrap();
+                self.cluster_map.insert(cluster_id, cluster);
+            }
+
+            // 2. Forget the new cluster, its id will be given again
+            self.locked.remove(&cluster_new_id);
+            self.merge_tree.remove(&cluster_new_id);
+            self.size_map.remove(&cluster_new_id);
+            self.cluster_id_next -= 1;
+            self._distance_map_restore(cluster_new_id);
+
+            // 3. Restore candidates of the old clusters
+            if self.neighbourhood.is_some() {
+                self._distance_heap_restore_neighbourhood(
+                    cluster_new_id, cluster_id1, cluster_id2
+                );
+            } else if self.opt_low_memory {
+                self._best_map_restore(
                     cluster_new_id, cluster_id1, cluster_id2
                 );
-                self._distance_heap_push_related_1d(cluster_new_id);
             } else {
-                self._distance_heap_push_related(cluster_new_id);
+                self._distance_heap_restore(
+                    cluster_new_id, cluster_id1, cluster_id2
+                );
             }
 
             // Return
@@ -265,10 +783,124 @@ impl<T: ClusterTrait + Clone> HierarchicalClusterizer<T> {
         }
     }
 
+    pub fn rollback(&mut self, cluster_count: usize) -> usize {
+        // Reverts steps until there are cluster_count clusters
+        while self.cluster_count() < cluster_count && self.unstep() {}
+        self.cluster_count()
+    }
+
+    fn _distance_heap_retain_valid(&mut self, cluster_new_id: usize) {
+        // Heap nodes of the removed id would be taken for the next 
+        // cluster with the same id
+        self.distance_heap.retain(|Reverse(node)| 
+            node.cluster_ids.0 != cluster_new_id && 
+            node.cluster_ids.1 != cluster_new_id
+        );
+    }
+
+    fn _distance_heap_restore(&mut self, cluster_new_id: usize, 
+                              cluster_id1: usize, cluster_id2: usize) {
+        self._distance_heap_retain_valid(cluster_new_id);
+
+        // Candidates of the old clusters could be dropped by 
+        // _distance_heap_pop while they were merged, so they are 
+        // pushed again (duplicates are harmless)
+        let cluster_ids: Vec<usize> = self.cluster_map.keys().cloned()
+            .collect();
+
+        let pairs: Vec<(usize, usize)> = cluster_ids.iter()
+            .filter(|&&cluster_id| cluster_id != cluster_id1)
+            .map(|&cluster_id| (cluster_id, cluster_id1))
+            .chain(cluster_ids.iter()
+                .filter(|&&cluster_id| 
+                    cluster_id != cluster_id1 && cluster_id != cluster_id2
+                )
+                .map(|&cluster_id| (cluster_id, cluster_id2))
+            )
+            .collect();
+
+        for (cluster_id, cluster_old_id) in pairs {
+            if let Some(node) = self._create_distance_heap_node(
+                        cluster_id.min(cluster_old_id), 
+                        cluster_id.max(cluster_old_id)
+                    ) {
+                self.distance_heap.push(node);
+            }
+        }
+    }
+
+    fn _distance_heap_restore_neighbourhood(
+                &mut self, cluster_new_id: usize, 
+                cluster_id1: usize, cluster_id2: usize) {
+        self._distance_heap_retain_valid(cluster_new_id);
+
+        let pairs = self.neighbourhood.as_mut().unwrap().split(
+            cluster_new_id, cluster_id1, cluster_id2
+        );
+
+        for (cluster_id1, cluster_id2) in pairs {
+            if let Some(node) = self._create_distance_heap_node(
+                        cluster_id1, cluster_id2
+                    ) {
+                self.distance_heap.push(node);
+            }
+        }
+    }
+
+    fn _best_map_restore(&mut self, cluster_new_id: usize, 
+                         cluster_id1: usize, cluster_id2: usize) {
+        // Stale clusters are handled as in _best_map_update, only 
+        // the old clusters are searched over all the clusters
+        self.best_map.remove(&cluster_new_id);
+        self.best_stale.remove(&cluster_new_id);
+
+        let cluster_ids: Vec<usize> = self.best_map.keys().cloned().collect();
+
+        for cluster_id in cluster_ids {
+            let mut best = self.best_map[&cluster_id];
+
+            let node = [cluster_id1, cluster_id2].into_iter()
+                .filter_map(|cluster_old_id| self._create_distance_heap_node(
+                    cluster_id.min(cluster_old_id), 
+                    cluster_id.max(cluster_old_id)
+                ))
+                .map(|Reverse(node)| node)
+                .min();
+
+            if self.best_stale.contains(&cluster_id) || best.is_some_and(
+                        |best| 
+                            best.cluster_ids.0 == cluster_new_id || 
+                            best.cluster_ids.1 == cluster_new_id
+                        ) {
+                match node {
+                    Some(node) if node.distance < best.unwrap().distance => {
+                        best = Some(node);
+                        self.best_stale.remove(&cluster_id);
+                    }
+                    _ => {
+                        self.best_stale.insert(cluster_id);
+                    }
+                }
+            } else if let Some(node) = node {
+                if best.is_none_or(|best| node < best) {
+                    best = Some(node);
+                }
+            }
+
+            self.best_map.insert(cluster_id, best);
+        }
+
+        for cluster_id in [cluster_id1, cluster_id2] {
+            let best = self._find_best(cluster_id);
+            self.best_map.insert(cluster_id, best);
+        }
+    }
+
     pub fn clusterize(&mut self, final_cluster_count: usize) -> usize {
         let mut cluster_count = self.cluster_count();
+        self.stopped = false;
         
-        while final_cluster_count < cluster_count {
+        while final_cluster_count < cluster_count && !self.stopped {
             let success = self.step();
             if success {
                 cluster_count -= 1;
@@ -282,13 +914,21 @@ impl<T: ClusterTrait + Clone> HierarchicalClusterizer<T> {
 }
 
 
+impl<T: ClusterTrait + Clone> Default for HierarchicalClusterizer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
     extern crate rand;
 
     use super::*;
     use test::Bencher;
-    use rand::Rng;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
 
     #[derive(Debug, Clone, PartialEq)]
     struct Cluster {
@@ -332,6 +972,26 @@ mod tests {
         }
     }
 
+    #[derive(Debug, Clone, PartialEq)]
+    struct ClusterNan {
+        x: f64,
+    }
+
+    impl ClusterTrait for ClusterNan {
+        fn merge(cluster1: &Self, cluster2: &Self) -> Self {
+            Self { x: cluster1.x.min(cluster2.x) }
+        }
+
+        fn distance(cluster1: &Self, cluster2: &Self) -> Option<f64> {
+            // Negative values cannot be merged with each other
+            if cluster1.x < 0.0 && cluster2.x < 0.0 {
+                Some(f64::NAN)
+            } else {
+                Some((cluster1.x - cluster2.x).abs())
+            }
+        }
+    }
+
     #[test]
     fn test_2d() {
         let clusters = vec![
@@ -376,6 +1036,365 @@ mod tests {
         clusterizer.initialize(&clusters);
 
         assert_eq!(clusterizer.clusterize(1), 2);
+
+        let members: Vec<Vec<usize>> = clusterizer.get_cluster_ids()
+            .into_iter()
+            .map(|cluster_id| clusterizer.get_members(cluster_id))
+            .collect();
+        assert_eq!(members, vec![vec![3, 4], vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_connectivity() {
+        // 2x2 grid, the diagonal cells are the closest but not adjacent
+        let clusters = vec![
+            Cluster { x: 0.0, y: 0.0, w: 1.0 },
+            Cluster { x: 2.0, y: 2.0, w: 1.0 },
+            Cluster { x: 3.0, y: 3.0, w: 1.0 },
+            Cluster { x: 0.5, y: 0.5, w: 1.0 },
+        ];
+
+        let mut clusterizer = HierarchicalClusterizer::<Cluster>::new();
+        clusterizer.set_connectivity(&[(0, 1), (2, 3), (0, 2), (1, 3)]);
+        clusterizer.initialize(&clusters);
+        clusterizer.step();
+
+        assert_eq!(clusterizer.get_cluster_ids(), vec![0, 2, 4]);
+        assert_eq!(clusterizer.get_members(4), vec![1, 3]);
+
+        // Clusters 0 and 2 are not adjacent but the new cluster is 
+        // adjacent to both
+        assert_eq!(clusterizer.clusterize(1), 1);
+    }
+
+    #[test]
+    fn test_observer() {
+        use std::sync::{Arc, Mutex};
+
+        let clusters = vec![
+            Cluster { x: 0.0, y: 0.0, w: 1.0 },
+            Cluster { x: 10.0, y: 10.0, w: 1.0 },
+            Cluster { x: 11.0, y: 11.0, w: 1.0 },
+            Cluster { x: 4.0, y: 4.0, w: 1.0 },
+            Cluster { x: 1.0, y: 1.0, w: 1.0 },
+        ];
+
+        let steps = Arc::new(Mutex::new(Vec::new()));
+        let steps_observed = steps.clone();
+
+        let mut clusterizer = HierarchicalClusterizer::<Cluster>::new();
+        clusterizer.set_observer(Some(Box::new(move |info| {
+            steps_observed.lock().unwrap().push(*info);
+            info.cluster_count <= 3
+        })));
+        clusterizer.initialize(&clusters);
+
+        assert_eq!(clusterizer.clusterize(1), 3);
+        assert!(clusterizer.is_stopped());
+        assert_eq!(*steps.lock().unwrap(), vec![
+            StepInfo { 
+                cluster_ids: (0, 4), distance: 2.0, 
+                cluster_new_id: 5, cluster_count: 4,
+            },
+            StepInfo { 
+                cluster_ids: (1, 2), distance: 2.0, 
+                cluster_new_id: 6, cluster_count: 3,
+            },
+        ]);
+    }
+
+    fn assert_unstep(clusterizer: &mut HierarchicalClusterizer<Cluster>, 
+                     clusters: &[Cluster]) {
+        // Steps after rollback must repeat the steps before
+        clusterizer.initialize(clusters);
+
+        let mut history = vec![clusterizer.get_clusters()];
+        while clusterizer.step() {
+            history.push(clusterizer.get_clusters());
+        }
+
+        assert_eq!(clusterizer.rollback(clusters.len()), clusters.len());
+        assert_eq!(clusterizer.get_clusters(), history[0]);
+
+        for idx in 1..history.len() {
+            assert!(clusterizer.step());
+            assert_eq!(clusterizer.get_clusters(), history[idx]);
+            if idx % 2 == 0 {
+                assert!(clusterizer.unstep());
+                assert_eq!(clusterizer.get_clusters(), history[idx - 1]);
+                assert!(clusterizer.step());
+            }
+        }
+
+        assert!(!clusterizer.step());
+    }
+
+    #[test]
+    fn test_unstep() {
+        let size = 30;
+        let mut rng = StdRng::seed_from_u64(0);
+        let clusters: Vec<Cluster> = (0..size)
+            .map(|_| Cluster { x: rng.gen(), y: rng.gen(), w: 1.0 })
+            .collect();
+
+        let mut clusterizer = HierarchicalClusterizer::<Cluster>::new();
+        assert_unstep(&mut clusterizer, &clusters);
+
+        clusterizer.set_low_memory_opt(true);
+        assert_unstep(&mut clusterizer, &clusters);
+
+        clusterizer.set_1d_opt(true);
+        assert_unstep(&mut clusterizer, &clusters);
+
+        clusterizer.set_neighbourhood(Box::new(GraphNeighbourhood::grid(5, 6)));
+        assert_unstep(&mut clusterizer, &clusters);
+
+        let distances: Vec<f64> = (0..size)
+            .flat_map(|i| ((i + 1)..size).map(move |j| (i, j)))
+            .map(|(i, j)| Cluster::distance(&clusters[i], &clusters[j])
+                .unwrap().sqrt())
+            .collect();
+
+        let mut clusterizer = HierarchicalClusterizer::<Cluster>::new();
+        clusterizer.set_linkage(Linkage::Ward, &distances);
+        assert_unstep(&mut clusterizer, &clusters);
+    }
+
+    #[test]
+    fn test_merge() {
+        let clusters = vec![
+            Cluster1d { x: 0.0, w: 1.0 },
+            Cluster1d { x: 0.01, w: 1.0 },
+            Cluster1d { x: 0.04, w: 1.0 },
+        ];
+
+        let mut clusterizer = HierarchicalClusterizer::<Cluster1d>::new();
+        clusterizer.initialize(&clusters);
+
+        assert_eq!(clusterizer.merge(0, 2), Some(3));
+        assert_eq!(clusterizer.merge(0, 1), None);
+        assert_eq!(clusterizer.get_members(3), vec![0, 2]);
+        assert_eq!(clusterizer.get_clusters()[1], Cluster1d { x: 0.02, w: 2.0 });
+
+        assert!(clusterizer.step());
+        assert_eq!(clusterizer.cluster_count(), 1);
+
+        // With opt_1d only neighbours in the order are merged
+        let mut clusterizer = HierarchicalClusterizer::<Cluster1d>::new();
+        clusterizer.set_1d_opt(true);
+        clusterizer.initialize(&clusters);
+
+        assert_eq!(clusterizer.merge(0, 2), None);
+        assert_eq!(clusterizer.merge(1, 0), None);
+        assert_eq!(clusterizer.merge(0, 1), Some(3));
+        assert_eq!(clusterizer.merge(3, 2), Some(4));
+        assert_eq!(clusterizer.cluster_count(), 1);
+    }
+
+    #[test]
+    fn test_locked() {
+        let clusters = vec![
+            Cluster1d { x: 0.0, w: 1.0 },
+            Cluster1d { x: 0.01, w: 1.0 },
+            Cluster1d { x: 0.02, w: 1.0 },
+            Cluster1d { x: 0.03, w: 1.0 },
+        ];
+
+        for low_memory in [false, true] {
+            let mut clusterizer = HierarchicalClusterizer::<Cluster1d>::new();
+            clusterizer.set_low_memory_opt(low_memory);
+            clusterizer.initialize(&clusters);
+
+            assert!(clusterizer.set_locked(0, true));
+            assert!(!clusterizer.set_locked(9, true));
+            assert_eq!(clusterizer.clusterize(1), 2);
+            assert_eq!(clusterizer.get_members(5), vec![1, 2, 3]);
+
+            // Forced merges ignore the lock
+            assert_eq!(clusterizer.merge(0, 5), Some(6));
+            assert!(clusterizer.unstep());
+            assert!(clusterizer.is_locked(0));
+
+            assert!(clusterizer.set_locked(0, false));
+            assert_eq!(clusterizer.clusterize(1), 1);
+        }
+
+        let mut clusterizer = HierarchicalClusterizer::<Cluster1d>::new();
+        clusterizer.set_1d_opt(true);
+        clusterizer.initialize(&clusters);
+        clusterizer.set_locked(1, true);
+        assert_eq!(clusterizer.clusterize(1), 3);
+        assert_eq!(clusterizer.get_members(4), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_nan_distance() {
+        // Merging clusters of zero weight gives x = 0/0, so the distance 
+        // to the merged cluster is NaN
+        let clusters = vec![
+            Cluster1d { x: 0.0, w: 0.0 },
+            Cluster1d { x: 0.001, w: 0.0 },
+            Cluster1d { x: 0.03, w: 1.0 },
+        ];
+
+        let mut clusterizer = HierarchicalClusterizer::<Cluster1d>::new();
+        clusterizer.initialize(&clusters);
+
+        assert_eq!(clusterizer.clusterize(1), 2);
+        assert_eq!(clusterizer.get_clusters()[0], 
+                   Cluster1d { x: 0.03, w: 1.0 });
+    }
+
+    #[test]
+    fn test_nan_distance_never_merged() {
+        // NaN is the smallest distance in total_cmp order but the pair 
+        // is never picked, in all modes
+        let clusters = vec![
+            ClusterNan { x: -1.0 },
+            ClusterNan { x: -2.0 },
+            ClusterNan { x: 5.0 },
+            ClusterNan { x: 6.0 },
+        ];
+
+        for (opt_1d, opt_low_memory) in [(false, false), (true, false), 
+                                          (false, true)] {
+            let mut clusterizer = HierarchicalClusterizer::<ClusterNan>::new();
+            clusterizer.set_1d_opt(opt_1d);
+            clusterizer.set_low_memory_opt(opt_low_memory);
+            clusterizer.initialize(&clusters);
+
+            assert_eq!(clusterizer.clusterize(1), 2);
+            for cluster_id in clusterizer.get_cluster_ids() {
+                let members = clusterizer.get_members(cluster_id);
+                assert!(members.contains(&0) != members.contains(&1));
+            }
+            assert!(!clusterizer.step());
+
+            // A forced merge records an infinite distance
+            let cluster_ids = clusterizer.get_cluster_ids();
+            let mut distances = Vec::new();
+            clusterizer.set_observer(Some(Box::new(move |info: &StepInfo| {
+                distances.push(info.distance);
+                assert_eq!(distances, vec![f64::INFINITY]);
+                false
+            })));
+            assert!(clusterizer.merge(cluster_ids[0], cluster_ids[1])
+                .is_some());
+        }
+    }
+
+    #[test]
+    fn test_distance_ties() {
+        let clusters = vec![
+            Cluster1d { x: 0.0, w: 1.0 },
+            Cluster1d { x: 0.03125, w: 1.0 },
+            Cluster1d { x: 0.0625, w: 1.0 },
+            Cluster1d { x: 0.09375, w: 1.0 },
+        ];
+
+        let mut clusterizer = HierarchicalClusterizer::<Cluster1d>::new();
+        clusterizer.set_1d_opt(true);
+        clusterizer.initialize(&clusters);
+        clusterizer.step();
+
+        // All distances are equal, so the pair with the smallest ids wins
+        assert_eq!(clusterizer.get_clusters()[0], 
+                   Cluster1d { x: 0.0625, w: 1.0 });
+        assert_eq!(clusterizer.get_clusters()[2], 
+                   Cluster1d { x: 0.015625, w: 2.0 });
+    }
+
+    #[test]
+    fn test_low_memory() {
+        let size = 50;
+        let mut rng = StdRng::seed_from_u64(0);
+        let clusters: Vec<Cluster> = (0..size)
+            .map(|_| Cluster { x: rng.gen(), y: rng.gen(), w: 1.0 })
+            .collect();
+
+        let mut clusterizer = HierarchicalClusterizer::<Cluster>::new();
+        clusterizer.initialize(&clusters);
+
+        let mut clusterizer_lm = HierarchicalClusterizer::<Cluster>::new();
+        clusterizer_lm.set_low_memory_opt(true);
+        clusterizer_lm.initialize(&clusters);
+
+        for _ in 0..(size - 1) {
+            assert!(clusterizer.step());
+            assert!(clusterizer_lm.step());
+            assert_eq!(clusterizer.get_clusters(), 
+                       clusterizer_lm.get_clusters());
+        }
+
+        assert!(!clusterizer_lm.step());
+    }
+
+    #[test]
+    fn test_low_memory_ties() {
+        // Points of a grid have equal distances, stale clusters must 
+        // resolve ties the same way as distance_heap
+        let clusters: Vec<Cluster> = (0..36)
+            .map(|i| Cluster { x: (i % 6) as f64, y: (i / 6) as f64, w: 1.0 })
+            .collect();
+
+        let mut clusterizer = HierarchicalClusterizer::<Cluster>::new();
+        clusterizer.initialize(&clusters);
+
+        let mut clusterizer_lm = HierarchicalClusterizer::<Cluster>::new();
+        clusterizer_lm.set_low_memory_opt(true);
+        clusterizer_lm.initialize(&clusters);
+
+        while clusterizer.step() {
+            assert!(clusterizer_lm.step());
+            assert_eq!(clusterizer.get_cluster_ids(), 
+                       clusterizer_lm.get_cluster_ids());
+            assert_eq!(clusterizer.get_clusters(), 
+                       clusterizer_lm.get_clusters());
+        }
+
+        assert!(!clusterizer_lm.step());
+    }
+
+    fn assert_linkage_matrix(distances: &[f64], linkage: Linkage, 
+                             expected: &[[f64; 4]]) {
+        let matrix = linkage_matrix(distances, linkage);
+        assert_eq!(matrix.len(), expected.len());
+        for (row, row_expected) in matrix.iter().zip(expected) {
+            for (x, x_expected) in row.iter().zip(row_expected) {
+                assert!((x - x_expected).abs() < 1e-12, 
+                        "{:?} != {:?}", matrix, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_linkage() {
+        // Points 0, 1, 3, 7 on a line
+        let distances = [1.0, 3.0, 7.0, 2.0, 6.0, 4.0];
+
+        assert_linkage_matrix(&distances, Linkage::Single, &[
+            [0.0, 1.0, 1.0, 2.0],
+            [2.0, 4.0, 2.0, 3.0],
+            [3.0, 5.0, 4.0, 4.0],
+        ]);
+
+        assert_linkage_matrix(&distances, Linkage::Complete, &[
+            [0.0, 1.0, 1.0, 2.0],
+            [2.0, 4.0, 3.0, 3.0],
+            [3.0, 5.0, 7.0, 4.0],
+        ]);
+
+        assert_linkage_matrix(&distances, Linkage::Average, &[
+            [0.0, 1.0, 1.0, 2.0],
+            [2.0, 4.0, 2.5, 3.0],
+            [3.0, 5.0, 17.0 / 3.0, 4.0],
+        ]);
+
+        assert_linkage_matrix(&distances, Linkage::Ward, &[
+            [0.0, 1.0, 1.0, 2.0],
+            [2.0, 4.0, (25.0f64 / 3.0).sqrt(), 3.0],
+            [3.0, 5.0, 1.5f64.sqrt() * 17.0 / 3.0, 4.0],
+        ]);
     }
 
     #[bench]
@@ -392,6 +1411,39 @@ mod tests {
         });
     }
 
+    #[bench]
+    fn bench_2d_low_memory(bencher: &mut Bencher) {
+        let size = 100;
+        let mut rng = rand::thread_rng();
+        let clusters: Vec<Cluster> = (0..size)
+            .map(|_| Cluster { x: rng.gen(), y: rng.gen(), w: 1.0 })
+            .collect();
+        bencher.iter(|| {
+            let mut clusterizer = HierarchicalClusterizer::<Cluster>::new();
+            clusterizer.set_low_memory_opt(true);
+            clusterizer.initialize(&clusters);
+            clusterizer.clusterize(1);
+        });
+    }
+
+    #[bench]
+    #[cfg_attr(debug_assertions, ignore)]
+    fn bench_2d_low_memory_5k(bencher: &mut Bencher) {
+        // As many clusters as categories of a large categorial feature, 
+        // too slow to be run once by the unoptimized tests
+        let size = 5_000;
+        let mut rng = rand::thread_rng();
+        let clusters: Vec<Cluster> = (0..size)
+            .map(|_| Cluster { x: rng.gen(), y: rng.gen(), w: 1.0 })
+            .collect();
+        bencher.iter(|| {
+            let mut clusterizer = HierarchicalClusterizer::<Cluster>::new();
+            clusterizer.set_low_memory_opt(true);
+            clusterizer.initialize(&clusters);
+            clusterizer.clusterize(1);
+        });
+    }
+
     #[bench]
     fn bench_1d(bencher: &mut Bencher) {
         let size = 100;
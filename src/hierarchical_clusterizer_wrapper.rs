@@ -0,0 +1,36 @@
+use crate::hierarchical_clusterizer::{Linkage, condensed_size, linkage_matrix};
+
+
+#[export_name="hc_linkage_matrix"]
+extern "C" fn linkage_matrix_wrapper(distances_size: usize, 
+                                     distances: *const f64, 
+                                     linkage: usize, 
+                                     matrix_array: *mut [f64; 4]) -> isize {
+    // Returns the number of rows written, fewer than the number of points 
+    // minus one if NaN distances block merges. -1 for an unknown linkage 
+    // or a distances size that is not of a condensed matrix.
+    let linkage = match linkage {
+        0 => Linkage::Single,
+        1 => Linkage::Complete,
+        2 => Linkage::Average,
+        3 => Linkage::Ward,
+        _ => return -1,
+    };
+    if condensed_size(distances_size).is_none() {
+        return -1;
+    }
+
+    let mut distances_buff: Vec<f64> = vec![0.0; distances_size];
+
+    unsafe {
+        distances.copy_to(distances_buff.as_mut_ptr(), distances_size);
+    }
+
+    // The matrix has at most one row less than the number of points
+    let matrix = linkage_matrix(&distances_buff, linkage);
+
+    unsafe {
+        matrix.as_ptr().copy_to(matrix_array, matrix.len());
+    }
+    matrix.len() as isize
+}
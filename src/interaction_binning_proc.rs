@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+
+use crate::hierarchical_clusterizer::{ClusterTrait, HierarchicalClusterizer};
+use crate::neighbourhood::GraphNeighbourhood;
+use crate::woe_binning_proc::{WoeBinningCluster, calc_iv, calc_woe};
+
+
+#[derive(Debug, Clone, PartialEq)]
+struct RegionCluster {
+    stat: WoeBinningCluster,
+    // Bounding box of the cells: rows (x pre-bins) and columns
+    // (y pre-bins), inclusive
+    rows: (usize, usize),
+    cols: (usize, usize),
+    rectangular: bool,
+}
+
+
+impl ClusterTrait for RegionCluster {
+    fn merge(cluster1: &Self, cluster2: &Self) -> Self {
+        // Empty cells take nothing from the other cluster, so 0 / 0
+        // smooth never appears
+        let mut stat = match (cluster1.stat.n, cluster2.stat.n) {
+            (0, _) => cluster2.stat.clone(),
+            (_, 0) => cluster1.stat.clone(),
+            _ => WoeBinningCluster::merge(&cluster1.stat, &cluster2.stat),
+        };
+        stat.size = cluster1.stat.size + cluster2.stat.size;
+        stat.value_min = cluster1.stat.value_min.min(cluster2.stat.value_min);
+
+        Self {
+            stat,
+            rows: (cluster1.rows.0.min(cluster2.rows.0),
+                   cluster1.rows.1.max(cluster2.rows.1)),
+            cols: (cluster1.cols.0.min(cluster2.cols.0),
+                   cluster1.cols.1.max(cluster2.cols.1)),
+            rectangular: cluster1.rectangular,
+        }
+    }
+
+    fn distance(cluster1: &Self, cluster2: &Self) -> Option<f64> {
+        if cluster1.rectangular {
+            // The union must fill its bounding box
+            let merged = Self::merge(cluster1, cluster2);
+            let area = (merged.rows.1 - merged.rows.0 + 1) *
+                       (merged.cols.1 - merged.cols.0 + 1);
+            if area != merged.stat.size {
+                return None;
+            }
+        }
+
+        // Empty cells are merged first
+        if cluster1.stat.n == 0 || cluster2.stat.n == 0 {
+            return Some(f64::NEG_INFINITY);
+        }
+
+        WoeBinningCluster::distance(&cluster1.stat, &cluster2.stat)
+    }
+}
+
+
+pub struct InteractionBinningProc {
+    clusterizer: HierarchicalClusterizer<RegionCluster>,
+    desirable_bins_num: usize,
+    smooth: f64,
+    max_prebins: usize,
+    rectangular: bool,
+    // Smallest values of the pre-bins of each axis
+    x_cuts: Vec<usize>,
+    y_cuts: Vec<usize>,
+    regions: Option<Vec<(usize, RegionCluster)>>,
+}
+
+
+impl InteractionBinningProc {
+    pub fn new(desirable_bins_num: usize, smooth: f64) -> Self {
+        Self {
+            clusterizer: HierarchicalClusterizer::new(),
+            desirable_bins_num,
+            smooth,
+            max_prebins: 10,
+            rectangular: false,
+            x_cuts: Vec::new(),
+            y_cuts: Vec::new(),
+            regions: None,
+        }
+    }
+
+    pub fn set_max_prebins(&mut self, max_prebins: usize) {
+        // Each axis is split into at most max_prebins pre-bins of about
+        // equal number of rows, the grid has up to max_prebins^2 cells
+        assert!(max_prebins > 0);
+        self.max_prebins = max_prebins;
+    }
+
+    pub fn set_rectangular(&mut self, rectangular: bool) {
+        // Regions are rectangles of cells instead of any connected
+        // shapes, the binning can stop above the desirable number of
+        // regions if no pair of them makes a rectangle
+        self.rectangular = rectangular;
+    }
+
+    pub fn process(&mut self, x: &[usize], y: &[usize], target: &[bool]) {
+        // Both series are numeric (values are ordered)
+        assert_eq!(self.regions, None);
+        assert_eq!(x.len(), target.len());
+        assert_eq!(y.len(), target.len());
+
+        self.x_cuts = Self::_axis_cuts(x, self.max_prebins);
+        self.y_cuts = Self::_axis_cuts(y, self.max_prebins);
+
+        let (rows, cols) = (self.x_cuts.len(), self.y_cuts.len());
+
+        // Counts of target 1 and 0 in cells, numbered row by row
+        let mut counts = vec![(0, 0); rows * cols];
+        for idx in 0..target.len() {
+            let cell = self._get_cell(x[idx], y[idx]);
+            if target[idx] {
+                counts[cell].0 += 1;
+            } else {
+                counts[cell].1 += 1;
+            }
+        }
+
+        let c1: usize = counts.iter().map(|c| c.0).sum();
+        let c0: usize = counts.iter().map(|c| c.1).sum();
+
+        // Laplace smoothing as in WoeBinningProc, empty cells have no
+        // statistics at all
+        let cells: Vec<RegionCluster> = counts.iter().enumerate()
+            .map(|(cell, &(n1, n0))| {
+                let is_empty = n1 + n0 == 0;
+                RegionCluster {
+                    stat: WoeBinningCluster {
+                        n: n1 + n0,
+                        n1,
+                        n0,
+                        p1: if is_empty { 0.0 }
+                            else { (n1 + 1) as f64 / (c1 + 2) as f64 },
+                        p0: if is_empty { 0.0 }
+                            else { (n0 + 1) as f64 / (c0 + 2) as f64 },
+                        size: 1,
+                        value_min: cell,
+                        smooth: self.smooth,
+                        cannot_link: Vec::new(),
+                    },
+                    rows: (cell / cols, cell / cols),
+                    cols: (cell % cols, cell % cols),
+                    rectangular: self.rectangular,
+                }
+            })
+            .collect();
+
+        self.clusterizer.set_neighbourhood(
+            Box::new(GraphNeighbourhood::grid(rows, cols))
+        );
+        self.clusterizer.initialize(&cells);
+        self.clusterizer.clusterize(self.desirable_bins_num);
+        self.regions = Some(self._sort_regions());
+    }
+
+    pub fn set_bins_num(&mut self, bins_num: usize) {
+        // Merges are reverted or continued without processing again
+        assert!(self.is_done());
+
+        self.desirable_bins_num = bins_num;
+        self.clusterizer.rollback(bins_num);
+        self.clusterizer.clusterize(bins_num);
+        self.regions = Some(self._sort_regions());
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.regions.is_some()
+    }
+
+    pub fn get_x_cuts(&self) -> &[usize] {
+        &self.x_cuts
+    }
+
+    pub fn get_y_cuts(&self) -> &[usize] {
+        &self.y_cuts
+    }
+
+    pub fn get_regions_num(&self) -> Option<usize> {
+        self.regions.as_ref().map(|r| r.len())
+    }
+
+    pub fn get_region_cells(&self, idx: usize) -> Option<Vec<(usize, usize)>> {
+        // Cells of a region as (row, column), that is indices of
+        // pre-bins of x and y
+        let cols = self.y_cuts.len();
+        self.regions.as_ref().map(|regions| {
+            let mut cells = self.clusterizer.get_members(regions[idx].0);
+            cells.sort();
+            cells.into_iter().map(|cell| (cell / cols, cell % cols)).collect()
+        })
+    }
+
+    pub fn get_woe_array(&self) -> Option<Vec<f64>> {
+        // Zero for regions of empty cells only
+        self.regions.as_ref().map(|v|
+            v.iter().map(|(_, r)| if r.stat.n == 0 { 0.0 }
+                                  else { calc_woe(r.stat.p0, r.stat.p1) })
+                .collect()
+        )
+    }
+
+    pub fn get_iv_array(&self) -> Option<Vec<f64>> {
+        self.regions.as_ref().map(|v|
+            v.iter().map(|(_, r)| if r.stat.n == 0 { 0.0 }
+                                  else { calc_iv(r.stat.p0, r.stat.p1) })
+                .collect()
+        )
+    }
+
+    pub fn get_iv_total(&self) -> Option<f64> {
+        self.get_iv_array().map(|v| v.iter().sum())
+    }
+
+    pub fn get_count_array(&self) -> Option<Vec<usize>> {
+        self.regions.as_ref().map(|v|
+            v.iter().map(|(_, r)| r.stat.n).collect()
+        )
+    }
+
+    pub fn transform(&self, x: &[usize], y: &[usize]) -> Vec<f64> {
+        // WOE of the regions of pairs, values between pre-bins go to
+        // the left one on each axis (less than all go to the first)
+        assert!(self.is_done());
+        assert_eq!(x.len(), y.len());
+
+        let woe_array = self.get_woe_array().unwrap();
+        let mut cell_woe = vec![0.0; self.x_cuts.len() * self.y_cuts.len()];
+        for (idx, woe) in woe_array.into_iter().enumerate() {
+            for (row, col) in self.get_region_cells(idx).unwrap() {
+                cell_woe[row * self.y_cuts.len() + col] = woe;
+            }
+        }
+
+        x.iter().zip(y)
+            .map(|(&x, &y)| cell_woe[self._get_cell(x, y)])
+            .collect()
+    }
+
+    fn _get_cell(&self, x: usize, y: usize) -> usize {
+        let row = self.x_cuts.partition_point(|&cut| cut <= x).max(1) - 1;
+        let col = self.y_cuts.partition_point(|&cut| cut <= y).max(1) - 1;
+        row * self.y_cuts.len() + col
+    }
+
+    fn _axis_cuts(series: &[usize], max_prebins: usize) -> Vec<usize> {
+        // Smallest values of pre-bins with about equal number of rows,
+        // a value never splits between pre-bins
+        let mut counts = BTreeMap::new();
+        for &value in series.iter() {
+            *counts.entry(value).or_insert(0) += 1;
+        }
+
+        let mut cuts = Vec::new();
+        let mut acc = 0;
+        let mut start = true;
+
+        for (&value, &count) in counts.iter() {
+            if start {
+                cuts.push(value);
+                start = false;
+            }
+            acc += count;
+            if acc * max_prebins >= series.len() * cuts.len() {
+                start = true;
+            }
+        }
+
+        if cuts.is_empty() {
+            cuts.push(0);
+        }
+
+        cuts
+    }
+
+    fn _sort_regions(&self) -> Vec<(usize, RegionCluster)> {
+        // Ascending by WOE, ties are resolved by the first cell
+        let mut regions: Vec<(usize, RegionCluster)> =
+            self.clusterizer.get_cluster_ids().into_iter()
+                .zip(self.clusterizer.get_clusters())
+                .collect();
+        let woe = |r: &RegionCluster| if r.stat.n == 0 { 0.0 }
+                                      else { calc_woe(r.stat.p0, r.stat.p1) };
+        regions.sort_by(|a, b| woe(&a.1).total_cmp(&woe(&b.1))
+            .then(a.1.stat.value_min.cmp(&b.1.stat.value_min)));
+        regions
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn create_dataset(size: usize) -> (Vec<usize>, Vec<usize>, Vec<bool>) {
+        // All the pairs of 0..100 for 10 000 rows, target 1 is likely 
+        // only if both x and y are large
+        let mut rng = StdRng::seed_from_u64(0);
+        let x: Vec<usize> = (0..size).map(|i| i % 100).collect();
+        let y: Vec<usize> = (0..size).map(|i| i / 100 % 100).collect();
+        let target = x.iter().zip(&y)
+            .map(|(&x, &y)| rng.gen::<f64>() <
+                            if x >= 50 && y >= 50 { 0.8 } else { 0.1 })
+            .collect();
+        (x, y, target)
+    }
+
+    #[test]
+    fn test_process() {
+        let (x, y, target) = create_dataset(10_000);
+
+        let mut ibp = InteractionBinningProc::new(2, 1.0);
+        ibp.process(&x, &y, &target);
+
+        assert_eq!(ibp.get_x_cuts().len(), 10);
+        assert_eq!(ibp.get_x_cuts()[5], 50);
+        assert_eq!(ibp.get_regions_num(), Some(2));
+        assert_eq!(ibp.get_count_array().unwrap().iter().sum::<usize>(),
+                   10_000);
+
+        // The risky quarter is a region of its own
+        let cells = ibp.get_region_cells(0).unwrap();
+        assert_eq!(cells.len(), 25);
+        assert!(cells.iter().all(|&(row, col)| row >= 5 && col >= 5));
+
+        let woe = ibp.get_woe_array().unwrap();
+        assert!(woe[0] < 0.0 && woe[1] > 0.0);
+        assert_eq!(ibp.transform(&[99, 0, 60], &[99, 99, 10]),
+                   vec![woe[0], woe[1], woe[1]]);
+
+        ibp.set_bins_num(5);
+        assert_eq!(ibp.get_regions_num(), Some(5));
+        ibp.set_bins_num(2);
+        assert_eq!(ibp.get_woe_array().unwrap(), woe);
+    }
+
+    #[test]
+    fn test_rectangular() {
+        let (x, y, target) = create_dataset(10_000);
+
+        let mut ibp = InteractionBinningProc::new(3, 1.0);
+        ibp.set_rectangular(true);
+        ibp.process(&x, &y, &target);
+
+        for idx in 0..ibp.get_regions_num().unwrap() {
+            let cells = ibp.get_region_cells(idx).unwrap();
+            let rows = cells.iter().map(|c| c.0).max().unwrap() -
+                       cells.iter().map(|c| c.0).min().unwrap() + 1;
+            let cols = cells.iter().map(|c| c.1).max().unwrap() -
+                       cells.iter().map(|c| c.1).min().unwrap() + 1;
+            assert_eq!(rows * cols, cells.len());
+        }
+    }
+
+    #[test]
+    fn test_empty_cells() {
+        // Points on the diagonal only, off-diagonal cells are empty
+        let x: Vec<usize> = (0..1000).map(|i| i % 10).collect();
+        let target: Vec<bool> = x.iter().map(|&v| v % 3 == 0).collect();
+
+        let mut ibp = InteractionBinningProc::new(3, 1.0);
+        ibp.process(&x, &x, &target);
+
+        assert_eq!(ibp.get_regions_num(), Some(3));
+        assert!(ibp.get_woe_array().unwrap().iter().all(|w| w.is_finite()));
+        assert!(ibp.get_count_array().unwrap().iter().all(|&n| n > 0));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::interaction_binning_proc::InteractionBinningProc;
+
+
+#[repr(C)]
+pub(crate) struct RegionInfo {
+    woe: f64,
+    iv: f64,
+    count: usize,
+    cells: usize,
+}
+
+
+pub(crate) struct InteractionBinningProcWrapper {
+    pub(crate) ibp: InteractionBinningProc,
+}
+
+
+impl InteractionBinningProcWrapper {
+    #[export_name="ibp_new"]
+    pub extern "C" fn new(desirable_bins_num: usize, smooth: f64) -> Box<Self> {
+        Box::new(Self {
+            ibp: InteractionBinningProc::new(desirable_bins_num, smooth)
+        })
+    }
+
+    #[export_name="ibp_set_max_prebins"]
+    pub extern "C" fn set_max_prebins(&mut self, max_prebins: usize) {
+        self.ibp.set_max_prebins(max_prebins);
+    }
+
+    #[export_name="ibp_set_rectangular"]
+    pub extern "C" fn set_rectangular(&mut self, rectangular: bool) {
+        self.ibp.set_rectangular(rectangular);
+    }
+
+    #[export_name="ibp_process"]
+    pub extern "C" fn process(&mut self, size: usize, x: *const usize,
+                              y: *const usize, target: *const bool) {
+        let (x_buff, y_buff, target_buff) = unsafe {
+            (Self::_prepare_array(size, x), Self::_prepare_array(size, y),
+             Self::_prepare_target(size, target))
+        };
+        self.ibp.process(&x_buff, &y_buff, &target_buff);
+    }
+
+    #[export_name="ibp_set_bins_num"]
+    pub extern "C" fn set_bins_num(&mut self, bins_num: usize) {
+        self.ibp.set_bins_num(bins_num);
+    }
+
+    #[export_name="ibp_get_regions_num"]
+    pub extern "C" fn get_regions_num(&self) -> usize {
+        self.ibp.get_regions_num().unwrap()
+    }
+
+    #[export_name="ibp_get_x_cuts_num"]
+    pub extern "C" fn get_x_cuts_num(&self) -> usize {
+        self.ibp.get_x_cuts().len()
+    }
+
+    #[export_name="ibp_get_x_cuts"]
+    pub extern "C" fn get_x_cuts(&self, size: usize, cuts_array: *mut usize) {
+        unsafe {
+            self.ibp.get_x_cuts().as_ptr().copy_to(cuts_array, size);
+        }
+    }
+
+    #[export_name="ibp_get_y_cuts_num"]
+    pub extern "C" fn get_y_cuts_num(&self) -> usize {
+        self.ibp.get_y_cuts().len()
+    }
+
+    #[export_name="ibp_get_y_cuts"]
+    pub extern "C" fn get_y_cuts(&self, size: usize, cuts_array: *mut usize) {
+        unsafe {
+            self.ibp.get_y_cuts().as_ptr().copy_to(cuts_array, size);
+        }
+    }
+
+    #[export_name="ibp_get_regions_info"]
+    pub extern "C" fn get_regions_info(&self, regions_num: usize,
+                                       region_info_array: *mut RegionInfo) {
+        let woe_vec = self.ibp.get_woe_array().unwrap();
+        let iv_vec = self.ibp.get_iv_array().unwrap();
+        let count_vec = self.ibp.get_count_array().unwrap();
+
+        let regions_info = (0..regions_num).map(|i| RegionInfo {
+            woe: woe_vec[i], iv: iv_vec[i], count: count_vec[i],
+            cells: self.ibp.get_region_cells(i).unwrap().len(),
+        }).collect::<Vec<RegionInfo>>();
+
+        unsafe {
+            regions_info.as_ptr().copy_to(region_info_array, regions_num);
+        }
+    }
+
+    #[export_name="ibp_get_region_cells"]
+    pub extern "C" fn get_region_cells(&self, region_idx: usize, size: usize,
+                                       rows_array: *mut usize,
+                                       cols_array: *mut usize) {
+        // Rows are pre-bins of x, columns are pre-bins of y
+        let (rows, cols): (Vec<usize>, Vec<usize>) =
+            self.ibp.get_region_cells(region_idx).unwrap().into_iter().unzip();
+        unsafe {
+            rows.as_ptr().copy_to(rows_array, size);
+            cols.as_ptr().copy_to(cols_array, size);
+        }
+    }
+
+    #[export_name="ibp_transform"]
+    pub extern "C" fn transform(&self, size: usize, x: *const usize,
+                                y: *const usize, woe_array: *mut f64) {
+        let (x_buff, y_buff) = unsafe {
+            (Self::_prepare_array(size, x), Self::_prepare_array(size, y))
+        };
+        let woe = self.ibp.transform(&x_buff, &y_buff);
+        unsafe {
+            woe.as_ptr().copy_to(woe_array, size);
+        }
+    }
+
+    unsafe fn _prepare_array(size: usize, values: *const usize) ->
+                Vec<usize> {
+        let mut values_buff: Vec<usize> = vec![0; size];
+
+        unsafe {
+            values.copy_to(values_buff.as_mut_ptr(), size);
+        }
+
+        values_buff
+    }
+
+    unsafe fn _prepare_target(size: usize, target: *const bool) ->
+                Vec<bool> {
+        let mut target_buff: Vec<bool> = vec![false; size];
+
+        unsafe {
+            target.copy_to(target_buff.as_mut_ptr(), size);
+        }
+
+        target_buff
+    }
+}
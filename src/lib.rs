@@ -1,11 +1,43 @@
-#![feature(test)]
+// libtest is for benchmarks only, the library itself does not link it
+#![cfg_attr(test, feature(test))]
+#[cfg(test)]
 extern crate test;
 
+#[cfg(feature = "arrow")]
+pub mod arrow_input;
 pub mod hierarchical_clusterizer;
+pub mod hierarchical_clusterizer_wrapper;
+pub mod interaction_binning_proc;
+pub mod interaction_binning_proc_wrapper;
+pub mod logistic_regression;
+pub mod logistic_regression_wrapper;
+pub mod metrics;
+pub mod neighbourhood;
+pub mod optimal_binning;
+#[cfg(feature = "python")]
+pub mod python_module;
+pub mod pmml_export;
+pub mod pmml_export_wrapper;
+pub mod scorecard;
+pub mod scorecard_wrapper;
+pub mod sql_export;
+pub mod sql_export_wrapper;
 pub mod woe_binning_proc;
 pub mod woe_binning_proc_wrapper;
+#[cfg(feature = "wasm")]
+pub mod wasm_module;
 
+#[cfg(feature = "arrow")]
+pub use crate::arrow_input::*;
 pub use crate::hierarchical_clusterizer::*;
+pub use crate::interaction_binning_proc::*;
+pub use crate::logistic_regression::*;
+pub use crate::metrics::*;
+pub use crate::neighbourhood::*;
+pub use crate::optimal_binning::*;
+pub use crate::pmml_export::*;
+pub use crate::scorecard::*;
+pub use crate::sql_export::*;
 pub use crate::woe_binning_proc::*;
 
 
@@ -0,0 +1,317 @@
+use crate::woe_binning_proc::WoeBinningProc;
+
+
+pub fn erfc(x: f64) -> f64 {
+    // Complementary error function by Chebyshev approximation,
+    // relative error is less than 1.2e-7
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t * (-z * z - 1.26551223 + t * (1.00002368 + t * (0.37409196 +
+        t * (0.09678418 + t * (-0.18628806 + t * (0.27886807 +
+        t * (-1.13520398 + t * (1.48851587 + t * (-0.82215223 +
+        t * 0.17087277))))))))).exp();
+    if x >= 0.0 { r } else { 2.0 - r }
+}
+
+
+fn invert(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    // Gauss-Jordan elimination with partial pivoting, None if
+    // the matrix is singular
+    let size = matrix.len();
+    let mut left = matrix.to_vec();
+    let mut right: Vec<Vec<f64>> = (0..size)
+        .map(|i| (0..size).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+
+    for col in 0..size {
+        let pivot = (col..size)
+            .max_by(|&a, &b| left[a][col].abs().total_cmp(&left[b][col].abs()))
+            .unwrap();
+
+        if left[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+
+        left.swap(col, pivot);
+        right.swap(col, pivot);
+
+        let value = left[col][col];
+        for j in 0..size {
+            left[col][j] /= value;
+            right[col][j] /= value;
+        }
+
+        for row in 0..size {
+            if row != col {
+                let factor = left[row][col];
+                for j in 0..size {
+                    left[row][j] -= factor * left[col][j];
+                    right[row][j] -= factor * right[col][j];
+                }
+            }
+        }
+    }
+
+    Some(right)
+}
+
+
+pub struct LogisticRegression {
+    l2: f64,
+    max_iter: usize,
+    tolerance: f64,
+    // Intercept goes first in the results
+    coefficients: Option<Vec<f64>>,
+    std_errors: Option<Vec<f64>>,
+    iterations: usize,
+    converged: bool,
+}
+
+
+impl LogisticRegression {
+    pub fn new() -> Self {
+        Self {
+            l2: 0.0,
+            max_iter: 100,
+            tolerance: 1e-8,
+            coefficients: None,
+            std_errors: None,
+            iterations: 0,
+            converged: false,
+        }
+    }
+
+    pub fn set_l2(&mut self, l2: f64) {
+        // Penalty l2 * |beta|^2 / 2 on coefficients (not the intercept)
+        assert!(l2 >= 0.0, "L2 penalty must not be negative");
+        self.l2 = l2;
+    }
+
+    pub fn set_max_iter(&mut self, max_iter: usize) {
+        self.max_iter = max_iter;
+    }
+
+    pub fn set_tolerance(&mut self, tolerance: f64) {
+        // Newton iterations stop when no coefficient changes more
+        self.tolerance = tolerance;
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    pub fn fit(&mut self, features: &[Vec<f64>], target: &[bool]) -> bool {
+        // Newton (IRLS) iterations for columns of features, returns
+        // whether they converged
+        for column in features.iter() {
+            assert_eq!(column.len(), target.len());
+        }
+
+        let size = features.len() + 1;
+        let x = |row: usize, j: usize| if j == 0 { 1.0 }
+                                        else { features[j - 1][row] };
+
+        let mut beta = vec![0.0; size];
+        let mut hessian_inv = None;
+
+        self.converged = false;
+        self.iterations = 0;
+
+        while self.iterations < self.max_iter {
+            self.iterations += 1;
+
+            // Gradient and Hessian of the penalized log-likelihood
+            let mut gradient = vec![0.0; size];
+            let mut hessian = vec![vec![0.0; size]; size];
+
+            for (row, &y) in target.iter().enumerate() {
+                let eta: f64 = (0..size).map(|j| beta[j] * x(row, j)).sum();
+                let p = 1.0 / (1.0 + (-eta).exp());
+                let w = p * (1.0 - p);
+                let r = if y { 1.0 } else { 0.0 } - p;
+
+                for j in 0..size {
+                    gradient[j] += r * x(row, j);
+                    for k in 0..=j {
+                        hessian[j][k] += w * x(row, j) * x(row, k);
+                    }
+                }
+            }
+
+            for j in 0..size {
+                for k in 0..j {
+                    hessian[k][j] = hessian[j][k];
+                }
+                if j > 0 {
+                    gradient[j] -= self.l2 * beta[j];
+                    hessian[j][j] += self.l2;
+                }
+            }
+
+            hessian_inv = invert(&hessian);
+            let inv = match &hessian_inv {
+                Some(inv) => inv,
+                None => break,
+            };
+
+            let mut change: f64 = 0.0;
+            for j in 0..size {
+                let step: f64 = (0..size).map(|k| inv[j][k] * gradient[k])
+                    .sum();
+                beta[j] += step;
+                change = change.max(step.abs());
+            }
+
+            if change < self.tolerance {
+                self.converged = true;
+                break;
+            }
+        }
+
+        // Standard errors are by the Hessian of the previous iteration,
+        // it is the same on convergence
+        self.std_errors = hessian_inv.map(|inv|
+            (0..size).map(|j| inv[j][j].sqrt()).collect()
+        );
+        self.coefficients = Some(beta);
+
+        self.converged
+    }
+
+    pub fn fit_woe(&mut self, wbps: &[&WoeBinningProc],
+                   series: &[Vec<usize>], target: &[bool]) -> bool {
+        // Fits on WOE of the series of fitted binnings
+        assert_eq!(wbps.len(), series.len());
+
+        let features: Vec<Vec<f64>> = wbps.iter().zip(series)
+            .map(|(wbp, values)| wbp.transform(values))
+            .collect();
+
+        self.fit(&features, target)
+    }
+
+    pub fn is_converged(&self) -> bool {
+        self.converged
+    }
+
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn get_intercept(&self) -> Option<f64> {
+        self.coefficients.as_ref().map(|c| c[0])
+    }
+
+    pub fn get_coefficients(&self) -> Option<Vec<f64>> {
+        self.coefficients.as_ref().map(|c| c[1..].to_vec())
+    }
+
+    pub fn get_std_errors(&self) -> Option<Vec<f64>> {
+        // Intercept goes first, None if the Hessian is singular
+        self.std_errors.clone()
+    }
+
+    pub fn get_p_values(&self) -> Option<Vec<f64>> {
+        // Two-sided p-values of the Wald test, intercept goes first
+        self.coefficients.as_ref().zip(self.std_errors.as_ref())
+            .map(|(coefficients, std_errors)|
+                coefficients.iter().zip(std_errors)
+                    .map(|(b, se)| erfc((b / se).abs() / 2.0f64.sqrt()))
+                    .collect()
+            )
+    }
+
+    pub fn get_sign_checks(&self) -> Option<Vec<bool>> {
+        // WOE is ln(p0 / p1), so a larger WOE means less target 1 and
+        // the coefficient is expected to be negative. A positive one
+        // usually means collinearity or overfitting.
+        self.get_coefficients().map(|c| c.iter().map(|&b| b <= 0.0).collect())
+    }
+}
+
+
+impl Default for LogisticRegression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn create_dataset(size: usize, beta: &[f64]) ->
+                      (Vec<Vec<f64>>, Vec<bool>) {
+        let mut rng = StdRng::seed_from_u64(0);
+        let features: Vec<Vec<f64>> = (1..beta.len())
+            .map(|_| (0..size).map(|_| rng.gen::<f64>() * 2.0 - 1.0).collect())
+            .collect();
+        let target = (0..size)
+            .map(|row| {
+                let eta: f64 = beta[0] + (1..beta.len())
+                    .map(|j| beta[j] * features[j - 1][row])
+                    .sum::<f64>();
+                rng.gen::<f64>() < 1.0 / (1.0 + (-eta).exp())
+            })
+            .collect();
+        (features, target)
+    }
+
+    #[test]
+    fn test_erfc() {
+        assert!((erfc(0.0) - 1.0).abs() < 1e-7);
+        assert!((erfc(1.0) - 0.157299207050285).abs() < 1e-7);
+        assert!((erfc(-1.0) - 1.842700792949715).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_fit() {
+        let (mut features, target) = create_dataset(20_000,
+                                                    &[-0.5, -1.0, 2.0]);
+
+        // Noise feature
+        let mut rng = StdRng::seed_from_u64(1);
+        features.push((0..20_000).map(|_| rng.gen::<f64>()).collect());
+
+        let mut lr = LogisticRegression::new();
+        assert!(lr.fit(&features, &target));
+
+        let beta = lr.get_coefficients().unwrap();
+        assert!((lr.get_intercept().unwrap() + 0.5).abs() < 0.1);
+        assert!((beta[0] + 1.0).abs() < 0.1);
+        assert!((beta[1] - 2.0).abs() < 0.1);
+        assert!(beta[2].abs() < 0.1);
+
+        let p_values = lr.get_p_values().unwrap();
+        assert!(p_values[1] < 1e-6 && p_values[2] < 1e-6);
+        assert!(p_values[3] > 0.01);
+        assert_eq!(lr.get_sign_checks().unwrap()[..2], [true, false]);
+
+        // L2 shrinks coefficients
+        let mut lr_l2 = LogisticRegression::new();
+        lr_l2.set_l2(1_000.0);
+        lr_l2.fit(&features, &target);
+        assert!(lr_l2.get_coefficients().unwrap()[1] < beta[1]);
+    }
+
+    #[test]
+    fn test_fit_woe() {
+        // On a single WOE feature the coefficient is about -1, because 
+        // WOE is the log-odds of the bin minus the log-odds of all
+        let mut rng = StdRng::seed_from_u64(0);
+        let series: Vec<usize> = (0..10_000).map(|_| rng.gen_range(0..20))
+            .collect();
+        let target: Vec<bool> = series.iter()
+            .map(|&v| rng.gen::<f64>() < v as f64 / 25.0)
+            .collect();
+
+        let mut wbp = WoeBinningProc::new(5, 1.0);
+        wbp.process_numeric(&series, &target);
+
+        let mut lr = LogisticRegression::new();
+        assert!(lr.fit_woe(&[&wbp], &[series], &target));
+        assert!((lr.get_coefficients().unwrap()[0] + 1.0).abs() < 0.01);
+        assert_eq!(lr.get_sign_checks(), Some(vec![true]));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::logistic_regression::LogisticRegression;
+use crate::woe_binning_proc_wrapper::WoeBinningProcWrapper;
+
+
+#[repr(C)]
+struct CoefficientInfo {
+    coefficient: f64,
+    std_error: f64,
+    p_value: f64,
+    sign_ok: bool,
+}
+
+
+struct LogisticRegressionWrapper {
+    lr: LogisticRegression,
+}
+
+
+impl LogisticRegressionWrapper {
+    #[export_name="lr_new"]
+    pub extern "C" fn new() -> Box<Self> {
+        Box::new(Self {
+            lr: LogisticRegression::new()
+        })
+    }
+
+    #[export_name="lr_set_l2"]
+    pub extern "C" fn set_l2(&mut self, l2: f64) {
+        self.lr.set_l2(l2);
+    }
+
+    #[export_name="lr_set_max_iter"]
+    pub extern "C" fn set_max_iter(&mut self, max_iter: usize) {
+        self.lr.set_max_iter(max_iter);
+    }
+
+    #[export_name="lr_fit_woe"]
+    pub extern "C" fn fit_woe(&mut self, features_num: usize, 
+                              wbps: *const &WoeBinningProcWrapper, 
+                              size: usize, series: *const usize, 
+                              target: *const bool) -> bool {
+        // Series of the features go one after another
+        let mut wbps_buff: Vec<&WoeBinningProcWrapper> = 
+            Vec::with_capacity(features_num);
+        let mut series_buff: Vec<usize> = vec![0; features_num * size];
+        let mut target_buff: Vec<bool> = vec![false; size];
+
+        unsafe {
+            wbps.copy_to(wbps_buff.as_mut_ptr(), features_num);
+            wbps_buff.set_len(features_num);
+            series.copy_to(series_buff.as_mut_ptr(), features_num * size);
+            target.copy_to(target_buff.as_mut_ptr(), size);
+        }
+
+        let wbps: Vec<_> = wbps_buff.iter().map(|w| &w.wbp).collect();
+        let series: Vec<Vec<usize>> = series_buff.chunks(size.max(1))
+            .map(|c| c.to_vec())
+            .collect();
+
+        self.lr.fit_woe(&wbps, &series, &target_buff)
+    }
+
+    #[export_name="lr_get_coefficients_info"]
+    pub extern "C" fn get_coefficients_info(&self, size: usize, 
+                                            info_array: *mut CoefficientInfo) {
+        // The intercept goes first, its sign is always ok
+        let coefficients: Vec<f64> = 
+            [self.lr.get_intercept().unwrap()].into_iter()
+                .chain(self.lr.get_coefficients().unwrap())
+                .collect();
+        let std_errors = self.lr.get_std_errors()
+            .unwrap_or(vec![f64::NAN; size]);
+        let p_values = self.lr.get_p_values()
+            .unwrap_or(vec![f64::NAN; size]);
+        let sign_checks: Vec<bool> = [true].into_iter()
+            .chain(self.lr.get_sign_checks().unwrap())
+            .collect();
+
+        let info = (0..size).map(|i| CoefficientInfo {
+            coefficient: coefficients[i],
+            std_error: std_errors[i],
+            p_value: p_values[i],
+            sign_ok: sign_checks[i],
+        }).collect::<Vec<CoefficientInfo>>();
+
+        unsafe {
+            info.as_ptr().copy_to(info_array, size);
+        }
+    }
+}
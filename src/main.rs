@@ -0,0 +1,601 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use woebin::{Trend, WoeBinningProc};
+
+
+const USAGE: &str = "\
+Usage: woebin [OPTIONS] --target COLUMN [FILE]
+
+Bins columns of a CSV file (stdin if FILE is missing or -) by WOE and
+writes the table of bins.
+
+Options:
+  -t, --target COLUMN      Target column of 0/1 or false/true
+  -f, --features COLUMNS   Comma separated feature columns, all the
+                           others by default
+  -b, --bins N             Desirable number of bins [default: 10]
+  -s, --smooth X           Smooth of the binning [default: 1.0]
+  -m, --mode MODE          auto, numeric, categorial or ordered
+                           [default: auto]
+      --exact              Exact binning (numeric and ordered modes)
+      --min-bin-size N     Minimal number of rows in a bin (exact only)
+      --trend TREND        ascending, descending, peak, valley or auto
+                           (the one with maximal IV, exact only)
+  -d, --delimiter CHAR     Delimiter of the input [default: ,]
+  -o, --format FORMAT      csv, json or markdown [default: csv]
+  -h, --help               Print this help
+";
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    // Numeric if all the values are numbers, categorial otherwise
+    Auto,
+    Numeric,
+    Categorial,
+    Ordered,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Csv,
+    Json,
+    Markdown,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    path: Option<String>,
+    target: String,
+    features: Option<Vec<String>>,
+    bins: usize,
+    smooth: f64,
+    mode: Mode,
+    exact: bool,
+    min_bin_size: usize,
+    trend: Option<Trend>,
+    auto_trend: bool,
+    delimiter: char,
+    format: Format,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+struct BinRow {
+    feature: String,
+    bin: usize,
+    values: String,
+    count: usize,
+    events: usize,
+    woe: f64,
+    iv: f64,
+}
+
+
+fn parse_args(args: &[String]) -> Result<Option<Options>, String> {
+    // None if the help is requested
+    let mut path = None;
+    let mut target = None;
+    let mut features = None;
+    let mut bins = 10;
+    let mut smooth = 1.0;
+    let mut mode = Mode::Auto;
+    let mut exact = false;
+    let mut min_bin_size = 0;
+    let mut trend = None;
+    let mut auto_trend = false;
+    let mut delimiter = ',';
+    let mut format = Format::Csv;
+
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| iter.next().cloned()
+            .ok_or(format!("{} requires a value", name));
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-t" | "--target" => target = Some(value(arg)?),
+            "-f" | "--features" => features = Some(
+                value(arg)?.split(',').map(|f| f.trim().to_string()).collect()
+            ),
+            "-b" | "--bins" => bins = value(arg)?.parse()
+                .map_err(|_| format!("{} must be a positive integer", arg))?,
+            "-s" | "--smooth" => smooth = value(arg)?.parse()
+                .map_err(|_| format!("{} must be a number", arg))?,
+            "-m" | "--mode" => mode = match value(arg)?.as_str() {
+                "auto" => Mode::Auto,
+                "numeric" => Mode::Numeric,
+                "categorial" => Mode::Categorial,
+                "ordered" => Mode::Ordered,
+                other => return Err(format!("Unknown mode: {}", other)),
+            },
+            "--exact" => exact = true,
+            "--min-bin-size" => min_bin_size = value(arg)?.parse()
+                .map_err(|_| format!("{} must be an integer", arg))?,
+            "--trend" => (trend, auto_trend) = match value(arg)?.as_str() {
+                "ascending" => (Some(Trend::Ascending), false),
+                "descending" => (Some(Trend::Descending), false),
+                "peak" => (Some(Trend::Peak), false),
+                "valley" => (Some(Trend::Valley), false),
+                "auto" => (None, true),
+                other => return Err(format!("Unknown trend: {}", other)),
+            },
+            "-d" | "--delimiter" => {
+                let text = value(arg)?;
+                let text = if text == "\\t" { "\t" } else { text.as_str() };
+                let mut chars = text.chars();
+                delimiter = match (chars.next(), chars.next()) {
+                    (Some(c), None) if c != '"' => c,
+                    _ => return Err(format!("{} must be a single character",
+                                            arg)),
+                };
+            },
+            "-o" | "--format" => format = match value(arg)?.as_str() {
+                "csv" => Format::Csv,
+                "json" => Format::Json,
+                "markdown" | "md" => Format::Markdown,
+                other => return Err(format!("Unknown format: {}", other)),
+            },
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("Unknown option: {}", arg));
+            },
+            _ => {
+                if path.is_some() {
+                    return Err("Only one input file is allowed".to_string());
+                }
+                path = Some(arg.clone());
+            },
+        }
+    }
+
+    let target = target.ok_or("--target is required")?;
+
+    if bins == 0 {
+        return Err("--bins must be a positive integer".to_string());
+    }
+    if exact && !matches!(mode, Mode::Auto | Mode::Numeric | Mode::Ordered) {
+        return Err("--exact is for numeric or ordered mode only".to_string());
+    }
+    if !exact && (min_bin_size > 0 || trend.is_some() || auto_trend) {
+        return Err("--min-bin-size and --trend require --exact".to_string());
+    }
+
+    Ok(Some(Options {
+        path: path.filter(|p| p != "-"),
+        target, features, bins, smooth, mode, exact, min_bin_size, trend,
+        auto_trend, delimiter, format,
+    }))
+}
+
+
+fn parse_csv(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+    // Records of RFC 4180: quoted fields can have delimiters, newlines
+    // and doubled quotes, CRLF is accepted, empty lines are skipped
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' && field.is_empty() {
+            quoted = true;
+        } else if c == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            record.push(std::mem::take(&mut field));
+            if record.len() > 1 || !record[0].is_empty() {
+                records.push(std::mem::take(&mut record));
+            } else {
+                record.clear();
+            }
+        } else {
+            field.push(c);
+        }
+    }
+
+    if quoted {
+        return Err("Unterminated quoted field".to_string());
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+
+fn parse_target(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "1.0" | "true" => Some(true),
+        "0" | "0.0" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+
+fn parse_number(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|x| !x.is_nan())
+}
+
+
+fn bin_feature(name: &str, column: &[&str], target: &[bool],
+               options: &Options) -> Result<Vec<BinRow>, String> {
+    let numbers: Option<Vec<f64>> = column.iter()
+        .map(|v| parse_number(v))
+        .collect();
+
+    let is_numeric = match options.mode {
+        Mode::Auto => numbers.is_some(),
+        Mode::Numeric => {
+            if numbers.is_none() {
+                return Err(format!("Column {} is not numeric", name));
+            }
+            true
+        },
+        Mode::Categorial | Mode::Ordered => false,
+    };
+
+    let mut wbp = WoeBinningProc::new(options.bins, options.smooth);
+    wbp.set_min_bin_size(options.min_bin_size);
+    wbp.set_trend(options.trend);
+    wbp.set_auto_trend(options.auto_trend);
+
+    // Values are encoded by their ranks: distinct numbers ascending,
+    // distinct strings in lexicographical order
+    let labels: Vec<String>;
+    let series: Vec<usize>;
+
+    if is_numeric {
+        let numbers = numbers.unwrap();
+        let mut distinct = numbers.clone();
+        distinct.sort_by(|a, b| a.total_cmp(b));
+        distinct.dedup();
+        series = numbers.iter()
+            .map(|x| distinct.binary_search_by(|d| d.total_cmp(x)).unwrap())
+            .collect();
+        labels = distinct.iter().map(|x| x.to_string()).collect();
+
+        if options.exact {
+            wbp.process_numeric_exact(&series, target);
+        } else {
+            wbp.process_numeric(&series, target);
+        }
+    } else {
+        let mut distinct: Vec<&str> = column.to_vec();
+        distinct.sort();
+        distinct.dedup();
+        series = column.iter()
+            .map(|v| distinct.binary_search(v).unwrap())
+            .collect();
+        labels = distinct.iter().map(|v| v.to_string()).collect();
+
+        if options.mode == Mode::Ordered && options.exact {
+            wbp.process_categorial_ordered_exact(&series, target);
+        } else if options.mode == Mode::Ordered {
+            wbp.process_categorial_ordered(&series, target);
+        } else if options.exact {
+            return Err(format!(
+                "Exact binning of categorial column {} requires ordered mode",
+                name
+            ));
+        } else {
+            wbp.process_categorial(&series, target);
+        }
+    }
+
+    if !wbp.is_feasible() {
+        eprintln!("woebin: no bins of column {} satisfy --min-bin-size and \
+                   --trend, all values are in one bin", name);
+    }
+
+    let bins = wbp.get_bins_array().unwrap();
+    let counts = wbp.get_count_array().unwrap();
+    let events = wbp.get_event_array().unwrap();
+    let woe = wbp.get_woe_array().unwrap();
+    let iv = wbp.get_iv_array().unwrap();
+
+    Ok((0..bins.len()).map(|idx| BinRow {
+        feature: name.to_string(),
+        bin: idx,
+        values: if is_numeric {
+            format!("{}..{}", labels[bins[idx][0]],
+                    labels[bins[idx][bins[idx].len() - 1]])
+        } else {
+            let values: Vec<&str> = bins[idx].iter()
+                .map(|&v| labels[v].as_str())
+                .collect();
+            values.join(", ")
+        },
+        count: counts[idx],
+        events: events[idx],
+        woe: woe[idx],
+        iv: iv[idx],
+    }).collect())
+}
+
+
+fn bin_records(records: &[Vec<String>],
+               options: &Options) -> Result<Vec<BinRow>, String> {
+    let header = records.first().ok_or("Input is empty")?;
+    let rows = &records[1..];
+
+    let find_column = |name: &str| header.iter().position(|h| h == name)
+        .ok_or(format!("Column {} is not found", name));
+
+    let target_idx = find_column(&options.target)?;
+
+    let feature_idxs: Vec<usize> = match &options.features {
+        Some(features) => features.iter()
+            .map(|f| find_column(f))
+            .collect::<Result<Vec<usize>, String>>()?,
+        None => (0..header.len()).filter(|&i| i != target_idx).collect(),
+    };
+
+    if rows.is_empty() {
+        return Err("Input has no rows".to_string());
+    }
+
+    for (line, row) in rows.iter().enumerate() {
+        if row.len() != header.len() {
+            return Err(format!("Row {} has {} fields instead of {}",
+                               line + 1, row.len(), header.len()));
+        }
+    }
+
+    let target: Vec<bool> = rows.iter().enumerate()
+        .map(|(line, row)| parse_target(&row[target_idx])
+            .ok_or(format!("Target {:?} in row {} is not 0/1",
+                           row[target_idx], line + 1)))
+        .collect::<Result<Vec<bool>, String>>()?;
+
+    let mut table = Vec::new();
+
+    for idx in feature_idxs {
+        let column: Vec<&str> = rows.iter().map(|row| row[idx].as_str())
+            .collect();
+        table.extend(bin_feature(&header[idx], &column, &target, options)?);
+    }
+
+    Ok(table)
+}
+
+
+fn render_csv(table: &[BinRow]) -> String {
+    let quote = |text: &str| {
+        if text.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.to_string()
+        }
+    };
+
+    let mut csv = String::from("feature,bin,values,count,events,woe,iv\n");
+    for row in table {
+        csv += &format!("{},{},{},{},{},{},{}\n", quote(&row.feature), row.bin,
+                        quote(&row.values), row.count, row.events, row.woe,
+                        row.iv);
+    }
+    csv
+}
+
+
+fn render_json(table: &[BinRow]) -> String {
+    let quote = |text: &str| {
+        let mut json = String::from("\"");
+        for c in text.chars() {
+            match c {
+                '"' => json += "\\\"",
+                '\\' => json += "\\\\",
+                '\n' => json += "\\n",
+                '\r' => json += "\\r",
+                '\t' => json += "\\t",
+                c if (c as u32) < 0x20 => json += &format!("\\u{:04x}",
+                                                           c as u32),
+                c => json.push(c),
+            }
+        }
+        json + "\""
+    };
+    // JSON has no NaN and infinities
+    let number = |x: f64| if x.is_finite() { x.to_string() }
+                          else { "null".to_string() };
+
+    let rows: Vec<String> = table.iter()
+        .map(|row| format!(
+            "  {{\"feature\": {}, \"bin\": {}, \"values\": {}, \
+             \"count\": {}, \"events\": {}, \"woe\": {}, \"iv\": {}}}",
+            quote(&row.feature), row.bin, quote(&row.values), row.count,
+            row.events, number(row.woe), number(row.iv)
+        ))
+        .collect();
+
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+
+fn render_markdown(table: &[BinRow]) -> String {
+    let escape = |text: &str| text.replace('|', "\\|").replace('\n', " ");
+
+    let mut md = String::from(
+        "| feature | bin | values | count | events | woe | iv |\n\
+         |---|---:|---|---:|---:|---:|---:|\n"
+    );
+    for row in table {
+        md += &format!("| {} | {} | {} | {} | {} | {:.4} | {:.4} |\n",
+                       escape(&row.feature), row.bin, escape(&row.values),
+                       row.count, row.events, row.woe, row.iv);
+    }
+    md
+}
+
+
+fn run(args: &[String]) -> Result<(), String> {
+    let options = match parse_args(args)? {
+        Some(options) => options,
+        None => {
+            print!("{}", USAGE);
+            return Ok(());
+        },
+    };
+
+    let text = match &options.path {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| format!("{}: {}", path, e))?,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)
+                .map_err(|e| format!("stdin: {}", e))?;
+            text
+        },
+    };
+
+    let records = parse_csv(&text, options.delimiter)?;
+    let table = bin_records(&records, &options)?;
+
+    let output = match options.format {
+        Format::Csv => render_csv(&table),
+        Format::Json => render_json(&table),
+        Format::Markdown => render_markdown(&table),
+    };
+
+    // A closed pipe (such as head) is not an error
+    let _ = io::stdout().write_all(output.as_bytes());
+
+    Ok(())
+}
+
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if let Err(error) = run(&args) {
+        eprintln!("woebin: {}", error);
+        eprintln!("Try 'woebin --help' for more information.");
+        process::exit(2);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(&to_args(
+            "-t y -f a,b -b 4 -m numeric --exact --trend ascending -o md data.csv"
+        )).unwrap().unwrap();
+        assert_eq!(options.path, Some("data.csv".to_string()));
+        assert_eq!(options.features, Some(vec!["a".to_string(),
+                                               "b".to_string()]));
+        assert_eq!((options.bins, options.mode, options.exact),
+                   (4, Mode::Numeric, true));
+        assert_eq!(options.trend, Some(Trend::Ascending));
+        assert_eq!(options.format, Format::Markdown);
+
+        assert_eq!(parse_args(&to_args("--help")), Ok(None));
+        assert!(parse_args(&to_args("-b 4")).is_err());
+        assert!(parse_args(&to_args("-t y --trend ascending")).is_err());
+        assert!(parse_args(&to_args("-t y --exact --trend auto")).unwrap()
+            .unwrap().auto_trend);
+        assert!(parse_args(&to_args("-t y -m categorial --exact")).is_err());
+        assert_eq!(parse_args(&to_args("-t y -")).unwrap().unwrap().path,
+                   None);
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let records = parse_csv("a;b\r\n\"x;\"\"y\"\"\";\n\n1;\"2\n3\"", ';')
+            .unwrap();
+        assert_eq!(records, vec![
+            vec!["a", "b"],
+            vec!["x;\"y\"", ""],
+            vec!["1", "2\n3"],
+        ]);
+        assert!(parse_csv("a,\"b", ',').is_err());
+    }
+
+    #[test]
+    fn test_bin_records() {
+        let mut text = String::from("x,c,y\n");
+        for i in 0..200 {
+            let c = ["a", "b", "c", ""][i % 4];
+            text += &format!("{},{},{}\n", i % 20, c,
+                             (i % 20 >= 10 || c == "a") as u8);
+        }
+        let records = parse_csv(&text, ',').unwrap();
+
+        let mut options = parse_args(&to_args("-t y -b 2")).unwrap().unwrap();
+        let table = bin_records(&records, &options).unwrap();
+
+        // x is detected as numeric, c as categorial with the empty value
+        let x: Vec<&BinRow> = table.iter().filter(|r| r.feature == "x")
+            .collect();
+        assert_eq!(x.len(), 2);
+        assert_eq!(x[0].values, "0..9");
+        assert_eq!(x.iter().map(|r| r.count).sum::<usize>(), 200);
+        assert!(x[0].woe > 0.0 && x[1].woe < 0.0);
+
+        let c: Vec<&BinRow> = table.iter().filter(|r| r.feature == "c")
+            .collect();
+        assert_eq!(c.len(), 2);
+        assert!(c.iter().any(|r| r.values == "a"));
+        assert!(c.iter().any(|r| r.values == ", b, c"));
+
+        options.mode = Mode::Numeric;
+        assert!(bin_records(&records, &options).is_err());
+
+        options.features = Some(vec!["z".to_string()]);
+        assert!(bin_records(&records, &options).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let table = vec![BinRow {
+            feature: "a,b".to_string(),
+            bin: 0,
+            values: "x|\"y\"".to_string(),
+            count: 10,
+            events: 3,
+            woe: 0.5,
+            iv: f64::INFINITY,
+        }];
+
+        assert_eq!(render_csv(&table),
+                   "feature,bin,values,count,events,woe,iv\n\
+                    \"a,b\",0,\"x|\"\"y\"\"\",10,3,0.5,inf\n");
+        assert!(render_json(&table).contains(
+            "\"values\": \"x|\\\"y\\\"\", \"count\": 10, \"events\": 3, \
+             \"woe\": 0.5, \"iv\": null}"
+        ));
+        assert!(render_markdown(&table).ends_with(
+            "| a,b | 0 | x\\|\"y\" | 10 | 3 | 0.5000 | inf |\n"
+        ));
+    }
+}
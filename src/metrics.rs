@@ -0,0 +1,107 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    pub auc: f64,
+    pub gini: f64,
+    pub ks: f64,
+    pub divergence: f64,
+}
+
+
+pub fn calc_metrics(groups: &[(f64, usize, usize)]) -> Metrics {
+    // Groups of rows with the same score (WOE) and their counts of target
+    // 1 and 0. A larger score means less target 1, rows of the same score
+    // are ties. NaN if there are no rows of target 1 or 0.
+    let mut groups = groups.to_vec();
+    groups.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let c1: usize = groups.iter().map(|g| g.1).sum();
+    let c0: usize = groups.iter().map(|g| g.2).sum();
+    let (c1, c0) = (c1 as f64, c0 as f64);
+
+    // Pairs of target 1 and 0 ordered correctly plus a half of ties
+    let mut pairs = 0.0;
+    let mut above0 = c0;
+
+    // Largest difference of cumulative distributions
+    let mut ks: f64 = 0.0;
+    let mut acc1 = 0.0;
+    let mut acc0 = 0.0;
+
+    for &(_, n1, n0) in groups.iter() {
+        let (n1, n0) = (n1 as f64, n0 as f64);
+        above0 -= n0;
+        pairs += n1 * above0 + 0.5 * n1 * n0;
+        acc1 += n1;
+        acc0 += n0;
+        ks = ks.max((acc1 / c1 - acc0 / c0).abs());
+    }
+
+    // Divergence of the score distributions of target 1 and 0
+    let mean1 = groups.iter().map(|g| g.0 * g.1 as f64).sum::<f64>() / c1;
+    let mean0 = groups.iter().map(|g| g.0 * g.2 as f64).sum::<f64>() / c0;
+    let var1 = groups.iter()
+        .map(|g| (g.0 - mean1).powi(2) * g.1 as f64).sum::<f64>() / c1;
+    let var0 = groups.iter()
+        .map(|g| (g.0 - mean0).powi(2) * g.2 as f64).sum::<f64>() / c0;
+
+    let auc = pairs / (c1 * c0);
+
+    Metrics {
+        auc,
+        gini: 2.0 * auc - 1.0,
+        ks: if c1 > 0.0 && c0 > 0.0 { ks } else { f64::NAN },
+        divergence: (mean0 - mean1).powi(2) / (0.5 * (var0 + var1)),
+    }
+}
+
+
+pub fn calc_metrics_by_scores(scores: &[f64], target: &[bool]) -> Metrics {
+    // The same by scores of rows
+    assert_eq!(scores.len(), target.len());
+
+    let mut rows: Vec<(f64, bool)> = scores.iter().cloned()
+        .zip(target.iter().cloned())
+        .collect();
+    rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut groups: Vec<(f64, usize, usize)> = Vec::new();
+
+    for (score, trg) in rows {
+        if groups.last().is_none_or(|g| g.0.total_cmp(&score).is_ne()) {
+            groups.push((score, 0, 0));
+        }
+        let group = groups.last_mut().unwrap();
+        if trg {
+            group.1 += 1;
+        } else {
+            group.2 += 1;
+        }
+    }
+
+    calc_metrics(&groups)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calc_metrics() {
+        let metrics = calc_metrics(&[(1.0, 1, 3), (-1.0, 3, 1), (0.0, 2, 2)]);
+        assert!((metrics.auc - 26.0 / 36.0).abs() < 1e-12);
+        assert!((metrics.gini - 16.0 / 36.0).abs() < 1e-12);
+        assert!((metrics.ks - 1.0 / 3.0).abs() < 1e-12);
+        assert!((metrics.divergence - 0.8).abs() < 1e-12);
+
+        let scores = [1.0, 1.0, 1.0, 1.0, -1.0, -1.0, -1.0, -1.0,
+                      0.0, 0.0, 0.0, 0.0];
+        let target = [true, false, false, false, true, true, true, false,
+                      true, true, false, false];
+        assert_eq!(calc_metrics_by_scores(&scores, &target), metrics);
+
+        // Perfect separation
+        let metrics = calc_metrics(&[(-1.0, 5, 0), (1.0, 0, 5)]);
+        assert_eq!((metrics.auc, metrics.gini, metrics.ks), (1.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+
+pub trait Neighbourhood {
+    // Ids of initial clusters are 0..size
+    fn initialize(&mut self, size: usize);
+    // Pairs of adjacent initial clusters
+    fn pairs(&self) -> Vec<(usize, usize)>;
+    // Whether merge accepts the clusters
+    fn can_merge(&self, cluster_id1: usize, cluster_id2: usize) -> bool;
+    // Replaces the merged clusters with the new one and returns the pairs
+    // of the new cluster with its neighbours
+    fn merge(&mut self, cluster_new_id: usize,
+             cluster_id1: usize, cluster_id2: usize) -> Vec<(usize, usize)>;
+    // Reverts the last merge and returns the pairs of the restored
+    // clusters with their neighbours (including each other)
+    fn split(&mut self, cluster_new_id: usize,
+             cluster_id1: usize, cluster_id2: usize) -> Vec<(usize, usize)>;
+}
+
+
+pub struct ChainNeighbourhood {
+    order_map: BTreeMap<usize, (Option<usize>, Option<usize>)>,
+}
+
+
+impl ChainNeighbourhood {
+    pub fn new() -> Self {
+        Self {
+            order_map: BTreeMap::new(),
+        }
+    }
+}
+
+
+impl Default for ChainNeighbourhood {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl Neighbourhood for ChainNeighbourhood {
+    fn initialize(&mut self, size: usize) {
+        self.order_map.clear();
+
+        for cluster_id in 0..size {
+            self.order_map.insert(
+                cluster_id,
+                (
+                    if cluster_id > 0 { Some(cluster_id - 1) }
+                                 else { None },
+                    if cluster_id < size - 1 { Some(cluster_id + 1) }
+                                        else { None },
+                )
+            );
+        }
+    }
+
+    fn pairs(&self) -> Vec<(usize, usize)> {
+        // Left cluster goes first in each pair
+        self.order_map.iter()
+            .filter_map(|(&cluster_id, &(_, right))|
+                right.map(|right| (cluster_id, right))
+            )
+            .collect()
+    }
+
+    fn can_merge(&self, cluster_id1: usize, cluster_id2: usize) -> bool {
+        // cluster_id1 goes right before cluster_id2 in the linked list
+        self.order_map.get(&cluster_id1)
+            .is_some_and(|&(_, right)| right == Some(cluster_id2))
+    }
+
+    fn merge(&mut self, cluster_new_id: usize,
+             cluster_id1: usize, cluster_id2: usize) -> Vec<(usize, usize)> {
+        assert!(self.can_merge(cluster_id1, cluster_id2),
+                "Clusters are not neighbours");
+
+        // Insert new node
+        self.order_map.insert(
+            cluster_new_id,
+            (
+                self.order_map[&cluster_id1].0,
+                self.order_map[&cluster_id2].1,
+            )
+        );
+
+        // Rebind left node
+        if let Some(cluster_id) = self.order_map[&cluster_new_id].0 {
+            self.order_map.get_mut(&cluster_id).unwrap().1 =
+                Some(cluster_new_id);
+        }
+
+        // Rebind right node
+        if let Some(cluster_id) = self.order_map[&cluster_new_id].1 {
+            self.order_map.get_mut(&cluster_id).unwrap().0 =
+                Some(cluster_new_id);
+        }
+
+        // Remove old nodes
+        self.order_map.remove(&cluster_id1);
+        self.order_map.remove(&cluster_id2);
+
+        // Left and right pairs
+        let (left, right) = self.order_map[&cluster_new_id];
+        left.map(|cluster_id| (cluster_id, cluster_new_id)).into_iter()
+            .chain(right.map(|cluster_id| (cluster_new_id, cluster_id)))
+            .collect()
+    }
+
+    fn split(&mut self, cluster_new_id: usize,
+             cluster_id1: usize, cluster_id2: usize) -> Vec<(usize, usize)> {
+        // Restore old nodes
+        let (left, right) = self.order_map.remove(&cluster_new_id).unwrap();
+        self.order_map.insert(cluster_id1, (left, Some(cluster_id2)));
+        self.order_map.insert(cluster_id2, (Some(cluster_id1), right));
+
+        // Rebind left node
+        if let Some(cluster_id) = left {
+            self.order_map.get_mut(&cluster_id).unwrap().1 =
+                Some(cluster_id1);
+        }
+
+        // Rebind right node
+        if let Some(cluster_id) = right {
+            self.order_map.get_mut(&cluster_id).unwrap().0 =
+                Some(cluster_id2);
+        }
+
+        // Left, middle and right pairs
+        left.map(|cluster_id| (cluster_id, cluster_id1)).into_iter()
+            .chain(Some((cluster_id1, cluster_id2)))
+            .chain(right.map(|cluster_id| (cluster_id2, cluster_id)))
+            .collect()
+    }
+}
+
+
+pub struct GraphNeighbourhood {
+    edges: Vec<(usize, usize)>,
+    neighbour_map: BTreeMap<usize, BTreeSet<usize>>,
+    split_map: BTreeMap<usize, (BTreeSet<usize>, BTreeSet<usize>)>,
+}
+
+
+impl GraphNeighbourhood {
+    pub fn new(edges: &[(usize, usize)]) -> Self {
+        // Edges are undirected, loops and edges out of the range of 
+        // clusters are ignored
+        Self {
+            edges: edges.to_vec(),
+            neighbour_map: BTreeMap::new(),
+            split_map: BTreeMap::new(),
+        }
+    }
+
+    pub fn grid(rows: usize, cols: usize) -> Self {
+        // Cells are numbered row by row, each one is adjacent to
+        // the cells on the left, right, top and bottom
+        let mut edges = Vec::new();
+
+        for row in 0..rows {
+            for col in 0..cols {
+                let cell = row * cols + col;
+                if col + 1 < cols {
+                    edges.push((cell, cell + 1));
+                }
+                if row + 1 < rows {
+                    edges.push((cell, cell + cols));
+                }
+            }
+        }
+
+        Self::new(&edges)
+    }
+}
+
+
+impl Neighbourhood for GraphNeighbourhood {
+    fn initialize(&mut self, size: usize) {
+        self.split_map.clear();
+        self.neighbour_map.clear();
+        self.neighbour_map.extend((0..size).map(|id| (id, BTreeSet::new())));
+
+        for &(cluster_id1, cluster_id2) in self.edges.iter() {
+            if cluster_id1 != cluster_id2 && 
+                    cluster_id1 < size && cluster_id2 < size {
+                self.neighbour_map.get_mut(&cluster_id1).unwrap()
+                    .insert(cluster_id2);
+                self.neighbour_map.get_mut(&cluster_id2).unwrap()
+                    .insert(cluster_id1);
+            }
+        }
+    }
+
+    fn pairs(&self) -> Vec<(usize, usize)> {
+        // Smaller id goes first in each pair
+        self.neighbour_map.iter()
+            .flat_map(|(&cluster_id, neighbours)|
+                neighbours.range((cluster_id + 1)..)
+                    .map(move |&neighbour_id| (cluster_id, neighbour_id))
+            )
+            .collect()
+    }
+
+    fn can_merge(&self, cluster_id1: usize, cluster_id2: usize) -> bool {
+        // Any clusters, not only adjacent ones
+        cluster_id1 != cluster_id2 && 
+            self.neighbour_map.contains_key(&cluster_id1) && 
+            self.neighbour_map.contains_key(&cluster_id2)
+    }
+
+    fn merge(&mut self, cluster_new_id: usize,
+             cluster_id1: usize, cluster_id2: usize) -> Vec<(usize, usize)> {
+        // The new cluster inherits the neighbours of both merged ones,
+        // they are kept for split
+        let neighbours1 = self.neighbour_map.remove(&cluster_id1).unwrap();
+        let neighbours2 = self.neighbour_map.remove(&cluster_id2).unwrap();
+
+        let mut neighbours = &neighbours1 | &neighbours2;
+        neighbours.remove(&cluster_id1);
+        neighbours.remove(&cluster_id2);
+
+        // Rebind neighbours
+        for neighbour_id in neighbours.iter() {
+            let neighbour_set = self.neighbour_map.get_mut(neighbour_id)
+                .unwrap();
+            neighbour_set.remove(&cluster_id1);
+            neighbour_set.remove(&cluster_id2);
+            neighbour_set.insert(cluster_new_id);
+        }
+
+        // The new id is the largest one
+        let pairs = neighbours.iter()
+            .map(|&neighbour_id| (neighbour_id, cluster_new_id))
+            .collect();
+
+        self.neighbour_map.insert(cluster_new_id, neighbours);
+        self.split_map.insert(cluster_new_id, (neighbours1, neighbours2));
+
+        pairs
+    }
+
+    fn split(&mut self, cluster_new_id: usize,
+             cluster_id1: usize, cluster_id2: usize) -> Vec<(usize, usize)> {
+        // Neighbours are the same as before the merge, because later
+        // merges have been split already
+        let (neighbours1, neighbours2) = self.split_map
+            .remove(&cluster_new_id).unwrap();
+
+        // Unbind the merged cluster
+        for neighbour_id in self.neighbour_map.remove(&cluster_new_id)
+                .unwrap() {
+            self.neighbour_map.get_mut(&neighbour_id).unwrap()
+                .remove(&cluster_new_id);
+        }
+
+        // Rebind neighbours
+        let mut pairs = Vec::new();
+
+        for (cluster_id, neighbours) in [(cluster_id1, &neighbours1),
+                                         (cluster_id2, &neighbours2)] {
+            for &neighbour_id in neighbours.iter() {
+                if neighbour_id != cluster_id1 && neighbour_id != cluster_id2 {
+                    self.neighbour_map.get_mut(&neighbour_id).unwrap()
+                        .insert(cluster_id);
+                }
+                if neighbour_id != cluster_id1 {
+                    pairs.push((cluster_id.min(neighbour_id),
+                                cluster_id.max(neighbour_id)));
+                }
+            }
+        }
+
+        self.neighbour_map.insert(cluster_id1, neighbours1);
+        self.neighbour_map.insert(cluster_id2, neighbours2);
+
+        pairs
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain() {
+        let mut neighbourhood = ChainNeighbourhood::new();
+        neighbourhood.initialize(4);
+
+        assert_eq!(neighbourhood.pairs(), vec![(0, 1), (1, 2), (2, 3)]);
+        assert!(neighbourhood.can_merge(1, 2));
+        assert!(!neighbourhood.can_merge(2, 1));
+        assert!(!neighbourhood.can_merge(0, 2));
+        assert!(!neighbourhood.can_merge(0, 10));
+        assert_eq!(neighbourhood.merge(4, 1, 2), vec![(0, 4), (4, 3)]);
+        assert_eq!(neighbourhood.merge(5, 4, 3), vec![(0, 5)]);
+        assert_eq!(neighbourhood.pairs(), vec![(0, 5)]);
+
+        assert_eq!(neighbourhood.split(5, 4, 3), vec![(0, 4), (4, 3)]);
+        assert_eq!(neighbourhood.split(4, 1, 2), vec![(0, 1), (1, 2), (2, 3)]);
+        assert_eq!(neighbourhood.pairs(), vec![(0, 1), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn test_grid() {
+        let mut neighbourhood = GraphNeighbourhood::grid(2, 3);
+        neighbourhood.initialize(6);
+
+        assert_eq!(neighbourhood.pairs(),
+                   vec![(0, 1), (0, 3), (1, 2), (1, 4), (2, 5), (3, 4), (4, 5)]);
+        assert!(neighbourhood.can_merge(0, 5));
+        assert!(!neighbourhood.can_merge(0, 6));
+        assert_eq!(neighbourhood.merge(6, 0, 1), vec![(2, 6), (3, 6), (4, 6)]);
+        assert_eq!(neighbourhood.merge(7, 3, 4), vec![(5, 7), (6, 7)]);
+        assert_eq!(neighbourhood.pairs(),
+                   vec![(2, 5), (2, 6), (5, 7), (6, 7)]);
+
+        assert_eq!(neighbourhood.split(7, 3, 4),
+                   vec![(3, 4), (3, 6), (4, 5), (4, 6)]);
+        assert_eq!(neighbourhood.split(6, 0, 1),
+                   vec![(0, 1), (0, 3), (1, 2), (1, 4)]);
+        assert_eq!(neighbourhood.pairs(),
+                   vec![(0, 1), (0, 3), (1, 2), (1, 4), (2, 5), (3, 4), (4, 5)]);
+    }
+
+    #[test]
+    fn test_graph_out_of_range() {
+        let mut neighbourhood = GraphNeighbourhood::new(&[(0, 1), (1, 5)]);
+        neighbourhood.initialize(3);
+        assert_eq!(neighbourhood.pairs(), vec![(0, 1)]);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::woe_binning_proc::calc_woe;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    // WOE strictly increases with value
+    Ascending,
+    // WOE strictly decreases with value
+    Descending,
+    // WOE strictly increases and then strictly decreases (inverted U)
+    Peak,
+    // WOE strictly decreases and then strictly increases (U)
+    Valley,
+}
+
+
+impl Trend {
+    pub const ALL: [Trend; 4] = [
+        Trend::Ascending, Trend::Descending, Trend::Peak, Trend::Valley,
+    ];
+
+    fn next_phase(&self, phase: usize, woe_prev: f64, 
+                  woe_next: f64) -> Option<usize> {
+        // Phase of a partition after the next bin: 0 is for the first 
+        // bin only, 1 is the first direction of the shape and 2 is 
+        // the second one (after the turn). None if the shape is broken.
+        let is_up = woe_prev < woe_next;
+        let is_down = woe_prev > woe_next;
+        let (first, second) = match self {
+            Trend::Ascending => (is_up, false),
+            Trend::Descending => (is_down, false),
+            Trend::Peak => (is_up, is_down),
+            Trend::Valley => (is_down, is_up),
+        };
+        match phase {
+            0 | 1 if first => Some(1),
+            1 | 2 if second => Some(2),
+            _ => None,
+        }
+    }
+
+    fn is_complete(&self, phase: usize) -> bool {
+        // Peak and valley must turn, otherwise they are monotonic
+        match self {
+            Trend::Ascending | Trend::Descending => true,
+            Trend::Peak | Trend::Valley => phase == 2,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Prebin {
+    pub n: usize,
+    pub p0: f64,
+    pub p1: f64,
+}
+
+
+#[allow(clippy::needless_range_loop)]
+pub fn optimal_partition<F>(prebins: &[Prebin], bins_num: usize,
+                            min_size: usize, trend: Option<Trend>,
+                            score: F) -> Option<Vec<usize>>
+        where F: Fn(f64, f64) -> f64 {
+    // Partition of ordered pre-bins into at most bins_num contiguous bins
+    // maximizing the sum of score(p0, p1) over the bins. Each bin must
+    // have at least min_size rows and WOE of bins must follow the trend.
+    // Returns end indices of the bins or None if there is no partition
+    // satisfying the constraints.
+    let size = prebins.len();
+
+    if size == 0 || bins_num == 0 {
+        return None;
+    }
+
+    // There are not more bins than pre-bins, the table below grows with 
+    // bins_num
+    let bins_num = bins_num.min(size);
+
+    // Prefix sums
+    let mut n_acc = vec![0; size + 1];
+    let mut p0_acc = vec![0.0; size + 1];
+    let mut p1_acc = vec![0.0; size + 1];
+
+    for (idx, prebin) in prebins.iter().enumerate() {
+        n_acc[idx + 1] = n_acc[idx] + prebin.n;
+        p0_acc[idx + 1] = p0_acc[idx] + prebin.p0;
+        p1_acc[idx + 1] = p1_acc[idx] + prebin.p1;
+    }
+
+    // Bin of pre-bins [i, j)
+    let is_valid = |i: usize, j: usize| n_acc[j] - n_acc[i] >= min_size;
+    let bin_score = |i: usize, j: usize|
+        score(p0_acc[j] - p0_acc[i], p1_acc[j] - p1_acc[i]);
+    let bin_woe = |i: usize, j: usize|
+        calc_woe(p0_acc[j] - p0_acc[i], p1_acc[j] - p1_acc[i]);
+
+    // best[k][i][j][phase] is the best score of k bins covering [0, j) 
+    // with the last bin [i, j) in the phase of the trend, along with 
+    // the start of the previous bin and its phase
+    const PHASES: usize = 3;
+    let mut best = vec![vec![vec![[None::<(f64, usize, usize)>; PHASES];
+                                  size + 1]; size + 1]; bins_num + 1];
+
+    for j in 1..=size {
+        if is_valid(0, j) {
+            best[1][0][j][0] = Some((bin_score(0, j), 0, 0));
+        }
+    }
+
+    for k in 2..=bins_num {
+        for j in 1..=size {
+            for i in 1..j {
+                if !is_valid(i, j) {
+                    continue;
+                }
+
+                let score_last = bin_score(i, j);
+                let woe_last = bin_woe(i, j);
+
+                for h in 0..i {
+                    for phase_prev in 0..PHASES {
+                        let score_prev = match best[k - 1][h][i][phase_prev] {
+                            Some((score_prev, _, _)) => score_prev,
+                            None => continue,
+                        };
+
+                        let phase = match trend.map(|trend| trend.next_phase(
+                            phase_prev, bin_woe(h, i), woe_last
+                        )) {
+                            None => 0,
+                            Some(Some(phase)) => phase,
+                            Some(None) => continue,
+                        };
+
+                        // The first of equal candidates is kept
+                        let score_total = score_prev + score_last;
+                        let state = &mut best[k][i][j][phase];
+                        if state.is_none_or(|(s, _, _)| score_total > s) {
+                            *state = Some((score_total, h, phase_prev));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // The best final state over all the bin counts
+    let mut state: Option<(f64, usize, usize, usize)> = None;
+
+    for k in 1..=bins_num {
+        for i in 0..size {
+            for phase in 0..PHASES {
+                if trend.is_some_and(|trend| !trend.is_complete(phase)) {
+                    continue;
+                }
+                if let Some((score_total, _, _)) = best[k][i][size][phase] {
+                    if state.is_none_or(|(s, _, _, _)| score_total > s) {
+                        state = Some((score_total, k, i, phase));
+                    }
+                }
+            }
+        }
+    }
+
+    // Restore the bins
+    state.map(|(_, k, i, phase)| {
+        let mut ends = vec![size];
+        let (mut k, mut i, mut j, mut phase) = (k, i, size, phase);
+
+        while k > 1 {
+            let (_, h, phase_prev) = best[k][i][j][phase].unwrap();
+            ends.push(i);
+            (k, i, j, phase) = (k - 1, h, i, phase_prev);
+        }
+
+        ends.reverse();
+        ends
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::woe_binning_proc::calc_iv;
+
+    fn create_prebins() -> Vec<Prebin> {
+        [(10, 5), (8, 12), (15, 3), (4, 9), (12, 7), (6, 6)].iter()
+            .map(|&(n0, n1)| Prebin {
+                n: n0 + n1,
+                p0: n0 as f64 / 55.0,
+                p1: n1 as f64 / 42.0,
+            })
+            .collect()
+    }
+
+    fn score_partition(prebins: &[Prebin], ends: &[usize]) -> f64 {
+        let mut start = 0;
+        let mut total = 0.0;
+        for &end in ends {
+            let p0: f64 = prebins[start..end].iter().map(|b| b.p0).sum();
+            let p1: f64 = prebins[start..end].iter().map(|b| b.p1).sum();
+            total += calc_iv(p0, p1);
+            start = end;
+        }
+        total
+    }
+
+    #[test]
+    fn test_optimal_partition() {
+        let prebins = create_prebins();
+
+        let ends = optimal_partition(&prebins, 3, 0, None, calc_iv).unwrap();
+        let score = score_partition(&prebins, &ends);
+
+        // Brute force over all the partitions into 3 bins
+        for i in 1..6 {
+            for j in (i + 1)..6 {
+                let score_other = score_partition(&prebins, &[i, j, 6]);
+                assert!(score_other <= score + 1e-12);
+            }
+        }
+
+        assert_eq!(ends.len(), 3);
+    }
+
+    fn partition_woe(prebins: &[Prebin], ends: &[usize]) -> Vec<f64> {
+        let mut start = 0;
+        ends.iter()
+            .map(|&end| {
+                let bin = &prebins[start..end];
+                start = end;
+                calc_woe(bin.iter().map(|b| b.p0).sum(),
+                         bin.iter().map(|b| b.p1).sum())
+            })
+            .collect()
+    }
+
+    fn has_shape(woe: &[f64], up_first: bool) -> bool {
+        // Strictly one way, then strictly the other way, both non-empty
+        let ups: Vec<bool> = woe.windows(2).map(|w| w[0] < w[1]).collect();
+        let turn = ups.iter().position(|&up| up != up_first);
+        woe.windows(2).all(|w| w[0] != w[1]) && turn.is_some_and(|turn|
+            turn > 0 && ups[turn..].iter().all(|&up| up != up_first)
+        )
+    }
+
+    #[test]
+    fn test_optimal_partition_many_bins() {
+        // Huge bins_num is the same as one bin per pre-bin
+        let prebins = create_prebins();
+        assert_eq!(optimal_partition(&prebins, usize::MAX, 0, None, calc_iv),
+                   optimal_partition(&prebins, prebins.len(), 0, None, calc_iv));
+    }
+
+    #[test]
+    fn test_optimal_partition_shapes() {
+        let prebins = create_prebins();
+
+        for (trend, up_first) in [(Trend::Peak, true), (Trend::Valley, false)] {
+            let ends = optimal_partition(
+                &prebins, 4, 0, Some(trend), calc_iv
+            ).unwrap();
+            assert!(has_shape(&partition_woe(&prebins, &ends), up_first));
+
+            // Brute force over all the partitions into at most 4 bins
+            let score = score_partition(&prebins, &ends);
+            for mask in 0..32u32 {
+                let mut ends_other: Vec<usize> = (1..6)
+                    .filter(|i| mask & (1 << (i - 1)) != 0)
+                    .collect();
+                ends_other.push(6);
+                if ends_other.len() <= 4 && has_shape(
+                        &partition_woe(&prebins, &ends_other), up_first) {
+                    let score_other = score_partition(&prebins, &ends_other);
+                    assert!(score_other <= score + 1e-12);
+                }
+            }
+        }
+
+        // A peak needs at least 3 bins
+        assert_eq!(optimal_partition(&prebins, 2, 0, Some(Trend::Peak), 
+                                     calc_iv), None);
+    }
+
+    #[test]
+    fn test_optimal_partition_constraints() {
+        let prebins = create_prebins();
+
+        let ends = optimal_partition(
+            &prebins, 4, 20, Some(Trend::Ascending), calc_iv
+        ).unwrap();
+
+        let mut start = 0;
+        let mut woe_prev = f64::NEG_INFINITY;
+        for &end in ends.iter() {
+            let bin = &prebins[start..end];
+            let woe = calc_woe(bin.iter().map(|b| b.p0).sum(),
+                               bin.iter().map(|b| b.p1).sum());
+            assert!(bin.iter().map(|b| b.n).sum::<usize>() >= 20);
+            assert!(woe > woe_prev);
+            woe_prev = woe;
+            start = end;
+        }
+
+        assert_eq!(optimal_partition(&prebins, 4, 1000, None, calc_iv), None);
+    }
+}
@@ -0,0 +1,505 @@
+use std::collections::BTreeMap;
+
+use crate::scorecard::Scorecard;
+use crate::woe_binning_proc::WoeBinningProc;
+
+
+const PMML_NAMESPACE: &str = "http://www.dmg.org/PMML-4_4";
+
+
+// Bins of a feature as they go to PMML
+struct PmmlFeature<'a> {
+    name: &'a str,
+    is_numeric: bool,
+    bins: &'a [Vec<usize>],
+    results: Vec<f64>,
+    other_bin: Option<usize>,
+}
+
+
+pub struct PmmlExport {
+    default_value: f64,
+    null_value: Option<f64>,
+    value_map: BTreeMap<String, BTreeMap<usize, Option<String>>>,
+}
+
+
+impl PmmlExport {
+    pub fn new() -> Self {
+        Self {
+            default_value: 0.0,
+            null_value: None,
+            value_map: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_default(&mut self, default_value: f64) {
+        // Result for categories missing in bins without the other bin
+        self.default_value = default_value;
+    }
+
+    pub fn set_null(&mut self, null_value: Option<f64>) {
+        // Result for missing values if no bin has them, the default
+        // otherwise
+        self.null_value = null_value;
+    }
+
+    pub fn add_values(&mut self, field: &str,
+                      values: &[(usize, Option<String>)]) {
+        // PMML values of the field by values in series, None is
+        // a missing value. The values themselves are used by default.
+        self.value_map.entry(field.to_string()).or_default()
+            .extend(values.iter().cloned());
+    }
+
+    pub fn render_binnings(&self,
+                           binnings: &[(&str, &WoeBinningProc)]) -> String {
+        // Derived fields {field}_woe of WOE: Discretize for numeric
+        // binnings and MapValues for categorial ones
+        let bins: Vec<Vec<Vec<usize>>> = binnings.iter()
+            .map(|(_, wbp)| wbp.get_bins_array().unwrap())
+            .collect();
+        let features: Vec<PmmlFeature> = binnings.iter().zip(bins.iter())
+            .map(|((name, wbp), bins)| PmmlFeature {
+                name,
+                is_numeric: wbp.is_numeric(),
+                bins,
+                results: wbp.get_woe_array().unwrap(),
+                other_bin: wbp.get_other_bin(),
+            })
+            .collect();
+
+        let mut lines = self._render_header(&features);
+
+        lines.push("  <TransformationDictionary>".to_string());
+        for feature in features.iter() {
+            lines.extend(self._render_derived_field(feature));
+        }
+        lines.push("  </TransformationDictionary>".to_string());
+        lines.push("</PMML>".to_string());
+
+        lines.join("\n") + "\n"
+    }
+
+    pub fn render_scorecard(&self, scorecard: &Scorecard) -> String {
+        // Scorecard model with reason codes of the features, the baseline
+        // of a feature is its largest points, so reason codes go from
+        // the features losing most points
+        let features: Vec<PmmlFeature> = (0..scorecard.get_features_num())
+            .map(|idx| PmmlFeature {
+                name: scorecard.get_feature_name(idx),
+                is_numeric: scorecard.is_feature_numeric(idx),
+                bins: scorecard.get_feature_bins(idx),
+                results: scorecard.get_points(idx),
+                other_bin: scorecard.get_feature_other_bin(idx),
+            })
+            .collect();
+
+        let mut lines = self._render_header(&features);
+
+        lines.push(format!(
+            "  <Scorecard modelName=\"scorecard\" functionName=\"regression\" \
+             useReasonCodes=\"true\" reasonCodeAlgorithm=\"pointsBelow\" \
+             initialScore=\"{}\" baselineMethod=\"max\">",
+            scorecard.get_base_points()
+        ));
+
+        lines.push("    <MiningSchema>".to_string());
+        for feature in features.iter() {
+            lines.push(format!(
+                "      <MiningField name=\"{}\" usageType=\"active\"/>",
+                Self::_escape(feature.name)
+            ));
+        }
+        lines.push("    </MiningSchema>".to_string());
+
+        lines.push("    <Output>".to_string());
+        lines.push("      <OutputField name=\"score\" optype=\"continuous\" \
+                    dataType=\"double\" feature=\"predictedValue\"/>"
+                   .to_string());
+        for rank in 1..=features.len() {
+            lines.push(format!(
+                "      <OutputField name=\"reason_code_{}\" \
+                 optype=\"categorical\" dataType=\"string\" \
+                 feature=\"reasonCode\" rank=\"{}\"/>", rank, rank
+            ));
+        }
+        lines.push("    </Output>".to_string());
+
+        lines.push("    <Characteristics>".to_string());
+        for feature in features.iter() {
+            lines.extend(self._render_characteristic(feature));
+        }
+        lines.push("    </Characteristics>".to_string());
+
+        lines.push("  </Scorecard>".to_string());
+        lines.push("</PMML>".to_string());
+
+        lines.join("\n") + "\n"
+    }
+
+    fn _render_header(&self, features: &[PmmlFeature]) -> Vec<String> {
+        let mut lines = vec![
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+            format!("<PMML xmlns=\"{}\" version=\"4.4\">", PMML_NAMESPACE),
+            format!("  <Header><Application name=\"woebin\" \
+                     version=\"{}\"/></Header>", env!("CARGO_PKG_VERSION")),
+            format!("  <DataDictionary numberOfFields=\"{}\">",
+                    features.len()),
+        ];
+
+        for feature in features.iter() {
+            let name = Self::_escape(feature.name);
+            if feature.is_numeric {
+                lines.push(format!("    <DataField name=\"{}\" \
+                                    optype=\"continuous\" \
+                                    dataType=\"double\"/>", name));
+            } else {
+                lines.push(format!("    <DataField name=\"{}\" \
+                                    optype=\"categorical\" \
+                                    dataType=\"string\">", name));
+                for values in feature.bins.iter() {
+                    for &value in values.iter() {
+                        if let Some(value) = self._value(feature.name, value) {
+                            lines.push(format!("      <Value value=\"{}\"/>",
+                                               Self::_escape(&value)));
+                        }
+                    }
+                }
+                lines.push("    </DataField>".to_string());
+            }
+        }
+
+        lines.push("  </DataDictionary>".to_string());
+        lines
+    }
+
+    fn _render_derived_field(&self, feature: &PmmlFeature) -> Vec<String> {
+        let name = Self::_escape(feature.name);
+        let missing = self._missing_result(feature);
+        let mut lines = vec![format!(
+            "    <DerivedField name=\"{}_woe\" optype=\"continuous\" \
+             dataType=\"double\">", name
+        )];
+
+        if feature.is_numeric {
+            lines.push(format!(
+                "      <Discretize field=\"{}\" mapMissingTo=\"{}\" \
+                 dataType=\"double\">", name, missing
+            ));
+            for (left, right, result) in self._intervals(feature) {
+                let margins = match (&left, &right) {
+                    (None, None) => "closure=\"openOpen\"".to_string(),
+                    (None, Some(r)) => format!("closure=\"openOpen\" \
+                                                rightMargin=\"{}\"", r),
+                    (Some(l), None) => format!("closure=\"closedOpen\" \
+                                                leftMargin=\"{}\"", l),
+                    (Some(l), Some(r)) => format!("closure=\"closedOpen\" \
+                                                   leftMargin=\"{}\" \
+                                                   rightMargin=\"{}\"", l, r),
+                };
+                lines.push(format!(
+                    "        <DiscretizeBin binValue=\"{}\"><Interval {}/>\
+                     </DiscretizeBin>", result, margins
+                ));
+            }
+            lines.push("      </Discretize>".to_string());
+        } else {
+            lines.push(format!(
+                "      <MapValues outputColumn=\"woe\" mapMissingTo=\"{}\" \
+                 defaultValue=\"{}\" dataType=\"double\">",
+                missing, self._default_result(feature)
+            ));
+            lines.push(format!("        <FieldColumnPair field=\"{}\" \
+                                column=\"value\"/>", name));
+            lines.push("        <InlineTable>".to_string());
+            for (values, result) in feature.bins.iter()
+                    .zip(feature.results.iter()) {
+                for &value in values.iter() {
+                    if let Some(value) = self._value(feature.name, value) {
+                        lines.push(format!(
+                            "          <row><value>{}</value>\
+                             <woe>{}</woe></row>",
+                            Self::_escape(&value), result
+                        ));
+                    }
+                }
+            }
+            lines.push("        </InlineTable>".to_string());
+            lines.push("      </MapValues>".to_string());
+        }
+
+        lines.push("    </DerivedField>".to_string());
+        lines
+    }
+
+    fn _render_characteristic(&self, feature: &PmmlFeature) -> Vec<String> {
+        let name = Self::_escape(feature.name);
+        let baseline = feature.results.iter().cloned()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let mut lines = vec![format!(
+            "      <Characteristic name=\"{}_points\" reasonCode=\"{}\" \
+             baselineScore=\"{}\">", name, name, baseline
+        )];
+
+        // Missing values go first, PMML takes the first matching attribute
+        lines.push(format!(
+            "        <Attribute partialScore=\"{}\"><SimplePredicate \
+             field=\"{}\" operator=\"isMissing\"/></Attribute>",
+            self._missing_result(feature), name
+        ));
+
+        if feature.is_numeric {
+            for (left, right, result) in self._intervals(feature) {
+                let predicate = match (&left, &right) {
+                    (None, None) => "<True/>".to_string(),
+                    (None, Some(r)) => Self::_predicate(&name, "lessThan", r),
+                    (Some(l), None) => Self::_predicate(&name,
+                                                        "greaterOrEqual", l),
+                    (Some(l), Some(r)) => format!(
+                        "<CompoundPredicate booleanOperator=\"and\">{}{}\
+                         </CompoundPredicate>",
+                        Self::_predicate(&name, "greaterOrEqual", l),
+                        Self::_predicate(&name, "lessThan", r)
+                    ),
+                };
+                lines.push(format!("        <Attribute partialScore=\"{}\">\
+                                    {}</Attribute>", result, predicate));
+            }
+        } else {
+            for (values, result) in feature.bins.iter()
+                    .zip(feature.results.iter()) {
+                let values: Vec<String> = values.iter()
+                    .filter_map(|&v| self._value(feature.name, v))
+                    .map(|v| format!("\"{}\"", Self::_escape(
+                        &v.replace('\\', "\\\\").replace('"', "\\\"")
+                    )))
+                    .collect();
+                if !values.is_empty() {
+                    lines.push(format!(
+                        "        <Attribute partialScore=\"{}\">\
+                         <SimpleSetPredicate field=\"{}\" \
+                         booleanOperator=\"isIn\"><Array n=\"{}\" \
+                         type=\"string\">{}</Array></SimpleSetPredicate>\
+                         </Attribute>",
+                        result, name, values.len(), values.join(" ")
+                    ));
+                }
+            }
+            lines.push(format!("        <Attribute partialScore=\"{}\">\
+                                <True/></Attribute>",
+                               self._default_result(feature)));
+        }
+
+        lines.push("      </Characteristic>".to_string());
+        lines
+    }
+
+    fn _intervals(&self, feature: &PmmlFeature) ->
+                Vec<(Option<String>, Option<String>, f64)> {
+        // Margins of numeric bins: a bin goes up to the smallest value of
+        // the next one, the first and the last bins are unbounded.
+        // Missing values are not in bins.
+        let bins: Vec<(Vec<String>, f64)> = feature.bins.iter()
+            .zip(feature.results.iter())
+            .map(|(values, &result)| (
+                values.iter()
+                    .filter_map(|&v| self._value(feature.name, v))
+                    .collect::<Vec<String>>(),
+                result
+            ))
+            .filter(|(values, _)| !values.is_empty())
+            .collect();
+
+        (0..bins.len()).map(|idx| (
+            if idx > 0 { Some(bins[idx].0[0].clone()) } else { None },
+            bins.get(idx + 1).map(|bin| bin.0[0].clone()),
+            bins[idx].1,
+        )).collect()
+    }
+
+    fn _missing_result(&self, feature: &PmmlFeature) -> f64 {
+        feature.bins.iter().zip(feature.results.iter())
+            .find(|(values, _)|
+                values.iter().any(|&v| self._value(feature.name, v).is_none())
+            )
+            .map(|(_, &result)| result)
+            .or(self.null_value)
+            .unwrap_or(self._default_result(feature))
+    }
+
+    fn _default_result(&self, feature: &PmmlFeature) -> f64 {
+        // Unseen categories go to the other bin if there is one
+        feature.other_bin.map_or(self.default_value,
+                                 |idx| feature.results[idx])
+    }
+
+    fn _value(&self, field: &str, value: usize) -> Option<String> {
+        self.value_map.get(field)
+            .and_then(|values| values.get(&value))
+            .cloned()
+            .unwrap_or(Some(value.to_string()))
+    }
+
+    fn _predicate(field: &str, operator: &str, value: &str) -> String {
+        format!("<SimplePredicate field=\"{}\" operator=\"{}\" value=\"{}\"/>",
+                field, operator, Self::_escape(value))
+    }
+
+    fn _escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+}
+
+
+impl Default for PmmlExport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_wbp(series: &[usize], is_numeric: bool) -> WoeBinningProc {
+        let target: Vec<bool> = series.iter().map(|&v| v % 3 == 0).collect();
+        let mut wbp = WoeBinningProc::new(3, 1.0);
+        if is_numeric {
+            wbp.process_numeric(series, &target);
+        } else {
+            wbp.process_categorial(series, &target);
+        }
+        wbp
+    }
+
+    fn assert_balanced(pmml: &str, tags: &[&str]) {
+        for tag in tags {
+            let opened = pmml.matches(&format!("<{}>", tag)).count() +
+                         pmml.matches(&format!("<{} ", tag)).count();
+            let closed = pmml.matches(&format!("</{}>", tag)).count();
+            let empty = pmml.split(&format!("<{} ", tag)).skip(1)
+                .filter(|rest| rest.split('>').next().unwrap().ends_with('/'))
+                .count();
+            assert_eq!(opened, closed + empty, "Unbalanced {}", tag);
+        }
+    }
+
+    fn assert_valid(pmml: &str) {
+        // Validation against the schema, skipped without xmllint
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let schema = concat!(env!("CARGO_MANIFEST_DIR"),
+                             "/tests/pmml/pmml-4-4-subset.xsd");
+        let child = Command::new("xmllint")
+            .args(["--noout", "--schema", schema, "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(_) => {
+                eprintln!("xmllint is not found, PMML is not validated");
+                return;
+            },
+        };
+
+        child.stdin.take().unwrap().write_all(pmml.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "Invalid PMML: {}\n{}",
+                String::from_utf8_lossy(&output.stderr), pmml);
+    }
+
+    #[test]
+    fn test_render_binnings() {
+        let series: Vec<usize> = (0..100).map(|i| i % 10).collect();
+        let wbp_numeric = create_wbp(&series, true);
+        let wbp_categorial = create_wbp(&series, false);
+        let bins = wbp_numeric.get_bins_array().unwrap();
+        let woe = wbp_numeric.get_woe_array().unwrap();
+
+        let mut export = PmmlExport::new();
+        export.add_values("c", &[(0, None), (1, Some("a<b".to_string()))]);
+        let pmml = export.render_binnings(&[("x", &wbp_numeric),
+                                            ("c", &wbp_categorial)]);
+
+        assert!(pmml.contains(&format!(
+            "<DiscretizeBin binValue=\"{}\"><Interval closure=\"closedOpen\" \
+             leftMargin=\"{}\" rightMargin=\"{}\"/></DiscretizeBin>",
+            woe[1], bins[1][0], bins[2][0]
+        )));
+        assert!(pmml.contains("<value>a&lt;b</value>"));
+        assert_eq!(pmml.matches("<row>").count(), 9);
+        assert_eq!(pmml.matches("<Value ").count(), 9);
+        assert_balanced(&pmml, &["PMML", "DataDictionary", "DataField",
+                                 "TransformationDictionary", "DerivedField",
+                                 "Discretize", "MapValues", "InlineTable"]);
+        assert_valid(&pmml);
+    }
+
+    #[test]
+    fn test_render_other_bin() {
+        // Unseen and missing categories go to the bin of the rare ones
+        let mut series: Vec<usize> = (0..100).map(|i| i % 10).collect();
+        series.extend([10, 11]);
+        let target: Vec<bool> = series.iter().map(|&v| v % 3 == 0).collect();
+        let mut wbp = WoeBinningProc::new(3, 1.0);
+        wbp.set_rare_level(2, 0.0);
+        wbp.process_categorial(&series, &target);
+        let other = wbp.get_other_bin().unwrap();
+        let woe_other = wbp.get_woe_array().unwrap()[other];
+
+        let export = PmmlExport::new();
+        let pmml = export.render_binnings(&[("c", &wbp)]);
+        assert!(pmml.contains(&format!(
+            "<MapValues outputColumn=\"woe\" mapMissingTo=\"{}\" \
+             defaultValue=\"{}\"", woe_other, woe_other
+        )));
+        assert_valid(&pmml);
+
+        let mut scorecard = Scorecard::new(-1.0);
+        scorecard.add_feature("c", &wbp, -1.0);
+        let points_other = scorecard.get_points(0)[other];
+        let pmml = export.render_scorecard(&scorecard);
+        assert!(pmml.contains(&format!(
+            "<Attribute partialScore=\"{}\"><True/></Attribute>",
+            points_other
+        )));
+        assert_valid(&pmml);
+    }
+
+    #[test]
+    fn test_render_scorecard() {
+        let series: Vec<usize> = (0..100).map(|i| i % 10).collect();
+        let wbp_numeric = create_wbp(&series, true);
+        let wbp_categorial = create_wbp(&series, false);
+
+        let mut scorecard = Scorecard::new(-1.0);
+        scorecard.add_feature("x", &wbp_numeric, -0.5);
+        scorecard.add_feature("y", &wbp_categorial, -1.0);
+
+        let mut export = PmmlExport::new();
+        export.set_null(Some(-5.0));
+        let pmml = export.render_scorecard(&scorecard);
+
+        assert!(pmml.contains(&format!("initialScore=\"{}\"",
+                                       scorecard.get_base_points())));
+        assert_eq!(pmml.matches("feature=\"reasonCode\"").count(), 2);
+        assert_eq!(pmml.matches("operator=\"isMissing\"").count(), 2);
+        assert!(pmml.contains("<Attribute partialScore=\"-5\"><SimplePredicate \
+                               field=\"x\" operator=\"isMissing\"/>"));
+        assert_eq!(pmml.matches("<Attribute ").count(), 2 + 3 + 3 + 1);
+        assert_balanced(&pmml, &["PMML", "Scorecard", "MiningSchema",
+                                 "Output", "Characteristics",
+                                 "Characteristic", "Attribute",
+                                 "CompoundPredicate", "SimpleSetPredicate",
+                                 "Array"]);
+        assert_valid(&pmml);
+    }
+}
@@ -0,0 +1,97 @@
+use std::ffi::{CStr, CString, c_char};
+
+use crate::pmml_export::PmmlExport;
+use crate::scorecard_wrapper::ScorecardWrapper;
+use crate::woe_binning_proc_wrapper::WoeBinningProcWrapper;
+
+
+struct PmmlExportWrapper {
+    export: PmmlExport,
+}
+
+
+impl PmmlExportWrapper {
+    #[export_name="pmml_new"]
+    pub extern "C" fn new() -> Box<Self> {
+        Box::new(Self {
+            export: PmmlExport::new()
+        })
+    }
+
+    #[export_name="pmml_set_default"]
+    pub extern "C" fn set_default(&mut self, default_value: f64) {
+        self.export.set_default(default_value);
+    }
+
+    #[export_name="pmml_set_null"]
+    pub extern "C" fn set_null(&mut self, has_null_value: bool, 
+                               null_value: f64) {
+        self.export.set_null(has_null_value.then_some(null_value));
+    }
+
+    #[export_name="pmml_add_values"]
+    pub extern "C" fn add_values(&mut self, field: *const c_char, 
+                                 size: usize, values: *const usize, 
+                                 texts: *const *const c_char) {
+        // A null text is a missing value
+        let values: Vec<(usize, Option<String>)> = (0..size)
+            .map(|idx| unsafe {
+                let text = *texts.add(idx);
+                (*values.add(idx), 
+                 (!text.is_null()).then(|| Self::_to_string(text)))
+            })
+            .collect();
+
+        let field = unsafe {
+            Self::_to_string(field)
+        };
+        self.export.add_values(&field, &values);
+    }
+
+    #[export_name="pmml_render_binnings"]
+    pub extern "C" fn render_binnings(&self, size: usize, 
+                                      fields: *const *const c_char, 
+                                      wbps: *const &WoeBinningProcWrapper) -> 
+                *mut c_char {
+        // The string must be released by pmml_free_string, null if the 
+        // PMML has a NUL byte
+        let fields: Vec<String> = (0..size)
+            .map(|idx| unsafe { Self::_to_string(*fields.add(idx)) })
+            .collect();
+        let binnings: Vec<(&str, &_)> = (0..size)
+            .map(|idx| unsafe { (fields[idx].as_str(), &(*wbps.add(idx)).wbp) })
+            .collect();
+        let pmml = self.export.render_binnings(&binnings);
+        Self::_into_raw(pmml)
+    }
+
+    #[export_name="pmml_render_scorecard"]
+    pub extern "C" fn render_scorecard(&self, sc: &ScorecardWrapper) -> 
+                *mut c_char {
+        // The string must be released by pmml_free_string, null if the 
+        // PMML has a NUL byte
+        let pmml = self.export.render_scorecard(&sc.sc);
+        Self::_into_raw(pmml)
+    }
+
+    fn _into_raw(pmml: String) -> *mut c_char {
+        CString::new(pmml).map_or(std::ptr::null_mut(), CString::into_raw)
+    }
+
+    unsafe fn _to_string(text: *const c_char) -> String {
+        unsafe {
+            CStr::from_ptr(text).to_string_lossy().into_owned()
+        }
+    }
+}
+
+
+#[export_name="pmml_free_string"]
+extern "C" fn free_string(pmml: *mut c_char) {
+    if pmml.is_null() {
+        return;
+    }
+    unsafe {
+        drop(CString::from_raw(pmml));
+    }
+}
@@ -0,0 +1,655 @@
+use std::borrow::Cow;
+
+use numpy::{PyArray1, PyArrayMethods, PyReadonlyArray1};
+use pyo3::exceptions::{PyRuntimeError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::optimal_binning::Trend;
+use crate::woe_binning_proc::{BinOrder, WoeBinningProc};
+
+
+fn trend_name(trend: Trend) -> &'static str {
+    match trend {
+        Trend::Ascending => "ascending",
+        Trend::Descending => "descending",
+        Trend::Peak => "peak",
+        Trend::Valley => "valley",
+    }
+}
+
+
+// Values of series as the binning sees them. Contiguous uint64 arrays, 
+// int64 arrays of non-negative values (on 64-bit platforms) and float64 
+// arrays are borrowed from NumPy, floats are ranked without copying them. 
+// Other dtypes and pandas codes are converted in Rust without Python 
+// objects.
+enum Encoding {
+    // Values themselves
+    Identity,
+    // Ranks of distinct integers (some of them are negative)
+    IntRanks(Vec<i64>),
+    // Ranks of distinct floats, NaN is the last one
+    FloatRanks(Vec<f64>),
+    // Codes of a pandas categorical plus one, zero is missing
+    Categories(Vec<Py<PyAny>>),
+}
+
+
+enum RawSeries<'a> {
+    Unsigned(Cow<'a, [usize]>),
+    Signed(Cow<'a, [i64]>),
+    Float(Cow<'a, [f64]>),
+    Categorical(Cow<'a, [i64]>, Vec<Py<PyAny>>),
+}
+
+
+// Borrows of the arrays a series is read from
+#[derive(Default)]
+struct SeriesGuard<'py> {
+    unsigned: Option<PyReadonlyArray1<'py, u64>>,
+    signed: Option<PyReadonlyArray1<'py, i64>>,
+    float: Option<PyReadonlyArray1<'py, f64>>,
+}
+
+
+fn rank_of<T: PartialOrd>(distinct: &[T], value: &T, is_numeric: bool) -> usize {
+    // Numeric values between the known ones take the smaller one (less
+    // than all take the first), unknown categorial values get usize::MAX
+    // that is missing in bins
+    let count = distinct.partition_point(|d| d <= value);
+    if is_numeric {
+        count.max(1) - 1
+    } else if count > 0 && distinct[count - 1] == *value {
+        count - 1
+    } else {
+        usize::MAX
+    }
+}
+
+
+#[cfg(target_pointer_width = "64")]
+fn as_usize_slice(values: &[u64]) -> PyResult<Cow<'_, [usize]>> {
+    // The same layout on 64-bit platforms, so the values are not copied
+    Ok(Cow::Borrowed(unsafe {
+        std::slice::from_raw_parts(values.as_ptr() as *const usize, values.len())
+    }))
+}
+
+
+#[cfg(not(target_pointer_width = "64"))]
+fn as_usize_slice(values: &[u64]) -> PyResult<Cow<'_, [usize]>> {
+    use pyo3::exceptions::PyOverflowError;
+
+    // usize is narrower than u64, so the values are copied and checked
+    values.iter()
+        .map(|&v| usize::try_from(v).map_err(|_| PyOverflowError::new_err(
+            format!("Value {} does not fit usize", v)
+        )))
+        .collect::<PyResult<Vec<usize>>>()
+        .map(Cow::Owned)
+}
+
+
+fn as_unsigned(values: &[i64]) -> Option<&[u64]> {
+    // Non-negative i64 values have the same bits as u64 ones
+    values.iter().all(|&v| v >= 0).then(|| unsafe {
+        std::slice::from_raw_parts(values.as_ptr() as *const u64, values.len())
+    })
+}
+
+
+fn borrow_array<'a, 'py, T>(array: &Bound<'py, PyAny>,
+                            guard: &'a mut Option<PyReadonlyArray1<'py, T>>) ->
+                            PyResult<Option<&'a [T]>>
+        where T: numpy::Element {
+    // Contiguous 1D array of the dtype T if it is, the guard keeps 
+    // the borrow of the array
+    match array.cast::<PyArray1<T>>() {
+        Ok(array) => {
+            *guard = Some(array.try_readonly()?);
+            let guard: &'a Option<PyReadonlyArray1<'py, T>> = guard;
+            let slice = guard.as_ref().unwrap().as_slice()
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Ok(Some(slice))
+        },
+        Err(_) => Ok(None),
+    }
+}
+
+
+fn read_array<'py, T, U, F>(array: &'py Bound<'py, PyAny>,
+                            convert: F) -> PyResult<Option<Vec<U>>>
+        where T: numpy::Element, F: Fn(&[T]) -> Vec<U> {
+    // Contiguous 1D array of the dtype T if it is, converted
+    match array.cast::<PyArray1<T>>() {
+        Ok(array) => {
+            let array = array.try_readonly()?;
+            let slice = array.as_slice()
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Ok(Some(convert(slice)))
+        },
+        Err(_) => Ok(None),
+    }
+}
+
+
+fn read_series<'a, 'py>(series: &Bound<'py, PyAny>,
+                        guard: &'a mut SeriesGuard<'py>) ->
+                        PyResult<RawSeries<'a>> {
+    let py = series.py();
+
+    // pandas categorical (a Series with .cat or a Categorical)
+    let categorical = if series.hasattr("cat")? {
+        Some(series.getattr("cat")?)
+    } else if series.hasattr("codes")? && series.hasattr("categories")? {
+        Some(series.clone())
+    } else {
+        None
+    };
+
+    if let Some(categorical) = categorical {
+        let numpy = py.import("numpy")?;
+        let codes = numpy.call_method1(
+            "asarray", (categorical.getattr("codes")?, "int64")
+        )?;
+        let codes = borrow_array(&codes, &mut guard.signed)?.unwrap();
+        let categories: Vec<Py<PyAny>> = categorical.getattr("categories")?
+            .call_method0("tolist")?
+            .extract()?;
+        return Ok(RawSeries::Categorical(Cow::Borrowed(codes), categories));
+    }
+
+    let array = py.import("numpy")?.call_method1("ascontiguousarray", (series,))?;
+
+    // Zero-copy on 64-bit platforms
+    if let Some(values) = borrow_array(&array, &mut guard.unsigned)? {
+        return Ok(RawSeries::Unsigned(as_usize_slice(values)?));
+    }
+
+    if let Some(values) = borrow_array(&array, &mut guard.signed)? {
+        // Non-negative ones are the same as unsigned
+        return Ok(match as_unsigned(values) {
+            Some(values) => RawSeries::Unsigned(as_usize_slice(values)?),
+            None => RawSeries::Signed(Cow::Borrowed(values)),
+        });
+    }
+
+    if let Some(values) = borrow_array(&array, &mut guard.float)? {
+        return Ok(RawSeries::Float(Cow::Borrowed(values)));
+    }
+
+    macro_rules! read_as {
+        ($t:ty, $variant:ident, $target:ty) => {
+            if let Some(values) = read_array::<$t, _, _>(
+                &array, |s| s.iter().map(|&v| v as $target).collect()
+            )? {
+                return Ok(RawSeries::$variant(Cow::Owned(values)));
+            }
+        };
+    }
+
+    read_as!(f32, Float, f64);
+    read_as!(i32, Signed, i64);
+    read_as!(i16, Signed, i64);
+    read_as!(i8, Signed, i64);
+
+    read_as!(u32, Unsigned, usize);
+    read_as!(u16, Unsigned, usize);
+    read_as!(u8, Unsigned, usize);
+    read_as!(bool, Unsigned, usize);
+
+    Err(PyTypeError::new_err(format!(
+        "Series of dtype {} is not supported, use a numeric array or \
+         a pandas categorical", array.getattr("dtype")?
+    )))
+}
+
+
+fn read_target<'a, 'py>(target: &Bound<'py, PyAny>,
+                        guard: &'a mut Option<PyReadonlyArray1<'py, bool>>) ->
+                        PyResult<Cow<'a, [bool]>> {
+    let numpy = target.py().import("numpy")?;
+    let array = numpy.call_method1("ascontiguousarray", (target,))?;
+
+    if let Ok(array) = array.cast::<PyArray1<bool>>() {
+        *guard = Some(array.try_readonly()?);
+        let guard: &'a Option<PyReadonlyArray1<'py, bool>> = guard;
+        let slice = guard.as_ref().unwrap().as_slice()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        return Ok(Cow::Borrowed(slice));
+    }
+
+    let array = numpy.call_method1("not_equal", (array, 0))?;
+    let values = read_array::<bool, _, _>(&array, |s| s.to_vec())?
+        .ok_or_else(|| PyTypeError::new_err("Target must be numeric"))?;
+    Ok(Cow::Owned(values))
+}
+
+
+fn sorted_distinct<T: Copy, F>(values: &[T], cmp: F) -> Vec<T>
+        where F: Fn(&T, &T) -> std::cmp::Ordering {
+    let mut distinct = values.to_vec();
+    distinct.sort_by(&cmp);
+    distinct.dedup_by(|a, b| cmp(a, b).is_eq());
+    distinct
+}
+
+
+#[pyclass(name = "WoeBinningProc", module = "woebin._woebin")]
+struct PyWoeBinningProc {
+    wbp: Option<WoeBinningProc>,
+    encoding: Encoding,
+}
+
+
+impl PyWoeBinningProc {
+    fn _wbp(&self) -> PyResult<&WoeBinningProc> {
+        self.wbp.as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("Binning is not processed"))
+    }
+
+    fn _label(&self, py: Python<'_>, value: usize) -> PyResult<Py<PyAny>> {
+        Ok(match &self.encoding {
+            Encoding::Identity => value.into_pyobject(py)?.into_any().unbind(),
+            Encoding::IntRanks(distinct) =>
+                distinct[value].into_pyobject(py)?.into_any().unbind(),
+            Encoding::FloatRanks(distinct) =>
+                distinct[value].into_pyobject(py)?.into_any().unbind(),
+            Encoding::Categories(categories) => match value {
+                0 => py.None(),
+                _ => categories[value - 1].clone_ref(py),
+            },
+        })
+    }
+
+    fn _encode<'a, 'py>(&self, series: &Bound<'py, PyAny>,
+                        guard: &'a mut SeriesGuard<'py>) ->
+                        PyResult<Cow<'a, [usize]>> {
+        // New values in the encoding of the processed series
+        let is_numeric = self._wbp()?.is_numeric();
+        let raw = read_series(series, guard)?;
+
+        Ok(match (&self.encoding, raw) {
+            (Encoding::Identity, RawSeries::Unsigned(values)) => values,
+            (Encoding::Identity, RawSeries::Signed(values)) => Cow::Owned(
+                values.iter()
+                    .map(|&v| if v >= 0 { v as usize }
+                              else if is_numeric { 0 }
+                              else { usize::MAX })
+                    .collect()
+            ),
+            (Encoding::IntRanks(distinct), RawSeries::Unsigned(values)) =>
+                Cow::Owned(values.iter()
+                    .map(|&v| rank_of(distinct, &(v as i64), is_numeric))
+                    .collect()),
+            (Encoding::IntRanks(distinct), RawSeries::Signed(values)) =>
+                Cow::Owned(values.iter()
+                    .map(|v| rank_of(distinct, v, is_numeric))
+                    .collect()),
+            (Encoding::FloatRanks(distinct), RawSeries::Unsigned(values)) =>
+                Cow::Owned(values.iter()
+                    .map(|&v| rank_of(distinct, &(v as f64), is_numeric))
+                    .collect()),
+            (Encoding::FloatRanks(distinct), RawSeries::Signed(values)) =>
+                Cow::Owned(values.iter()
+                    .map(|&v| rank_of(distinct, &(v as f64), is_numeric))
+                    .collect()),
+            (Encoding::FloatRanks(distinct), RawSeries::Float(values)) => {
+                // NaN compares with nothing, so it is looked up separately
+                let nan_rank = distinct.iter().position(|d| d.is_nan())
+                    .unwrap_or(usize::MAX);
+                let known: Vec<f64> = distinct.iter().cloned()
+                    .filter(|d| !d.is_nan())
+                    .collect();
+                Cow::Owned(values.iter()
+                    .map(|v| if v.is_nan() { nan_rank }
+                             else { rank_of(&known, v, is_numeric) })
+                    .collect())
+            },
+            (Encoding::Categories(fitted),
+             RawSeries::Categorical(codes, categories)) => {
+                // Categories are matched by Python equality
+                let py = series.py();
+                let positions = PyDict::new(py);
+                for (idx, category) in fitted.iter().enumerate() {
+                    positions.set_item(category, idx + 1)?;
+                }
+                let map: Vec<usize> = categories.iter()
+                    .map(|c| Ok(positions.get_item(c)?
+                        .map(|p| p.extract()).transpose()?
+                        .unwrap_or(usize::MAX)))
+                    .collect::<PyResult<Vec<usize>>>()?;
+                Cow::Owned(codes.iter()
+                    .map(|&c| if c < 0 { 0 } else { map[c as usize] })
+                    .collect())
+            },
+            _ => return Err(PyTypeError::new_err(
+                "Series type differs from the processed one"
+            )),
+        })
+    }
+}
+
+
+#[pymethods]
+impl PyWoeBinningProc {
+    #[new]
+    fn new() -> Self {
+        Self {
+            wbp: None,
+            encoding: Encoding::Identity,
+        }
+    }
+
+    #[pyo3(signature = (series, target, bins=10, is_numeric=false,
+                        smooth=1.0, bin_order="woe", low_memory=false,
+                        exact=false, min_bin_size=0, trend=None,
+                        max_prebins=100, ordered=false))]
+    #[allow(clippy::too_many_arguments)]
+    fn process(&mut self, py: Python<'_>, series: &Bound<'_, PyAny>,
+               target: &Bound<'_, PyAny>, bins: usize, is_numeric: bool,
+               smooth: f64, bin_order: &str, low_memory: bool, exact: bool,
+               min_bin_size: usize, trend: Option<&str>, max_prebins: usize,
+               ordered: bool) -> PyResult<()> {
+        // The same as WoeBinningProc.process of the ctypes module, but
+        // series is a NumPy array (or anything numpy.asarray takes) or
+        // a pandas categorical
+        let bin_order = match bin_order {
+            "value" => BinOrder::Value,
+            "woe" => BinOrder::Woe,
+            "count" => BinOrder::Count,
+            _ => return Err(PyValueError::new_err(
+                format!("Unknown bin order: {}", bin_order)
+            )),
+        };
+        // "auto" takes the trend with maximal IV
+        let auto_trend = trend == Some("auto");
+        let trend = match trend {
+            None | Some("auto") => None,
+            Some("ascending") => Some(Trend::Ascending),
+            Some("descending") => Some(Trend::Descending),
+            Some("peak") => Some(Trend::Peak),
+            Some("valley") => Some(Trend::Valley),
+            Some(trend) => return Err(PyValueError::new_err(
+                format!("Unknown trend: {}", trend)
+            )),
+        };
+        if exact && !is_numeric && !ordered {
+            return Err(PyValueError::new_err(
+                "Exact binning is for numeric or ordered categorial only"
+            ));
+        }
+        if auto_trend && !exact {
+            return Err(PyValueError::new_err(
+                "trend=\"auto\" requires exact=True"
+            ));
+        }
+
+        let mut series_guard = SeriesGuard::default();
+        let mut target_guard = None;
+        let raw = read_series(series, &mut series_guard)?;
+        let target = read_target(target, &mut target_guard)?;
+
+        let (encoding, series): (Encoding, Cow<[usize]>) = match raw {
+            RawSeries::Unsigned(values) => (Encoding::Identity, values),
+            RawSeries::Signed(values) => {
+                let distinct = sorted_distinct(&values, |a, b| a.cmp(b));
+                let series = values.iter()
+                    .map(|v| distinct.binary_search(v).unwrap())
+                    .collect();
+                (Encoding::IntRanks(distinct), Cow::Owned(series))
+            },
+            RawSeries::Float(values) => {
+                if is_numeric && values.iter().any(|v| v.is_nan()) {
+                    return Err(PyTypeError::new_err("nan is not numeric"));
+                }
+                let distinct = sorted_distinct(&values, |a, b| a.total_cmp(b));
+                let series = values.iter()
+                    .map(|v| distinct.binary_search_by(|d| d.total_cmp(v))
+                        .unwrap())
+                    .collect();
+                (Encoding::FloatRanks(distinct), Cow::Owned(series))
+            },
+            RawSeries::Categorical(codes, categories) => {
+                if is_numeric && codes.iter().any(|&c| c < 0) {
+                    return Err(PyTypeError::new_err("None is not numeric"));
+                }
+                let series = codes.iter().map(|&c| (c + 1) as usize).collect();
+                (Encoding::Categories(categories), Cow::Owned(series))
+            },
+        };
+
+        if series.len() != target.len() {
+            return Err(PyValueError::new_err(
+                "Series and target have different lengths"
+            ));
+        }
+
+        let mut wbp = WoeBinningProc::new(bins, smooth);
+        wbp.set_bin_order(bin_order);
+        wbp.set_low_memory(low_memory);
+        wbp.set_min_bin_size(min_bin_size);
+        wbp.set_trend(trend);
+        wbp.set_auto_trend(auto_trend);
+        wbp.set_max_prebins(max_prebins);
+
+        // The binning runs without the GIL
+        py.detach(|| {
+            if is_numeric && exact {
+                wbp.process_numeric_exact(&series, &target);
+            } else if is_numeric {
+                wbp.process_numeric(&series, &target);
+            } else if ordered && exact {
+                wbp.process_categorial_ordered_exact(&series, &target);
+            } else if ordered {
+                wbp.process_categorial_ordered(&series, &target);
+            } else {
+                wbp.process_categorial(&series, &target);
+            }
+        });
+
+        self.wbp = Some(wbp);
+        self.encoding = encoding;
+
+        Ok(())
+    }
+
+    fn set_bins_num(&mut self, bins: usize) -> PyResult<()> {
+        self._wbp()?;
+        self.wbp.as_mut().unwrap().set_bins_num(bins);
+        Ok(())
+    }
+
+    fn get_bins_info<'py>(&self, py: Python<'py>) ->
+                         PyResult<Bound<'py, PyList>> {
+        let wbp = self._wbp()?;
+        let bins = wbp.get_bins_array().unwrap();
+        let woe = wbp.get_woe_array().unwrap();
+        let iv = wbp.get_iv_array().unwrap();
+        let locked = wbp.get_locked_array().unwrap();
+
+        let bins_info = PyList::empty(py);
+
+        for idx in 0..bins.len() {
+            let values = bins[idx].iter()
+                .map(|&v| self._label(py, v))
+                .collect::<PyResult<Vec<Py<PyAny>>>>()?;
+            let info = PyDict::new(py);
+            info.set_item("woe", woe[idx])?;
+            info.set_item("iv", iv[idx])?;
+            info.set_item("values", values)?;
+            info.set_item("locked", locked[idx])?;
+            bins_info.append(info)?;
+        }
+
+        Ok(bins_info)
+    }
+
+    fn get_woe_map<'py>(&self, py: Python<'py>) ->
+                       PyResult<Bound<'py, PyDict>> {
+        let wbp = self._wbp()?;
+        let woe_map = PyDict::new(py);
+
+        for (values, woe) in wbp.get_bins_array().unwrap().iter()
+                .zip(wbp.get_woe_array().unwrap()) {
+            for &value in values.iter() {
+                woe_map.set_item(self._label(py, value)?, woe)?;
+            }
+        }
+
+        Ok(woe_map)
+    }
+
+    fn get_iv_total(&self) -> PyResult<f64> {
+        Ok(self._wbp()?.get_iv_total().unwrap())
+    }
+
+    fn get_trend(&self) -> PyResult<Option<&'static str>> {
+        // Trend of exact binning, the detected one for trend="auto"
+        Ok(self._wbp()?.get_trend().map(trend_name))
+    }
+
+    fn get_trend_ivs<'py>(&self, py: Python<'py>) ->
+                          PyResult<Bound<'py, PyDict>> {
+        // IV of exact binning by trend for trend="auto", None for a trend
+        // without a feasible binning
+        let ivs = PyDict::new(py);
+        for (trend, iv) in self._wbp()?.get_trend_ivs().unwrap_or_default() {
+            ivs.set_item(trend_name(trend), iv)?;
+        }
+        Ok(ivs)
+    }
+
+    fn is_feasible(&self) -> PyResult<bool> {
+        // False if no exact binning satisfies min_bin_size and the trend,
+        // then all values are in one bin
+        Ok(self._wbp()?.is_feasible())
+    }
+
+    fn get_iv_gap(&self) -> PyResult<f64> {
+        // NaN for greedy binning
+        Ok(self._wbp()?.get_iv_gap().unwrap_or(f64::NAN))
+    }
+
+    fn transform<'py>(&self, py: Python<'py>,
+                      series: &Bound<'py, PyAny>) ->
+                      PyResult<Bound<'py, PyArray1<f64>>> {
+        // WOE as a NumPy array, numeric values between bins go to the
+        // left one, unknown categorial values get zero
+        let mut guard = SeriesGuard::default();
+        let values = self._encode(series, &mut guard)?;
+        let wbp = self._wbp()?;
+        let woe = py.detach(|| wbp.transform(&values));
+        Ok(PyArray1::from_vec(py, woe))
+    }
+
+    #[pyo3(signature = (series=None, target=None))]
+    fn get_metrics<'py>(&self, py: Python<'py>,
+                        series: Option<&Bound<'py, PyAny>>,
+                        target: Option<&Bound<'py, PyAny>>) ->
+                        PyResult<Bound<'py, PyDict>> {
+        // AUC, Gini, KS and divergence of WOE on the processed data or
+        // on the given sample
+        let wbp = self._wbp()?;
+        let metrics = match (series, target) {
+            (None, None) => wbp.get_metrics().unwrap(),
+            (Some(series), Some(target)) => {
+                let mut series_guard = SeriesGuard::default();
+                let mut target_guard = None;
+                let values = self._encode(series, &mut series_guard)?;
+                let target = read_target(target, &mut target_guard)?;
+                if values.len() != target.len() {
+                    return Err(PyValueError::new_err(
+                        "Series and target have different lengths"
+                    ));
+                }
+                wbp.get_metrics_on(&values, &target)
+            },
+            _ => return Err(PyValueError::new_err(
+                "Both series and target are required"
+            )),
+        };
+
+        let result = PyDict::new(py);
+        result.set_item("auc", metrics.auc)?;
+        result.set_item("gini", metrics.gini)?;
+        result.set_item("ks", metrics.ks)?;
+        result.set_item("divergence", metrics.divergence)?;
+        Ok(result)
+    }
+}
+
+
+#[pymodule]
+#[pyo3(name = "_woebin")]
+fn woebin_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyWoeBinningProc>()?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_of() {
+        let distinct = vec![-5i64, 0, 3, 10];
+
+        // Numeric values go to the smaller known one, less than all to
+        // the first
+        assert_eq!(rank_of(&distinct, &-5, true), 0);
+        assert_eq!(rank_of(&distinct, &-100, true), 0);
+        assert_eq!(rank_of(&distinct, &2, true), 1);
+        assert_eq!(rank_of(&distinct, &10, true), 3);
+        assert_eq!(rank_of(&distinct, &1000, true), 3);
+
+        // Unseen categorial values are missing in bins
+        assert_eq!(rank_of(&distinct, &3, false), 2);
+        assert_eq!(rank_of(&distinct, &2, false), usize::MAX);
+        assert_eq!(rank_of(&distinct, &-100, false), usize::MAX);
+        assert_eq!(rank_of(&distinct, &1000, false), usize::MAX);
+        assert_eq!(rank_of(&[] as &[i64], &1, false), usize::MAX);
+    }
+
+    #[test]
+    fn test_sorted_distinct() {
+        assert_eq!(
+            sorted_distinct(&[3i64, -1, 3, 0, -1], |a, b| a.cmp(b)),
+            vec![-1, 0, 3]
+        );
+
+        // NaN is the last one and is kept once
+        let distinct = sorted_distinct(
+            &[2.5, f64::NAN, -1.0, 2.5, f64::NAN], |a, b| a.total_cmp(b)
+        );
+        assert_eq!(distinct.len(), 3);
+        assert_eq!(&distinct[..2], &[-1.0, 2.5]);
+        assert!(distinct[2].is_nan());
+    }
+
+    #[test]
+    fn test_as_usize_slice() {
+        let values: Vec<u64> = vec![0, 7, 3, u32::MAX as u64];
+        let slice = as_usize_slice(&values).unwrap();
+        assert_eq!(&slice[..], &[0, 7, 3, u32::MAX as usize]);
+
+        // Zero-copy on 64-bit platforms: the same memory
+        if cfg!(target_pointer_width = "64") {
+            assert!(matches!(slice, Cow::Borrowed(_)));
+            assert_eq!(slice.as_ptr() as usize, values.as_ptr() as usize);
+        }
+    }
+
+    #[test]
+    fn test_as_unsigned() {
+        // Non-negative values are reinterpreted in place
+        let values: Vec<i64> = vec![0, 7, i64::MAX];
+        let unsigned = as_unsigned(&values).unwrap();
+        assert_eq!(unsigned, &[0, 7, i64::MAX as u64]);
+        assert_eq!(unsigned.as_ptr() as usize, values.as_ptr() as usize);
+
+        assert_eq!(as_unsigned(&[3, -1]), None);
+    }
+}
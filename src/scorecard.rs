@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+
+use crate::woe_binning_proc::WoeBinningProc;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScorecardRow {
+    pub feature: String,
+    pub bin: usize,
+    pub values: Vec<usize>,
+    pub woe: f64,
+    pub points: f64,
+}
+
+
+#[derive(Debug, Clone)]
+struct ScorecardFeature {
+    name: String,
+    coefficient: f64,
+    is_numeric: bool,
+    bins: Vec<Vec<usize>>,
+    woe: Vec<f64>,
+    // Bin index by value for categorial features
+    bin_map: BTreeMap<usize, usize>,
+    // Bin of rare and unseen categories if any
+    other_bin: Option<usize>,
+}
+
+
+impl ScorecardFeature {
+    fn find_bin(&self, value: usize) -> Option<usize> {
+        if self.is_numeric {
+            // Bins are ordered by value, a value between bins goes to
+            // the left one and a value less than all goes to the first
+            let idx = self.bins.partition_point(|bin| bin[0] <= value);
+            Some(idx.max(1) - 1)
+        } else {
+            self.bin_map.get(&value).cloned().or(self.other_bin)
+        }
+    }
+}
+
+
+pub struct Scorecard {
+    features: Vec<ScorecardFeature>,
+    intercept: f64,
+    base_score: f64,
+    base_odds: f64,
+    pdo: f64,
+}
+
+
+impl Scorecard {
+    pub fn new(intercept: f64) -> Self {
+        // Intercept and coefficients are of the logistic regression of
+        // the target on WOE features, the target 1 is the bad outcome
+        Self {
+            features: Vec::new(),
+            intercept,
+            base_score: 600.0,
+            base_odds: 50.0,
+            pdo: 20.0,
+        }
+    }
+
+    pub fn set_scaling(&mut self, base_score: f64, base_odds: f64,
+                       pdo: f64) {
+        // The score is base_score at good to bad odds base_odds, and
+        // the odds double every pdo points
+        assert!(base_odds > 0.0, "Base odds must be positive");
+        assert!(pdo != 0.0, "PDO must not be zero");
+        self.base_score = base_score;
+        self.base_odds = base_odds;
+        self.pdo = pdo;
+    }
+
+    pub fn add_feature(&mut self, name: &str, wbp: &WoeBinningProc,
+                       coefficient: f64) {
+        assert!(wbp.is_done());
+
+        let bins = wbp.get_bins_array().unwrap();
+        let bin_map = bins.iter().enumerate()
+            .flat_map(|(idx, values)| values.iter().map(move |&v| (v, idx)))
+            .collect();
+
+        self.features.push(ScorecardFeature {
+            name: name.to_string(),
+            coefficient,
+            is_numeric: wbp.is_numeric(),
+            bins,
+            woe: wbp.get_woe_array().unwrap(),
+            bin_map,
+            other_bin: wbp.get_other_bin(),
+        });
+    }
+
+    pub fn get_features_num(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn get_feature_name(&self, feature_idx: usize) -> &str {
+        &self.features[feature_idx].name
+    }
+
+    pub fn is_feature_numeric(&self, feature_idx: usize) -> bool {
+        self.features[feature_idx].is_numeric
+    }
+
+    pub fn get_feature_bins(&self, feature_idx: usize) -> &[Vec<usize>] {
+        // Values of the bins in the order of points
+        &self.features[feature_idx].bins
+    }
+
+    pub fn get_feature_other_bin(&self, feature_idx: usize) -> Option<usize> {
+        // Bin of categories missing in bins
+        self.features[feature_idx].other_bin
+    }
+
+    pub fn get_factor(&self) -> f64 {
+        self.pdo / 2.0f64.ln()
+    }
+
+    pub fn get_offset(&self) -> f64 {
+        self.base_score - self.get_factor() * self.base_odds.ln()
+    }
+
+    pub fn get_base_points(&self) -> f64 {
+        // Points of the intercept, they are added to every score
+        self.get_offset() - self.get_factor() * self.intercept
+    }
+
+    pub fn get_points(&self, feature_idx: usize) -> Vec<f64> {
+        // Points of the bins in the order of WoeBinningProc bins
+        let feature = &self.features[feature_idx];
+        let factor = self.get_factor();
+        feature.woe.iter()
+            .map(|woe| -factor * feature.coefficient * woe)
+            .collect()
+    }
+
+    pub fn score(&self, record: &[usize]) -> Option<f64> {
+        // Values of the features in the order they were added, None if
+        // a categorial value is missing in bins and there is no other bin
+        assert_eq!(record.len(), self.features.len());
+
+        let factor = self.get_factor();
+        let mut score = self.get_base_points();
+
+        for (feature, &value) in self.features.iter().zip(record) {
+            let bin = feature.find_bin(value)?;
+            score -= factor * feature.coefficient * feature.woe[bin];
+        }
+
+        Some(score)
+    }
+
+    pub fn get_table(&self) -> Vec<ScorecardRow> {
+        self.features.iter().enumerate()
+            .flat_map(|(feature_idx, feature)| {
+                let points = self.get_points(feature_idx);
+                (0..feature.bins.len()).map(move |bin| ScorecardRow {
+                    feature: feature.name.clone(),
+                    bin,
+                    values: feature.bins[bin].clone(),
+                    woe: feature.woe[bin],
+                    points: points[bin],
+                })
+            })
+            .collect()
+    }
+
+    pub fn to_csv(&self) -> String {
+        // The first row has the base points without a feature, values
+        // of a bin are separated by spaces, numeric bins are written
+        // as ranges min..max. Fields are quoted as in RFC 4180.
+        let mut csv = String::from("feature,bin,values,woe,points\n");
+        csv += &format!(",,,,{}\n", self.get_base_points());
+
+        for (feature_idx, feature) in self.features.iter().enumerate() {
+            let points = self.get_points(feature_idx);
+
+            for (bin, values) in feature.bins.iter().enumerate() {
+                let values = if feature.is_numeric {
+                    format!("{}..{}", values[0], values[values.len() - 1])
+                } else {
+                    let values: Vec<String> = values.iter()
+                        .map(|v| v.to_string())
+                        .collect();
+                    values.join(" ")
+                };
+                csv += &format!("{},{},{},{},{}\n",
+                                Self::_quote_csv(&feature.name), bin,
+                                Self::_quote_csv(&values), feature.woe[bin],
+                                points[bin]);
+            }
+        }
+
+        csv
+    }
+
+    fn _quote_csv(text: &str) -> String {
+        if text.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_wbp(series: &[usize], is_numeric: bool) -> WoeBinningProc {
+        let target: Vec<bool> = series.iter().map(|&v| v % 3 == 0).collect();
+        let mut wbp = WoeBinningProc::new(2, 1.0);
+        if is_numeric {
+            wbp.process_numeric(series, &target);
+        } else {
+            wbp.process_categorial(series, &target);
+        }
+        wbp
+    }
+
+    #[test]
+    fn test_scorecard() {
+        let series: Vec<usize> = (0..100).map(|i| i % 10).collect();
+        let wbp_numeric = create_wbp(&series, true);
+        let wbp_categorial = create_wbp(&series, false);
+
+        let mut scorecard = Scorecard::new(-1.5);
+        scorecard.set_scaling(600.0, 50.0, 20.0);
+        scorecard.add_feature("x", &wbp_numeric, -0.8);
+        scorecard.add_feature("y", &wbp_categorial, -1.2);
+
+        // The score is the offset plus factor times log-odds of good
+        let factor = 20.0 / 2.0f64.ln();
+        let offset = 600.0 - factor * 50.0f64.ln();
+        let woe_x = wbp_numeric.get_woe_array().unwrap();
+        let woe_y = wbp_categorial.get_woe_array().unwrap();
+        let x = wbp_numeric.get_bins_array().unwrap()[1][0];
+        let y = wbp_categorial.get_bins_array().unwrap()[0][0];
+        let score = offset - factor * (-1.5 - 0.8 * woe_x[1] - 1.2 * woe_y[0]);
+        assert!((scorecard.score(&[x, y]).unwrap() - score).abs() < 1e-9);
+
+        // Numeric values out of bins go to the nearest bin, categorial
+        // ones are unknown
+        assert_eq!(scorecard.score(&[1_000, y]),
+                   scorecard.score(&[9, y]));
+        assert_eq!(scorecard.score(&[x, 1_000]), None);
+
+        let table = scorecard.get_table();
+        assert_eq!(table.len(), 4);
+        assert_eq!(table[1].points, scorecard.get_points(0)[1]);
+        assert_eq!(scorecard.to_csv().lines().count(), 6);
+    }
+
+    #[test]
+    fn test_to_csv_quoting() {
+        let series: Vec<usize> = (0..100).map(|i| i % 4).collect();
+        let target: Vec<bool> = series.iter().map(|&v| v % 2 == 0).collect();
+        let mut wbp = WoeBinningProc::new(2, 1.0);
+        wbp.process_categorial(&series, &target);
+
+        let mut scorecard = Scorecard::new(-1.5);
+        scorecard.add_feature("income, \"net\"", &wbp, -1.2);
+        scorecard.add_feature("line\nbreak", &wbp, -1.2);
+
+        let csv = scorecard.to_csv();
+        let rows: Vec<&str> = csv.split('\n').collect();
+        assert!(rows[2].starts_with("\"income, \"\"net\"\"\",0,"));
+        assert!(csv.contains("\n\"line\nbreak\",0,"));
+        assert_eq!(rows[2].split(',').count(), 6);
+    }
+
+    #[test]
+    fn test_other_bin() {
+        // Unseen categories get the points of the bin of the rare ones
+        let mut series: Vec<usize> = (0..100).map(|i| i % 10).collect();
+        series.extend([10, 11]);
+        let target: Vec<bool> = series.iter().map(|&v| v % 3 == 0).collect();
+        let mut wbp = WoeBinningProc::new(2, 1.0);
+        wbp.set_rare_level(2, 0.0);
+        wbp.process_categorial(&series, &target);
+
+        let mut scorecard = Scorecard::new(-1.5);
+        scorecard.add_feature("y", &wbp, -1.2);
+
+        let other = wbp.get_other_bin().unwrap();
+        assert_eq!(scorecard.get_feature_other_bin(0), Some(other));
+        assert_eq!(scorecard.score(&[1_000]), scorecard.score(&[10]));
+        let points = scorecard.get_points(0);
+        assert_eq!(scorecard.score(&[1_000]).unwrap(),
+                   scorecard.get_base_points() + points[other]);
+    }
+}
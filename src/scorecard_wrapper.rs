@@ -0,0 +1,62 @@
+use std::ffi::{CStr, c_char};
+
+use crate::scorecard::Scorecard;
+use crate::woe_binning_proc_wrapper::WoeBinningProcWrapper;
+
+
+pub(crate) struct ScorecardWrapper {
+    pub(crate) sc: Scorecard,
+}
+
+
+impl ScorecardWrapper {
+    #[export_name="sc_new"]
+    pub extern "C" fn new(intercept: f64) -> Box<Self> {
+        Box::new(Self {
+            sc: Scorecard::new(intercept)
+        })
+    }
+
+    #[export_name="sc_set_scaling"]
+    pub extern "C" fn set_scaling(&mut self, base_score: f64, base_odds: f64,
+                                  pdo: f64) {
+        self.sc.set_scaling(base_score, base_odds, pdo);
+    }
+
+    #[export_name="sc_add_feature"]
+    pub extern "C" fn add_feature(&mut self, name: *const c_char,
+                                  wbp: &WoeBinningProcWrapper,
+                                  coefficient: f64) {
+        let name = unsafe {
+            CStr::from_ptr(name)
+        };
+        self.sc.add_feature(&name.to_string_lossy(), &wbp.wbp, coefficient);
+    }
+
+    #[export_name="sc_get_base_points"]
+    pub extern "C" fn get_base_points(&self) -> f64 {
+        self.sc.get_base_points()
+    }
+
+    #[export_name="sc_get_points"]
+    pub extern "C" fn get_points(&self, feature_idx: usize, size: usize,
+                                 points_array: *mut f64) {
+        let points = self.sc.get_points(feature_idx);
+        unsafe {
+            points.as_ptr().copy_to(points_array, size);
+        }
+    }
+
+    #[export_name="sc_score"]
+    pub extern "C" fn score(&self, size: usize, record: *const usize) -> f64 {
+        // NaN if a categorial value is missing in bins and there is no
+        // other bin
+        let mut record_buff: Vec<usize> = vec![0; size];
+
+        unsafe {
+            record.copy_to(record_buff.as_mut_ptr(), size);
+        }
+
+        self.sc.score(&record_buff).unwrap_or(f64::NAN)
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::scorecard::Scorecard;
+use crate::woe_binning_proc::WoeBinningProc;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    // "column", strings with doubled quotes
+    Ansi,
+    // `column`, backslashes are escaped as well
+    MySql,
+    // [column]
+    SqlServer,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlLiteral {
+    Null,
+    Number(f64),
+    Text(String),
+}
+
+
+pub struct SqlExport {
+    dialect: SqlDialect,
+    in_lists: bool,
+    default_value: f64,
+    null_value: Option<f64>,
+    literal_map: BTreeMap<String, BTreeMap<usize, SqlLiteral>>,
+    special_map: BTreeMap<String, BTreeSet<usize>>,
+}
+
+
+impl SqlExport {
+    pub fn new(dialect: SqlDialect) -> Self {
+        Self {
+            dialect,
+            in_lists: true,
+            default_value: 0.0,
+            null_value: None,
+            literal_map: BTreeMap::new(),
+            special_map: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_in_lists(&mut self, in_lists: bool) {
+        // Categories as col IN (a, b) or as col = a OR col = b
+        self.in_lists = in_lists;
+    }
+
+    pub fn set_default(&mut self, default_value: f64) {
+        // Result for categories missing in bins without the other bin
+        self.default_value = default_value;
+    }
+
+    pub fn set_null(&mut self, null_value: Option<f64>) {
+        // Result for NULL if no bin has it, the default otherwise
+        self.null_value = null_value;
+    }
+
+    pub fn add_literals(&mut self, column: &str,
+                        literals: &[(usize, SqlLiteral)]) {
+        // SQL values of the column by values in series, the values
+        // themselves are numbers by default
+        self.literal_map.entry(column.to_string()).or_default()
+            .extend(literals.iter().cloned());
+    }
+
+    pub fn set_special_values(&mut self, column: &str, values: &[usize]) {
+        // Numeric values checked for equality before the ranges (such as
+        // -1 for "no data"), they do not affect cut-points
+        self.special_map.insert(column.to_string(),
+                                values.iter().cloned().collect());
+    }
+
+    pub fn render_woe(&self, column: &str, wbp: &WoeBinningProc) -> String {
+        // CASE expression of WOE
+        self._render_case(column, wbp.is_numeric(),
+                          &wbp.get_bins_array().unwrap(),
+                          &wbp.get_woe_array().unwrap(),
+                          wbp.get_other_bin())
+    }
+
+    pub fn render_scorecard(&self, scorecard: &Scorecard,
+                            columns: &[&str]) -> String {
+        // Sum of the base points and CASE expressions of the points of
+        // the features, columns go in the order of features
+        assert_eq!(columns.len(), scorecard.get_features_num());
+
+        let mut sql = self._render_number(scorecard.get_base_points());
+
+        for (feature_idx, column) in columns.iter().enumerate() {
+            let case = self._render_case(
+                column, scorecard.is_feature_numeric(feature_idx),
+                scorecard.get_feature_bins(feature_idx),
+                &scorecard.get_points(feature_idx),
+                scorecard.get_feature_other_bin(feature_idx)
+            );
+            sql += "\n    + ";
+            sql += &case.replace('\n', "\n    ");
+        }
+
+        sql
+    }
+
+    fn _render_case(&self, column: &str, is_numeric: bool,
+                    bins: &[Vec<usize>], results: &[f64],
+                    other_bin: Option<usize>) -> String {
+        let name = self._quote(column);
+        // Unseen categories go to the other bin if there is one
+        let default_value = other_bin.map_or(self.default_value,
+                                             |idx| results[idx]);
+        let special_values = self.special_map.get(column)
+            .cloned().unwrap_or_default();
+
+        let mut lines = vec!["CASE".to_string()];
+        let mut has_null = false;
+
+        // Special values and categories
+        for (values, &result) in bins.iter().zip(results) {
+            let values: Vec<usize> = values.iter().cloned()
+                .filter(|v| !is_numeric || special_values.contains(v))
+                .collect();
+
+            let literals: Vec<SqlLiteral> = values.iter()
+                .map(|&v| self._literal(column, v))
+                .collect();
+            let mut conditions = Vec::new();
+
+            if literals.contains(&SqlLiteral::Null) {
+                has_null = true;
+                conditions.push(format!("{} IS NULL", name));
+            }
+
+            let literals: Vec<String> = literals.iter()
+                .filter(|l| **l != SqlLiteral::Null)
+                .map(|l| self._render_literal(l))
+                .collect();
+
+            if self.in_lists && !literals.is_empty() {
+                conditions.push(format!("{} IN ({})", name,
+                                        literals.join(", ")));
+            } else {
+                conditions.extend(literals.iter()
+                    .map(|l| format!("{} = {}", name, l)));
+            }
+
+            if !conditions.is_empty() {
+                lines.push(format!("    WHEN {} THEN {}",
+                                   conditions.join(" OR "),
+                                   self._render_number(result)));
+            }
+        }
+
+        // Without a bin of NULL it goes to the null value or the default
+        if !has_null && (is_numeric || self.null_value.is_some()) {
+            lines.insert(1, format!(
+                "    WHEN {} IS NULL THEN {}", name,
+                self._render_number(self.null_value
+                                        .unwrap_or(default_value))
+            ));
+        }
+
+        if is_numeric {
+            // Ranges up to the smallest value of the next bin, the last
+            // bin takes the rest
+            let bins: Vec<(Vec<usize>, f64)> = bins.iter().zip(results)
+                .map(|(values, &result)| (
+                    values.iter().cloned()
+                        .filter(|v| !special_values.contains(v))
+                        .collect::<Vec<usize>>(),
+                    result
+                ))
+                .filter(|(values, _)| !values.is_empty())
+                .collect();
+
+            for idx in 0..bins.len() {
+                if idx + 1 < bins.len() {
+                    let cut = self._literal(column, bins[idx + 1].0[0]);
+                    lines.push(format!("    WHEN {} < {} THEN {}", name,
+                                       self._render_literal(&cut),
+                                       self._render_number(bins[idx].1)));
+                } else {
+                    lines.push(format!("    ELSE {}",
+                                       self._render_number(bins[idx].1)));
+                }
+            }
+
+            if bins.is_empty() {
+                lines.push(format!("    ELSE {}",
+                                   self._render_number(default_value)));
+            }
+        } else {
+            lines.push(format!("    ELSE {}",
+                               self._render_number(default_value)));
+        }
+
+        lines.push("END".to_string());
+        lines.join("\n")
+    }
+
+    fn _literal(&self, column: &str, value: usize) -> SqlLiteral {
+        self.literal_map.get(column)
+            .and_then(|literals| literals.get(&value))
+            .cloned()
+            .unwrap_or(SqlLiteral::Number(value as f64))
+    }
+
+    fn _render_literal(&self, literal: &SqlLiteral) -> String {
+        match literal {
+            SqlLiteral::Null => "NULL".to_string(),
+            SqlLiteral::Number(number) => self._render_number(*number),
+            SqlLiteral::Text(text) => {
+                let mut text = text.replace('\'', "''");
+                if self.dialect == SqlDialect::MySql {
+                    text = text.replace('\\', "\\\\");
+                }
+                format!("'{}'", text)
+            },
+        }
+    }
+
+    fn _render_number(&self, number: f64) -> String {
+        // Display of f64 has no exponent, so any dialect parses it
+        if number.is_finite() {
+            number.to_string()
+        } else {
+            "NULL".to_string()
+        }
+    }
+
+    fn _quote(&self, column: &str) -> String {
+        match self.dialect {
+            SqlDialect::Ansi => format!("\"{}\"", column.replace('"', "\"\"")),
+            SqlDialect::MySql => format!("`{}`", column.replace('`', "``")),
+            SqlDialect::SqlServer => format!("[{}]", column.replace(']', "]]")),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_wbp(series: &[usize], is_numeric: bool) -> WoeBinningProc {
+        let target: Vec<bool> = series.iter().map(|&v| v % 3 == 0).collect();
+        let mut wbp = WoeBinningProc::new(3, 1.0);
+        if is_numeric {
+            wbp.process_numeric(series, &target);
+        } else {
+            wbp.process_categorial(series, &target);
+        }
+        wbp
+    }
+
+    #[test]
+    fn test_render_numeric() {
+        let series: Vec<usize> = (0..100).map(|i| i % 10).collect();
+        let wbp = create_wbp(&series, true);
+        let bins = wbp.get_bins_array().unwrap();
+        let woe = wbp.get_woe_array().unwrap();
+
+        let mut export = SqlExport::new(SqlDialect::MySql);
+        export.set_null(Some(-1.5));
+        let sql = export.render_woe("x", &wbp);
+
+        let expected = format!(
+            "CASE\n    WHEN `x` IS NULL THEN -1.5\n    \
+             WHEN `x` < {} THEN {}\n    WHEN `x` < {} THEN {}\n    \
+             ELSE {}\nEND",
+            bins[1][0], woe[0], bins[2][0], woe[1], woe[2]
+        );
+        assert_eq!(sql, expected);
+
+        // The special value is checked first and leaves the range
+        export.set_special_values("x", &[bins[1][0]]);
+        let sql = export.render_woe("x", &wbp);
+        assert!(sql.contains(&format!("WHEN `x` IN ({}) THEN {}",
+                                      bins[1][0], woe[1])));
+        if bins[1].len() > 1 {
+            assert!(sql.contains(&format!("WHEN `x` < {} THEN", bins[1][1])));
+        }
+    }
+
+    #[test]
+    fn test_render_categorial() {
+        let series: Vec<usize> = (0..100).map(|i| i % 10).collect();
+        let wbp = create_wbp(&series, false);
+        let bins = wbp.get_bins_array().unwrap();
+
+        let mut export = SqlExport::new(SqlDialect::Ansi);
+        export.set_default(0.25);
+        export.add_literals("c", &[
+            (bins[0][0], SqlLiteral::Null),
+            (bins[1][0], SqlLiteral::Text("it's".to_string())),
+        ]);
+        let sql = export.render_woe("c", &wbp);
+
+        assert!(sql.starts_with("CASE\n    WHEN \"c\" IS NULL"));
+        assert!(sql.contains("'it''s'"));
+        assert!(sql.ends_with("    ELSE 0.25\nEND"));
+        assert_eq!(sql.matches("WHEN").count(), 3);
+
+        export.set_in_lists(false);
+        let sql = export.render_woe("c", &wbp);
+        assert!(!sql.contains(" IN "));
+        assert!(sql.contains(&format!("\"c\" = {} OR", bins[2][0])));
+    }
+
+    #[test]
+    fn test_render_scorecard() {
+        let series: Vec<usize> = (0..100).map(|i| i % 10).collect();
+        let wbp_numeric = create_wbp(&series, true);
+        let wbp_categorial = create_wbp(&series, false);
+
+        let mut scorecard = Scorecard::new(-1.0);
+        scorecard.add_feature("x", &wbp_numeric, -0.5);
+        scorecard.add_feature("y", &wbp_categorial, -1.0);
+
+        let export = SqlExport::new(SqlDialect::SqlServer);
+        let sql = export.render_scorecard(&scorecard, &["x", "y"]);
+
+        assert!(sql.starts_with(&scorecard.get_base_points().to_string()));
+        assert_eq!(sql.matches("\n    + CASE").count(), 2);
+        assert!(sql.contains("        WHEN [x] IS NULL THEN 0"));
+    }
+
+    #[test]
+    fn test_render_other_bin() {
+        // Unseen categories go to the bin of the rare ones, not to
+        // the default
+        let mut series: Vec<usize> = (0..100).map(|i| i % 10).collect();
+        series.extend([10, 11]);
+        let target: Vec<bool> = series.iter().map(|&v| v % 3 == 0).collect();
+        let mut wbp = WoeBinningProc::new(3, 1.0);
+        wbp.set_rare_level(2, 0.0);
+        wbp.process_categorial(&series, &target);
+        let other = wbp.get_other_bin().unwrap();
+
+        let mut export = SqlExport::new(SqlDialect::Ansi);
+        export.set_default(0.25);
+        let sql = export.render_woe("c", &wbp);
+        assert!(sql.ends_with(&format!(
+            "    ELSE {}\nEND", wbp.get_woe_array().unwrap()[other]
+        )));
+
+        let mut scorecard = Scorecard::new(-1.0);
+        scorecard.add_feature("c", &wbp, -1.0);
+        let sql = export.render_scorecard(&scorecard, &["c"]);
+        assert!(sql.ends_with(&format!(
+            "        ELSE {}\n    END", scorecard.get_points(0)[other]
+        )));
+    }
+}
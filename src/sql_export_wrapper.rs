@@ -0,0 +1,121 @@
+use std::ffi::{CStr, CString, c_char};
+
+use crate::scorecard_wrapper::ScorecardWrapper;
+use crate::sql_export::{SqlDialect, SqlExport, SqlLiteral};
+use crate::woe_binning_proc_wrapper::WoeBinningProcWrapper;
+
+
+struct SqlExportWrapper {
+    export: SqlExport,
+}
+
+
+impl SqlExportWrapper {
+    #[export_name="sql_new"]
+    pub extern "C" fn new(dialect: usize) -> Box<Self> {
+        Box::new(Self {
+            export: SqlExport::new(match dialect {
+                0 => SqlDialect::Ansi,
+                1 => SqlDialect::MySql,
+                2 => SqlDialect::SqlServer,
+                _ => panic!("Unknown SQL dialect: {}", dialect),
+            })
+        })
+    }
+
+    #[export_name="sql_set_in_lists"]
+    pub extern "C" fn set_in_lists(&mut self, in_lists: bool) {
+        self.export.set_in_lists(in_lists);
+    }
+
+    #[export_name="sql_set_default"]
+    pub extern "C" fn set_default(&mut self, default_value: f64) {
+        self.export.set_default(default_value);
+    }
+
+    #[export_name="sql_set_null"]
+    pub extern "C" fn set_null(&mut self, has_null_value: bool, 
+                               null_value: f64) {
+        self.export.set_null(has_null_value.then_some(null_value));
+    }
+
+    #[export_name="sql_add_literals"]
+    pub extern "C" fn add_literals(&mut self, column: *const c_char, 
+                                   size: usize, values: *const usize, 
+                                   kinds: *const usize, numbers: *const f64, 
+                                   texts: *const *const c_char) {
+        // Kinds: 0 is NULL, 1 is a number, 2 is a text
+        let literals: Vec<(usize, SqlLiteral)> = (0..size).map(|idx| unsafe {
+            let literal = match *kinds.add(idx) {
+                0 => SqlLiteral::Null,
+                1 => SqlLiteral::Number(*numbers.add(idx)),
+                2 => SqlLiteral::Text(Self::_to_string(*texts.add(idx))),
+                kind => panic!("Unknown literal kind: {}", kind),
+            };
+            (*values.add(idx), literal)
+        }).collect();
+
+        let column = unsafe {
+            Self::_to_string(column)
+        };
+        self.export.add_literals(&column, &literals);
+    }
+
+    #[export_name="sql_set_special_values"]
+    pub extern "C" fn set_special_values(&mut self, column: *const c_char, 
+                                         size: usize, values: *const usize) {
+        let (column, values) = unsafe {
+            (Self::_to_string(column), 
+             std::slice::from_raw_parts(values, size).to_vec())
+        };
+        self.export.set_special_values(&column, &values);
+    }
+
+    #[export_name="sql_render_woe"]
+    pub extern "C" fn render_woe(&self, column: *const c_char, 
+                                 wbp: &WoeBinningProcWrapper) -> *mut c_char {
+        // The string must be released by sql_free_string, null if the 
+        // SQL has a NUL byte
+        let column = unsafe {
+            Self::_to_string(column)
+        };
+        let sql = self.export.render_woe(&column, &wbp.wbp);
+        Self::_into_raw(sql)
+    }
+
+    #[export_name="sql_render_scorecard"]
+    pub extern "C" fn render_scorecard(&self, sc: &ScorecardWrapper, 
+                                       size: usize, 
+                                       columns: *const *const c_char) -> 
+                *mut c_char {
+        // The string must be released by sql_free_string, null if the 
+        // SQL has a NUL byte
+        let columns: Vec<String> = (0..size)
+            .map(|idx| unsafe { Self::_to_string(*columns.add(idx)) })
+            .collect();
+        let columns: Vec<&str> = columns.iter().map(|c| c.as_str()).collect();
+        let sql = self.export.render_scorecard(&sc.sc, &columns);
+        Self::_into_raw(sql)
+    }
+
+    fn _into_raw(sql: String) -> *mut c_char {
+        CString::new(sql).map_or(std::ptr::null_mut(), CString::into_raw)
+    }
+
+    unsafe fn _to_string(text: *const c_char) -> String {
+        unsafe {
+            CStr::from_ptr(text).to_string_lossy().into_owned()
+        }
+    }
+}
+
+
+#[export_name="sql_free_string"]
+extern "C" fn free_string(sql: *mut c_char) {
+    if sql.is_null() {
+        return;
+    }
+    unsafe {
+        drop(CString::from_raw(sql));
+    }
+}
@@ -0,0 +1,304 @@
+use wasm_bindgen::prelude::*;
+
+use crate::optimal_binning::Trend;
+use crate::woe_binning_proc::WoeBinningProc;
+
+
+// JavaScript API on typed arrays: series is a Float64Array of numbers
+// (categories are given by their codes), target is a Uint8Array of 0/1.
+// Values are encoded by ranks of distinct numbers.
+#[wasm_bindgen(js_name = WoeBinningProc)]
+pub struct WasmWoeBinningProc {
+    bins: usize,
+    smooth: f64,
+    min_bin_size: usize,
+    trend: Option<Trend>,
+    auto_trend: bool,
+    wbp: Option<WoeBinningProc>,
+    // Distinct values of the processed series, ascending
+    values: Vec<f64>,
+}
+
+
+#[wasm_bindgen(js_class = WoeBinningProc)]
+impl WasmWoeBinningProc {
+    #[wasm_bindgen(constructor)]
+    pub fn new(bins: usize, smooth: f64) -> Self {
+        Self {
+            bins,
+            smooth,
+            min_bin_size: 0,
+            trend: None,
+            auto_trend: false,
+            wbp: None,
+            values: Vec::new(),
+        }
+    }
+
+    #[wasm_bindgen(js_name = setMinBinSize)]
+    pub fn set_min_bin_size(&mut self, min_bin_size: usize) {
+        // Applies to exact binning
+        self.min_bin_size = min_bin_size;
+    }
+
+    #[wasm_bindgen(js_name = setTrend)]
+    pub fn set_trend(&mut self, trend: Option<String>) -> Result<(), String> {
+        // "ascending", "descending", "peak", "valley", "auto" (the one
+        // with maximal IV) or undefined, applies to exact binning
+        (self.trend, self.auto_trend) = match trend.as_deref() {
+            None => (None, false),
+            Some("ascending") => (Some(Trend::Ascending), false),
+            Some("descending") => (Some(Trend::Descending), false),
+            Some("peak") => (Some(Trend::Peak), false),
+            Some("valley") => (Some(Trend::Valley), false),
+            Some("auto") => (None, true),
+            Some(trend) => return Err(format!("Unknown trend: {}", trend)),
+        };
+        Ok(())
+    }
+
+    pub fn trend(&self) -> Result<Option<String>, String> {
+        // Trend of exact binning, the detected one for "auto"
+        Ok(self._wbp()?.get_trend().map(|trend| match trend {
+            Trend::Ascending => "ascending",
+            Trend::Descending => "descending",
+            Trend::Peak => "peak",
+            Trend::Valley => "valley",
+        }.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = isFeasible)]
+    pub fn is_feasible(&self) -> Result<bool, String> {
+        // False if no exact binning satisfies the minimal bin size and
+        // the trend, then all values are in one bin
+        Ok(self._wbp()?.is_feasible())
+    }
+
+    #[wasm_bindgen(js_name = trendIv)]
+    pub fn trend_iv(&self) -> Result<Vec<f64>, String> {
+        // IV of exact binning for "auto" by trend in the order ascending,
+        // descending, peak and valley, NaN for a trend without a feasible
+        // binning, empty without detection
+        Ok(self._wbp()?.get_trend_ivs().unwrap_or_default().iter()
+            .map(|(_, iv)| iv.unwrap_or(f64::NAN))
+            .collect())
+    }
+
+    pub fn fit(&mut self, series: &[f64], target: &[u8], mode: &str,
+               exact: bool) -> Result<(), String> {
+        // mode is "numeric", "categorial" or "ordered", exact binning is
+        // for numeric and ordered modes
+        if series.len() != target.len() {
+            return Err("Series and target have different lengths".to_string());
+        }
+        if series.iter().any(|x| x.is_nan()) {
+            return Err("Series has NaN".to_string());
+        }
+        if exact && mode == "categorial" {
+            return Err("Exact binning is for numeric or ordered mode only"
+                .to_string());
+        }
+        if !exact && self.auto_trend {
+            return Err("Trend \"auto\" requires exact binning".to_string());
+        }
+
+        let mut values = series.to_vec();
+        values.sort_by(|a, b| a.total_cmp(b));
+        values.dedup();
+
+        let series: Vec<usize> = series.iter()
+            .map(|x| values.binary_search_by(|v| v.total_cmp(x)).unwrap())
+            .collect();
+        let target: Vec<bool> = target.iter().map(|&t| t != 0).collect();
+
+        let mut wbp = WoeBinningProc::new(self.bins, self.smooth);
+        wbp.set_min_bin_size(self.min_bin_size);
+        wbp.set_trend(self.trend);
+        wbp.set_auto_trend(self.auto_trend);
+
+        match (mode, exact) {
+            ("numeric", false) => wbp.process_numeric(&series, &target),
+            ("numeric", true) => wbp.process_numeric_exact(&series, &target),
+            ("categorial", _) => wbp.process_categorial(&series, &target),
+            ("ordered", false) =>
+                wbp.process_categorial_ordered(&series, &target),
+            ("ordered", true) =>
+                wbp.process_categorial_ordered_exact(&series, &target),
+            _ => return Err(format!("Unknown mode: {}", mode)),
+        }
+
+        self.wbp = Some(wbp);
+        self.values = values;
+
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setBinsNum)]
+    pub fn set_bins_num(&mut self, bins: usize) -> Result<(), String> {
+        self._wbp_mut()?.set_bins_num(bins);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = binsNum)]
+    pub fn bins_num(&self) -> Result<usize, String> {
+        Ok(self._wbp()?.get_bins_num().unwrap())
+    }
+
+    #[wasm_bindgen(js_name = binValues)]
+    pub fn bin_values(&self, idx: usize) -> Result<Vec<f64>, String> {
+        // Values of a bin as they were in series
+        let wbp = self._wbp()?;
+        if idx >= wbp.get_bins_num().unwrap() {
+            return Err(format!("Bin {} does not exist", idx));
+        }
+        Ok(wbp.get_bin_values(idx).unwrap().iter()
+            .map(|&v| self.values[v])
+            .collect())
+    }
+
+    pub fn woe(&self) -> Result<Vec<f64>, String> {
+        Ok(self._wbp()?.get_woe_array().unwrap())
+    }
+
+    pub fn iv(&self) -> Result<Vec<f64>, String> {
+        Ok(self._wbp()?.get_iv_array().unwrap())
+    }
+
+    #[wasm_bindgen(js_name = ivTotal)]
+    pub fn iv_total(&self) -> Result<f64, String> {
+        Ok(self._wbp()?.get_iv_total().unwrap())
+    }
+
+    pub fn counts(&self) -> Result<Vec<u32>, String> {
+        // Rows in each bin
+        Ok(self._wbp()?.get_count_array().unwrap().iter()
+            .map(|&n| n as u32)
+            .collect())
+    }
+
+    pub fn events(&self) -> Result<Vec<u32>, String> {
+        // Rows of target 1 in each bin
+        Ok(self._wbp()?.get_event_array().unwrap().iter()
+            .map(|&n| n as u32)
+            .collect())
+    }
+
+    pub fn table(&self) -> Result<String, String> {
+        // JSON array of bins with values, count, events, woe and iv,
+        // infinite numbers are null
+        let number = |x: f64| if x.is_finite() { x.to_string() }
+                              else { "null".to_string() };
+
+        let woe = self.woe()?;
+        let iv = self.iv()?;
+        let counts = self.counts()?;
+        let events = self.events()?;
+
+        let rows = (0..woe.len())
+            .map(|idx| {
+                let values: Vec<String> = self.bin_values(idx)?.iter()
+                    .map(|&v| number(v))
+                    .collect();
+                Ok(format!(
+                    "{{\"bin\":{},\"values\":[{}],\"count\":{},\"events\":{},\
+                     \"woe\":{},\"iv\":{}}}",
+                    idx, values.join(","), counts[idx], events[idx],
+                    number(woe[idx]), number(iv[idx])
+                ))
+            })
+            .collect::<Result<Vec<String>, String>>()?;
+
+        Ok(format!("[{}]", rows.join(",")))
+    }
+
+    pub fn transform(&self, series: &[f64]) -> Result<Vec<f64>, String> {
+        // WOE of values, numeric values between bins go to the left one,
+        // unknown categorial values get zero
+        let wbp = self._wbp()?;
+        let is_numeric = wbp.is_numeric();
+
+        let series: Vec<usize> = series.iter()
+            .map(|x| {
+                let count = self.values.partition_point(|v| v <= x);
+                if is_numeric {
+                    count.max(1) - 1
+                } else if count > 0 && self.values[count - 1] == *x {
+                    count - 1
+                } else {
+                    usize::MAX
+                }
+            })
+            .collect();
+
+        Ok(wbp.transform(&series))
+    }
+}
+
+
+impl WasmWoeBinningProc {
+    fn _wbp(&self) -> Result<&WoeBinningProc, String> {
+        self.wbp.as_ref().ok_or("Binning is not fitted".to_string())
+    }
+
+    fn _wbp_mut(&mut self) -> Result<&mut WoeBinningProc, String> {
+        self.wbp.as_mut().ok_or("Binning is not fitted".to_string())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_data() -> (Vec<f64>, Vec<u8>) {
+        let series: Vec<f64> = (0..400).map(|i| (i % 20) as f64 / 2.0)
+            .collect();
+        let target: Vec<u8> = series.iter().map(|&x| (x >= 5.0) as u8)
+            .collect();
+        (series, target)
+    }
+
+    #[test]
+    fn test_fit_numeric() {
+        let (series, target) = create_data();
+
+        let mut wbp = WasmWoeBinningProc::new(2, 1.0);
+        assert!(wbp.woe().is_err());
+        wbp.fit(&series, &target, "numeric", false).unwrap();
+
+        assert_eq!(wbp.bins_num(), Ok(2));
+        assert_eq!(wbp.bin_values(1).unwrap()[0], 5.0);
+        assert_eq!(wbp.counts(), Ok(vec![200, 200]));
+        assert_eq!(wbp.events(), Ok(vec![0, 200]));
+
+        let woe = wbp.woe().unwrap();
+        assert_eq!(wbp.transform(&[-1.0, 4.9, 5.0, 100.0]),
+                   Ok(vec![woe[0], woe[0], woe[1], woe[1]]));
+
+        let table = wbp.table().unwrap();
+        assert!(table.starts_with("[{\"bin\":0,\"values\":[0,0.5,"));
+        assert!(table.contains("\"count\":200,\"events\":200"));
+    }
+
+    #[test]
+    fn test_fit_categorial() {
+        let (series, target) = create_data();
+
+        let mut wbp = WasmWoeBinningProc::new(2, 1.0);
+        wbp.fit(&series, &target, "categorial", false).unwrap();
+        assert_eq!(wbp.transform(&[0.25]), Ok(vec![0.0]));
+
+        assert!(wbp.fit(&series, &target, "categorial", true).is_err());
+        assert!(wbp.fit(&series, &target, "other", false).is_err());
+        assert!(wbp.fit(&[f64::NAN], &[1], "numeric", false).is_err());
+        assert!(wbp.set_trend(Some("up".to_string())).is_err());
+        assert!(wbp.set_trend(Some("auto".to_string())).is_ok());
+        assert!(wbp.fit(&series, &target, "numeric", false).is_err());
+        assert!(wbp.fit(&series, &target, "numeric", true).is_ok());
+        assert_eq!(wbp.is_feasible(), Ok(true));
+
+        wbp.set_min_bin_size(1_000_000);
+        wbp.fit(&series, &target, "numeric", true).unwrap();
+        assert_eq!(wbp.is_feasible(), Ok(false));
+    }
+}
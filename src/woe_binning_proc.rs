@@ -1,6 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use crate::hierarchical_clusterizer::{ClusterTrait, HierarchicalClusterizer};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::hierarchical_clusterizer::{ClusterTrait, HierarchicalClusterizer, 
+                                     Observer};
+use crate::metrics::{Metrics, calc_metrics, calc_metrics_by_scores};
+use crate::optimal_binning::{Prebin, Trend, optimal_partition};
 
 
 pub fn calc_woe(p0: f64, p1: f64) -> f64 {
@@ -14,14 +21,18 @@ pub fn calc_iv(p0: f64, p1: f64) -> f64 {
 
 
 #[derive(Debug, Clone, PartialEq)]
-struct WoeBinningCluster {
-    n: usize,
-    n1: usize,
-    n0: usize,
-    p1: f64,
-    p0: f64,
-    values: Vec<usize>,
-    smooth: f64,
+pub(crate) struct WoeBinningCluster {
+    pub(crate) n: usize,
+    pub(crate) n1: usize,
+    pub(crate) n0: usize,
+    pub(crate) p1: f64,
+    pub(crate) p0: f64,
+    pub(crate) size: usize,
+    pub(crate) value_min: usize,
+    pub(crate) smooth: f64,
+    // Tags of cannot-link pairs of the values: 2 * k and 2 * k + 1 for 
+    // the two sides of the pair k, sorted
+    pub(crate) cannot_link: Vec<usize>,
 }
 
 
@@ -36,12 +47,31 @@ impl ClusterTrait for WoeBinningCluster {
             n0: cluster1.n0 + cluster2.n0,
             p1: cluster1.p1 + cluster2.p1,
             p0: cluster1.p0 + cluster2.p0,
-            values: [cluster1.values.clone(), cluster2.values.clone()].concat(),
+            size: cluster1.size + cluster2.size,
+            value_min: cluster1.value_min.min(cluster2.value_min),
             smooth,
+            cannot_link: if cluster2.cannot_link.is_empty() {
+                cluster1.cannot_link.clone()
+            } else {
+                let mut cannot_link: Vec<usize> = cluster1.cannot_link.iter()
+                    .chain(cluster2.cannot_link.iter())
+                    .cloned()
+                    .collect();
+                cannot_link.sort();
+                cannot_link.dedup();
+                cannot_link
+            },
         }
     }
 
     fn distance(cluster1: &Self, cluster2: &Self) -> Option<f64> {
+        // Clusters with two sides of a cannot-link pair are never merged
+        let is_linked = |tag: &usize| 
+            cluster2.cannot_link.binary_search(&(tag ^ 1)).is_ok();
+        if cluster1.cannot_link.iter().any(is_linked) {
+            return None;
+        }
+
         let smooth = (cluster1.n as f64 * cluster1.smooth + 
                       cluster2.n as f64 * cluster2.smooth) / 
                      (cluster1.n + cluster2.n) as f64;
@@ -58,11 +88,102 @@ impl ClusterTrait for WoeBinningCluster {
 }
 
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOrder {
+    // Ascending by the smallest value in the bin
+    Value,
+    // Ascending by WOE
+    Woe,
+    // Descending by the number of rows in the bin
+    Count,
+}
+
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValueCounts {
+    // Counts of target 1 and 0 by value
+    counts: BTreeMap<usize, (usize, usize)>,
+}
+
+
+impl ValueCounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_series(series: &[usize], target: &[bool]) -> Self {
+        let mut counts = Self::new();
+        counts.add_chunk(series, target);
+        counts
+    }
+
+    pub fn add(&mut self, value: usize, target: bool) {
+        let count = self.counts.entry(value).or_insert((0, 0));
+        if target {
+            count.0 += 1;
+        } else {
+            count.1 += 1;
+        }
+    }
+
+    pub fn add_chunk(&mut self, series: &[usize], target: &[bool]) {
+        // Chunks of the same data can be counted one by one, so it is 
+        // never needed as a whole
+        assert_eq!(series.len(), target.len());
+        for (&value, &trg) in series.iter().zip(target) {
+            self.add(value, trg);
+        }
+    }
+
+    pub fn get_values_num(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn get_rows_num(&self) -> usize {
+        self.counts.values().map(|(n1, n0)| n1 + n0).sum()
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    // Indices of a group in set_must_link and of a pair in 
+    // set_cannot_link
+    MustLink(usize),
+    CannotLink(usize),
+}
+
+
 pub struct WoeBinningProc {
     clusterizer: HierarchicalClusterizer<WoeBinningCluster>,
     desirable_bins_num: usize,
-    clusters: Option<Vec<WoeBinningCluster>>,
+    clusters: Option<Vec<(usize, WoeBinningCluster)>>,
+    values: Vec<usize>,
+    initial_clusters: Vec<WoeBinningCluster>,
     smooth: f64,
+    bin_order: BinOrder,
+    is_numeric: bool,
+    is_ordered: bool,
+    low_memory: bool,
+    connectivity: Option<Vec<(usize, usize)>>,
+    must_link: Vec<Vec<usize>>,
+    cannot_link: Vec<(usize, usize)>,
+    rare_min_count: usize,
+    rare_min_share: f64,
+    rare_values: Vec<usize>,
+    folds: usize,
+    seed: u64,
+    oof_woe: Option<Vec<f64>>,
+    fit_woe: Option<Vec<f64>>,
+    min_bin_size: usize,
+    trend: Option<Trend>,
+    auto_trend: bool,
+    trend_ivs: Option<Vec<(Trend, Option<f64>)>>,
+    max_prebins: usize,
+    greedy_iv_total: Option<f64>,
+    is_feasible: bool,
+    edited_bins_num: Option<usize>,
+    linked_bins_num: Option<usize>,
 }
 
 
@@ -72,34 +193,554 @@ impl WoeBinningProc {
             clusterizer: HierarchicalClusterizer::<WoeBinningCluster>::new(),
             desirable_bins_num,
             clusters: None,
+            values: Vec::new(),
+            initial_clusters: Vec::new(),
             smooth,
+            bin_order: BinOrder::Woe,
+            is_numeric: false,
+            is_ordered: false,
+            low_memory: false,
+            connectivity: None,
+            must_link: Vec::new(),
+            cannot_link: Vec::new(),
+            rare_min_count: 0,
+            rare_min_share: 0.0,
+            rare_values: Vec::new(),
+            folds: 0,
+            seed: 0,
+            oof_woe: None,
+            fit_woe: None,
+            min_bin_size: 0,
+            trend: None,
+            auto_trend: false,
+            trend_ivs: None,
+            max_prebins: 100,
+            greedy_iv_total: None,
+            is_feasible: true,
+            edited_bins_num: None,
+            linked_bins_num: None,
+        }
+    }
+
+    pub fn set_min_bin_size(&mut self, min_bin_size: usize) {
+        // Minimal number of rows in a bin, applies to exact numeric binning
+        self.min_bin_size = min_bin_size;
+    }
+
+    pub fn set_trend(&mut self, trend: Option<Trend>) {
+        // Shape of WOE by value (monotonic, peak or valley), applies to 
+        // exact numeric binning
+        self.trend = trend;
+    }
+
+    pub fn set_auto_trend(&mut self, auto_trend: bool) {
+        // Exact numeric binning is done for each trend and the one with 
+        // maximal IV is taken (the first of equal ones, so monotonic 
+        // trends win ties), set_trend is ignored
+        self.auto_trend = auto_trend;
+    }
+
+    pub fn set_max_prebins(&mut self, max_prebins: usize) {
+        // Exact numeric binning groups values into at most max_prebins 
+        // pre-bins of about equal number of rows, time is O(max_prebins^3)
+        self.max_prebins = max_prebins;
+    }
+
+    pub fn set_connectivity(&mut self, edges: &[(usize, usize)]) {
+        // Pairs of values that can be merged in categorial binning, 
+        // a bin can grow only by values adjacent to any of its values. 
+        // Edges with values missing in series are ignored.
+        self.connectivity = Some(edges.to_vec());
+    }
+
+    pub fn set_must_link(&mut self, groups: &[Vec<usize>]) -> bool {
+        // Groups of values that always share a bin in categorial 
+        // binning, they are merged before the binning and never split 
+        // by set_bins_num. Values missing in series are ignored. Returns 
+        // false and keeps the previous groups if groups (joined by 
+        // common values) contain both values of a cannot-link pair.
+        if Self::_is_linked_conflict(groups, &self.cannot_link) {
+            return false;
+        }
+        self.must_link = groups.to_vec();
+        true
+    }
+
+    pub fn set_cannot_link(&mut self, pairs: &[(usize, usize)]) -> bool {
+        // Pairs of values that never share a bin in categorial binning, 
+        // the binning can stop above the desirable number of bins. 
+        // Values missing in series are ignored. Returns false and keeps 
+        // the previous pairs if a value is paired with itself or both 
+        // values of a pair are must-link.
+        if Self::_is_linked_conflict(&self.must_link, pairs) {
+            return false;
         }
+        self.cannot_link = pairs.to_vec();
+        true
+    }
+
+    pub fn set_rare_level(&mut self, min_count: usize, min_share: f64) {
+        // Categorial values with fewer than min_count rows or a smaller 
+        // share of rows than min_share are pooled into the other bin 
+        // before the binning, so their noisy WOE does not drive merges. 
+        // The other bin also takes unseen values in transform. Values of 
+        // cannot-link pairs and must-link groups are never pooled.
+        self.rare_min_count = min_count;
+        self.rare_min_share = min_share;
+    }
+
+    pub fn set_cross_fitting(&mut self, folds: usize, seed: u64) {
+        // Processing of a series also gives out-of-fold WOE of its rows: 
+        // the rows are split into folds at random (reproducible by seed) 
+        // and each fold is transformed by the binning fitted on the other 
+        // ones, so the WOE does not leak the target of the row. Folds 
+        // less than 2 turn it off, more folds than rows are reduced to 
+        // the number of rows. Does not apply to processing of counts.
+        self.folds = folds;
+        self.seed = seed;
+    }
+
+    pub fn set_low_memory(&mut self, low_memory: bool) {
+        // Categorial binning with O(n) memory in the number of 
+        // distinct values, recommended for thousands of them
+        self.low_memory = low_memory;
+    }
+
+    pub fn set_observer(&mut self, observer: Option<Observer>) {
+        // Called on every merge of bins, can stop the binning earlier
+        self.clusterizer.set_observer(observer);
+    }
+
+    pub fn set_bin_order(&mut self, bin_order: BinOrder) {
+        // Applies to categorial binning, numeric bins are always 
+        // ordered by value
+        self.bin_order = bin_order;
     }
 
     pub fn process_categorial(&mut self, series: &[usize], target: &[bool]) {
-        assert_eq!(self.clusters, None);
         assert_eq!(series.len(), target.len());
+        self._process(series, target, Self::process_categorial_counts);
+    }
+
+    pub fn process_numeric(&mut self, series: &[usize], target: &[bool]) {
+        assert_eq!(series.len(), target.len());
+        self._process(series, target, Self::process_numeric_counts);
+    }
 
-        let initial_clusters = self._collect_clusters(series, target);
+    pub fn process_numeric_exact(&mut self, series: &[usize], 
+                                 target: &[bool]) {
+        assert_eq!(series.len(), target.len());
+        self._process(series, target, Self::process_numeric_exact_counts);
+    }
 
-        self.clusterizer.initialize(&initial_clusters);
+    pub fn process_categorial_ordered(&mut self, series: &[usize], 
+                                      target: &[bool]) {
+        assert_eq!(series.len(), target.len());
+        self._process(series, target, Self::process_categorial_ordered_counts);
+    }
+
+    pub fn process_categorial_ordered_exact(&mut self, series: &[usize], 
+                                            target: &[bool]) {
+        assert_eq!(series.len(), target.len());
+        self._process(series, target, 
+                      Self::process_categorial_ordered_exact_counts);
+    }
+
+    fn _process(&mut self, series: &[usize], target: &[bool], 
+                process: fn(&mut Self, &ValueCounts)) {
+        process(self, &ValueCounts::from_series(series, target));
+        if self.folds > 1 {
+            self._cross_fit(series, target, process);
+        }
+    }
+
+    fn _cross_fit(&mut self, series: &[usize], target: &[bool], 
+                  process: fn(&mut Self, &ValueCounts)) {
+        // Rows are shuffled into folds, WOE of the rows of a fold comes 
+        // from the binning fitted on the other folds with the same 
+        // settings. There are not more folds than rows, so none of 
+        // them is empty.
+        let folds = self.folds.min(series.len());
+
+        let mut order: Vec<usize> = (0..series.len()).collect();
+        order.shuffle(&mut StdRng::seed_from_u64(self.seed));
+        let mut row_folds = vec![0; series.len()];
+        for (pos, &row) in order.iter().enumerate() {
+            row_folds[row] = pos % folds;
+        }
+
+        let mut oof_woe = vec![0.0; series.len()];
+
+        for fold in 0..folds {
+            let mut counts = ValueCounts::new();
+            for row in (0..series.len()).filter(|&row| row_folds[row] != fold) {
+                counts.add(series[row], target[row]);
+            }
+            // A single row has no other folds, its value is unseen
+            if counts.get_values_num() == 0 {
+                continue;
+            }
+
+            let mut wbp = self._fold_proc();
+            process(&mut wbp, &counts);
+
+            let rows: Vec<usize> = (0..series.len())
+                .filter(|&row| row_folds[row] == fold)
+                .collect();
+            let values: Vec<usize> = rows.iter().map(|&row| series[row])
+                .collect();
+            for (row, woe) in rows.into_iter().zip(wbp.transform(&values)) {
+                oof_woe[row] = woe;
+            }
+        }
+
+        self.oof_woe = Some(oof_woe);
+        self.fit_woe = Some(self.transform(series));
+    }
+
+    fn _fold_proc(&self) -> Self {
+        // A new binning with the settings of this one (except the 
+        // observer)
+        let mut wbp = Self::new(self.desirable_bins_num, self.smooth);
+        wbp.bin_order = self.bin_order;
+        wbp.low_memory = self.low_memory;
+        wbp.connectivity = self.connectivity.clone();
+        wbp.must_link = self.must_link.clone();
+        wbp.cannot_link = self.cannot_link.clone();
+        wbp.rare_min_count = self.rare_min_count;
+        wbp.rare_min_share = self.rare_min_share;
+        wbp.min_bin_size = self.min_bin_size;
+        wbp.trend = self.trend;
+        wbp.auto_trend = self.auto_trend;
+        wbp.max_prebins = self.max_prebins;
+        wbp
+    }
+
+    // The same processing from counts of values collected beforehand 
+    // (for example, chunk by chunk from columnar files)
+
+    pub fn process_categorial_counts(&mut self, counts: &ValueCounts) {
+        assert_eq!(self.clusters, None);
+
+        self._collect_clusters(counts);
+
+        if let Some(edges) = &self.connectivity {
+            let edges: Vec<(usize, usize)> = edges.iter()
+                .filter_map(|(value1, value2)| 
+                    self.values.binary_search(value1).ok()
+                        .zip(self.values.binary_search(value2).ok())
+                )
+                .collect();
+            self.clusterizer.set_connectivity(&edges);
+        }
+
+        for (k, (value1, value2)) in self.cannot_link.iter().enumerate() {
+            if let (Ok(idx1), Ok(idx2)) = (self.values.binary_search(value1), 
+                                           self.values.binary_search(value2)) {
+                // Checked in set_cannot_link
+                assert_ne!(idx1, idx2, "Value is cannot-link to itself");
+                self.initial_clusters[idx1].cannot_link.push(2 * k);
+                self.initial_clusters[idx2].cannot_link.push(2 * k + 1);
+            }
+        }
+
+        let rows_num = counts.get_rows_num() as f64;
+        self.rare_values = self.values.iter().zip(self.initial_clusters.iter())
+            .filter(|(value, cluster)| cluster.cannot_link.is_empty() && 
+                !self.must_link.iter().any(|group| group.contains(value)) && (
+                cluster.n < self.rare_min_count || 
+                (cluster.n as f64) < self.rare_min_share * rows_num
+            ))
+            .map(|(&value, _)| value)
+            .collect();
+
+        self.clusterizer.set_low_memory_opt(self.low_memory);
+        self.clusterizer.initialize(&self.initial_clusters);
+        self._merge_linked();
         self.clusterizer.clusterize(self.desirable_bins_num);
-        self.clusters = Some(self.clusterizer.get_clusters());
+        self.clusters = Some(self._sort_clusters(self.bin_order));
     }
 
-    pub fn process_numeric(&mut self, series: &[usize], target: &[bool]) {
+    pub fn process_numeric_counts(&mut self, counts: &ValueCounts) {
         assert_eq!(self.clusters, None);
-        assert_eq!(series.len(), target.len());
 
-        let mut initial_clusters = self._collect_clusters(series, target);
-        initial_clusters.sort_by(
-            |a, b| a.values[0].partial_cmp(&b.values[0]).unwrap()
-        );
+        self.is_numeric = true;
+
+        // Initial clusters are already ordered by value
+        let initial_clusters = self._collect_clusters(counts);
         
         self.clusterizer.set_1d_opt(true);
         self.clusterizer.initialize(&initial_clusters);
         self.clusterizer.clusterize(self.desirable_bins_num);
-        self.clusters = Some(self.clusterizer.get_clusters());
+        self.clusters = Some(self._sort_clusters(BinOrder::Value));
+    }
+
+    pub fn process_numeric_exact_counts(&mut self, counts: &ValueCounts) {
+        // Bins with maximal total IV over pre-bins (instead of greedy 
+        // merging) satisfying min_bin_size and trend
+        assert_eq!(self.clusters, None);
+
+        self.is_numeric = true;
+
+        let initial_clusters = self._collect_clusters(counts);
+
+        self._process_exact(&initial_clusters);
+        self.clusters = Some(self._sort_clusters(BinOrder::Value));
+    }
+
+    pub fn process_categorial_ordered_counts(&mut self, counts: &ValueCounts) {
+        // Categorial binning where values are ordered by event rate and 
+        // only neighbours are merged, so it takes O(n log n) instead of 
+        // all the pairs of values
+        assert_eq!(self.clusters, None);
+
+        self.is_ordered = true;
+
+        let initial_clusters = self._collect_clusters_ordered(counts);
+
+        self.clusterizer.set_1d_opt(true);
+        self.clusterizer.initialize(&initial_clusters);
+        self.clusterizer.clusterize(self.desirable_bins_num);
+        self.clusters = Some(self._sort_clusters(self.bin_order));
+    }
+
+    pub fn process_categorial_ordered_exact_counts(&mut self, 
+                                                   counts: &ValueCounts) {
+        // The same as process_categorial_ordered, but bins are found as 
+        // in process_numeric_exact
+        assert_eq!(self.clusters, None);
+
+        self.is_ordered = true;
+
+        let initial_clusters = self._collect_clusters_ordered(counts);
+
+        self._process_exact(&initial_clusters);
+        self.clusters = Some(self._sort_clusters(self.bin_order));
+    }
+
+    fn _process_exact(&mut self, initial_clusters: &[WoeBinningCluster]) {
+        // Greedy binning for comparison
+        let mut clusterizer = HierarchicalClusterizer::new();
+        clusterizer.set_1d_opt(true);
+        clusterizer.initialize(initial_clusters);
+        clusterizer.clusterize(self.desirable_bins_num);
+        self.greedy_iv_total = Some(
+            clusterizer.get_clusters().iter()
+                .map(|c| calc_iv(c.p0, c.p1))
+                .sum()
+        );
+
+        // Pre-bins as ranges of initial clusters
+        let prebin_ends = Self::_prebin_ends(initial_clusters, 
+                                             self.max_prebins);
+        let mut prebins = Vec::new();
+        let mut start = 0;
+        for &end in prebin_ends.iter() {
+            prebins.push(Prebin {
+                n: initial_clusters[start..end].iter().map(|c| c.n).sum(),
+                p0: initial_clusters[start..end].iter().map(|c| c.p0).sum(),
+                p1: initial_clusters[start..end].iter().map(|c| c.p1).sum(),
+            });
+            start = end;
+        }
+
+        let partition = |trend: Option<Trend>| optimal_partition(
+            &prebins, self.desirable_bins_num, self.min_bin_size, 
+            trend, calc_iv
+        );
+
+        let ends = if self.auto_trend {
+            // IV of the best partition for each trend, None if there is 
+            // no feasible one
+            let partitions: Vec<(Trend, Option<Vec<usize>>)> = Trend::ALL
+                .iter()
+                .map(|&trend| (trend, partition(Some(trend))))
+                .collect();
+            let trend_ivs: Vec<(Trend, Option<f64>)> = partitions.iter()
+                .map(|(trend, ends)| (*trend, ends.as_ref().map(|ends| {
+                    let mut start = 0;
+                    ends.iter().map(|&end| {
+                        let bin = &prebins[start..end];
+                        start = end;
+                        calc_iv(bin.iter().map(|b| b.p0).sum(), 
+                                bin.iter().map(|b| b.p1).sum())
+                    }).sum()
+                })))
+                .collect();
+
+            let mut best: Option<(usize, f64)> = None;
+            for (idx, (_, iv)) in trend_ivs.iter().enumerate() {
+                if let Some(iv) = *iv {
+                    if best.is_none_or(|(_, iv_best)| iv > iv_best) {
+                        best = Some((idx, iv));
+                    }
+                }
+            }
+
+            self.trend_ivs = Some(trend_ivs);
+            self.trend = best.map(|(idx, _)| partitions[idx].0);
+            best.and_then(|(idx, _)| partitions[idx].1.clone())
+        } else {
+            partition(self.trend)
+        };
+
+        // Without a feasible partition everything goes to one bin
+        self.is_feasible = ends.is_some();
+        let ends = ends.unwrap_or(vec![prebins.len()]);
+
+        // Merge the initial clusters of each bin
+        self.clusterizer.set_1d_opt(true);
+        self.clusterizer.initialize(initial_clusters);
+
+        let mut start = 0;
+        for end in ends {
+            let end = prebin_ends[end - 1];
+            let mut cluster_id = start;
+            for idx in (start + 1)..end {
+                cluster_id = self.clusterizer.merge(cluster_id, idx).unwrap();
+            }
+            start = end;
+        }
+    }
+
+    pub fn get_trend(&self) -> Option<Trend> {
+        // The trend of exact binning, detected one with set_auto_trend
+        self.trend
+    }
+
+    pub fn get_trend_ivs(&self) -> Option<Vec<(Trend, Option<f64>)>> {
+        // IV of exact binning for each trend with set_auto_trend, None 
+        // for a trend without a feasible binning
+        self.trend_ivs.clone()
+    }
+
+    pub fn is_feasible(&self) -> bool {
+        // Whether exact binning found bins with at least min_bin_size 
+        // rows and WOE following the trend, otherwise all values are in 
+        // one bin. Always true for greedy binning.
+        self.is_feasible
+    }
+
+    pub fn get_iv_gap(&self) -> Option<f64> {
+        // IV of exact numeric binning minus IV of greedy one with 
+        // the same number of bins, can be negative if greedy binning 
+        // does not satisfy the constraints
+        self.greedy_iv_total.and_then(|greedy_iv_total| 
+            self.get_iv_total().map(|iv_total| iv_total - greedy_iv_total)
+        )
+    }
+
+    pub fn set_bins_num(&mut self, bins_num: usize) {
+        // Changes the number of bins of a processed binning without 
+        // processing again: merges are reverted or continued. Edited 
+        // bins are never reverted, so there can be fewer bins than 
+        // bins_num after an edit. Exact binning (until it is edited) is 
+        // found again for bins_num, along with its IV gap, trend and 
+        // feasibility.
+        assert!(self.is_done());
+
+        self.desirable_bins_num = bins_num;
+        if self.greedy_iv_total.is_some() {
+            let initial_clusters = self.initial_clusters.clone();
+            self._process_exact(&initial_clusters);
+        } else {
+            self.clusterizer.rollback(
+                self.edited_bins_num.or(self.linked_bins_num)
+                    .map_or(bins_num, |n| n.min(bins_num))
+            );
+            self.clusterizer.clusterize(bins_num);
+        }
+        self.clusters = Some(self._sort_clusters(self._get_bin_order()));
+    }
+
+    pub fn merge_bins(&mut self, idx1: usize, idx2: usize) -> bool {
+        // Merges two bins into one, numeric bins must be neighbours. 
+        // The new bin is locked if any of them is locked. Returns false 
+        // if the bins cannot be merged.
+        let mut bins = self._get_edited_bins();
+
+        if idx1 == idx2 || idx1 >= bins.len() || idx2 >= bins.len() || 
+                (self.is_numeric && idx1.abs_diff(idx2) != 1) {
+            return false;
+        }
+
+        let (members, locked) = bins.remove(idx1.max(idx2));
+        let bin = &mut bins[idx1.min(idx2)];
+        bin.0.extend(members);
+        bin.0.sort();
+        bin.1 |= locked;
+
+        self._rebuild(bins);
+        true
+    }
+
+    pub fn split_bin(&mut self, idx: usize, cut: usize) -> bool {
+        // Splits a numeric bin into values less than cut and the rest, 
+        // both bins keep the lock. Returns false if one of them would 
+        // be empty.
+        let mut bins = self._get_edited_bins();
+
+        if !self.is_numeric || idx >= bins.len() {
+            return false;
+        }
+
+        let (members, locked) = bins[idx].clone();
+        let pos = members.partition_point(|&member| self.values[member] < cut);
+
+        if pos == 0 || pos == members.len() {
+            return false;
+        }
+
+        bins[idx] = (members[..pos].to_vec(), locked);
+        bins.insert(idx + 1, (members[pos..].to_vec(), locked));
+
+        self._rebuild(bins);
+        true
+    }
+
+    pub fn move_value(&mut self, value: usize, idx: usize) -> bool {
+        // Moves a categorial value to another bin, the bin it leaves 
+        // disappears if it gets empty. Returns false for a numeric 
+        // binning or a value missing in series.
+        let mut bins = self._get_edited_bins();
+
+        if self.is_numeric || idx >= bins.len() {
+            return false;
+        }
+
+        let member = match self.values.iter().position(|&v| v == value) {
+            Some(member) => member,
+            None => return false,
+        };
+
+        for bin in bins.iter_mut() {
+            bin.0.retain(|&m| m != member);
+        }
+        bins[idx].0.push(member);
+        bins[idx].0.sort();
+        bins.retain(|bin| !bin.0.is_empty());
+
+        self._rebuild(bins);
+        true
+    }
+
+    pub fn set_bin_locked(&mut self, idx: usize, locked: bool) -> bool {
+        // A locked bin is kept as it is by set_bins_num, only manual 
+        // edits can change it
+        let mut bins = self._get_edited_bins();
+
+        if idx >= bins.len() {
+            return false;
+        }
+
+        bins[idx].1 = locked;
+
+        self._rebuild(bins);
+        true
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_numeric
     }
 
     pub fn is_done(&self) -> bool {
@@ -112,31 +753,45 @@ impl WoeBinningProc {
 
     pub fn get_bins_array(&self) -> Option<Vec<Vec<usize>>> {
         self.clusters.as_ref().map(|v| 
-            v.iter().map(|c| c.values.clone()).collect()
+            v.iter().map(|(id, _)| self._get_values(*id)).collect()
         )
     }
 
     pub fn get_size_array(&self) -> Option<Vec<usize>> {
         self.clusters.as_ref().map(|v| 
-            v.iter().map(|c| c.values.len()).collect()
+            v.iter().map(|(_, c)| c.size).collect()
+        )
+    }
+
+    pub fn get_count_array(&self) -> Option<Vec<usize>> {
+        // Number of rows in each bin
+        self.clusters.as_ref().map(|v| 
+            v.iter().map(|(_, c)| c.n).collect()
+        )
+    }
+
+    pub fn get_event_array(&self) -> Option<Vec<usize>> {
+        // Number of rows of target 1 in each bin
+        self.clusters.as_ref().map(|v| 
+            v.iter().map(|(_, c)| c.n1).collect()
         )
     }
 
     pub fn get_bin_values(&self, idx: usize) -> Option<Vec<usize>> {
         self.clusters.as_ref().map(|v| 
-            v[idx].values.clone()
+            self._get_values(v[idx].0)
         )
     }
 
     pub fn get_woe_array(&self) -> Option<Vec<f64>> {
         self.clusters.as_ref().map(|v| 
-            v.iter().map(|c| calc_woe(c.p0, c.p1)).collect()
+            v.iter().map(|(_, c)| calc_woe(c.p0, c.p1)).collect()
         )
     }
 
     pub fn get_iv_array(&self) -> Option<Vec<f64>> {
         self.clusters.as_ref().map(|v| 
-            v.iter().map(|c| calc_iv(c.p0, c.p1)).collect()
+            v.iter().map(|(_, c)| calc_iv(c.p0, c.p1)).collect()
         )
     }
 
@@ -144,53 +799,418 @@ impl WoeBinningProc {
         self.get_iv_array().map(|v| v.iter().sum())
     }
 
-    fn _collect_clusters(&self, series: &[usize], target: &[bool]) -> 
-                Vec<WoeBinningCluster> {
-        // Statistics for values in series
-        let mut stat = HashMap::new();
+    pub fn get_metrics(&self) -> Option<Metrics> {
+        // Gini (AUC), KS and divergence of WOE on the processed data
+        self.clusters.as_ref().map(|v| calc_metrics(
+            &v.iter().map(|(_, c)| (calc_woe(c.p0, c.p1), c.n1, c.n0))
+                .collect::<Vec<(f64, usize, usize)>>()
+        ))
+    }
 
-        // Counts of 1 and 0 targets
-        let mut c1 = 0;
-        let mut c0 = 0;
-
-        // Collect statistics
-        for idx in 0..series.len() {
-            let value = series[idx];
-
-            if !stat.contains_key(&value) {
-                stat.insert(
-                    value, 
-                    WoeBinningCluster {
-                        n: 0,
-                        n1: 0,
-                        n0: 0,
-                        p1: 0.0,
-                        p0: 0.0,
-                        values: vec![value],
-                        smooth: self.smooth,
+    pub fn get_metrics_on(&self, series: &[usize], 
+                          target: &[bool]) -> Metrics {
+        // The same on other data, values are transformed to WOE
+        calc_metrics_by_scores(&self.transform(series), target)
+    }
+
+    pub fn transform(&self, series: &[usize]) -> Vec<f64> {
+        // WOE of the bins of values. Numeric values between bins go to 
+        // the left one (less than all go to the first), categorial values 
+        // missing in bins go to the other bin or get zero WOE without it.
+        assert!(self.is_done());
+
+        let bins = self.get_bins_array().unwrap();
+        let woe_array = self.get_woe_array().unwrap();
+
+        if self.is_numeric {
+            series.iter()
+                .map(|&value| {
+                    let idx = bins.partition_point(|bin| bin[0] <= value);
+                    woe_array[idx.max(1) - 1]
+                })
+                .collect()
+        } else {
+            let woe_other = self.get_other_bin()
+                .map_or(0.0, |idx| woe_array[idx]);
+            let woe_map: BTreeMap<usize, f64> = bins.iter()
+                .zip(woe_array)
+                .flat_map(|(values, woe)| values.iter().map(move |&v| (v, woe)))
+                .collect();
+            series.iter()
+                .map(|value| woe_map.get(value).cloned().unwrap_or(woe_other))
+                .collect()
+        }
+    }
+
+    pub fn get_oof_woe_array(&self) -> Option<Vec<f64>> {
+        // Out-of-fold WOE of the rows of the processed series, with the 
+        // number of bins at processing
+        self.oof_woe.clone()
+    }
+
+    pub fn get_fit_woe_array(&self) -> Option<Vec<f64>> {
+        // WOE of the same rows by the binning fitted on all of them, 
+        // as for scoring. Like the out-of-fold WOE it is kept with the 
+        // bins at processing, transform gives WOE of the current bins 
+        // after set_bins_num or merges.
+        self.fit_woe.clone()
+    }
+
+    pub fn get_other_bin(&self) -> Option<usize> {
+        // Index of the bin with the rare values, None if there are no 
+        // rare values
+        let value = *self.rare_values.first()?;
+        self.get_bins_array()?.iter()
+            .position(|values| values.contains(&value))
+    }
+
+    pub fn get_rare_values(&self) -> &[usize] {
+        &self.rare_values
+    }
+
+    pub fn get_blocking_constraints(&self) -> Option<Vec<Constraint>> {
+        // Constraints that keep the binning from the desirable number of 
+        // bins: must-link groups that leave fewer bins (there would be 
+        // more bins without the group), cannot-link pairs between 
+        // remaining bins that could be merged otherwise (they are 
+        // unlocked and connected). Empty if the number is reached.
+        self.clusters.as_ref().map(|clusters| {
+            if clusters.len() < self.desirable_bins_num && 
+                    self.linked_bins_num.is_some() {
+                let components_num = self._linked_components_num(None);
+                (0..self.must_link.len())
+                    .filter(|&k| 
+                        self._linked_components_num(Some(k)) > components_num
+                    )
+                    .map(Constraint::MustLink)
+                    .collect()
+            } else if clusters.len() > self.desirable_bins_num && 
+                    !self.clusterizer.is_stopped() {
+                let bin_map: BTreeMap<usize, usize> = clusters.iter()
+                    .enumerate()
+                    .flat_map(|(bin, (cluster_id, _))| 
+                        self._get_values(*cluster_id).into_iter()
+                            .map(move |value| (value, bin))
+                    )
+                    .collect();
+                let is_locked = |bin: usize| 
+                    self.clusterizer.is_locked(clusters[bin].0);
+                let is_connected = |bin1: usize, bin2: usize| 
+                    self.connectivity.as_ref().is_none_or(|edges| 
+                        edges.iter().any(|(value1, value2)| {
+                            let bins = (bin_map.get(value1), 
+                                        bin_map.get(value2));
+                            bins == (Some(&bin1), Some(&bin2)) || 
+                                bins == (Some(&bin2), Some(&bin1))
+                        })
+                    );
+
+                self.cannot_link.iter().enumerate()
+                    .filter(|(_, (value1, value2))| 
+                        match (bin_map.get(value1), bin_map.get(value2)) {
+                            (Some(&bin1), Some(&bin2)) => 
+                                !is_locked(bin1) && !is_locked(bin2) && 
+                                is_connected(bin1, bin2),
+                            _ => false,
+                        }
+                    )
+                    .map(|(k, _)| Constraint::CannotLink(k))
+                    .collect()
+            } else {
+                Vec::new()
+            }
+        })
+    }
+
+    pub fn get_locked_array(&self) -> Option<Vec<bool>> {
+        self.clusters.as_ref().map(|v| 
+            v.iter().map(|(id, _)| self.clusterizer.is_locked(*id)).collect()
+        )
+    }
+
+    fn _get_bin_order(&self) -> BinOrder {
+        if self.is_numeric { 
+            BinOrder::Value 
+        } else { 
+            self.bin_order 
+        }
+    }
+
+    fn _get_edited_bins(&self) -> Vec<(Vec<usize>, bool)> {
+        // Indices of initial clusters and the lock of each bin, 
+        // in the order of bins
+        assert!(self.is_done());
+
+        self.clusters.as_ref().unwrap().iter()
+            .map(|(id, _)| {
+                let mut members = self.clusterizer.get_members(*id);
+                members.sort();
+                (members, self.clusterizer.is_locked(*id))
+            })
+            .collect()
+    }
+
+    fn _rebuild(&mut self, mut bins: Vec<(Vec<usize>, bool)>) {
+        // Bins are made again from the statistics of initial clusters, 
+        // so raw data is not needed. Members of each bin must go in a row 
+        // for the 1D clusterizer, so ordered categorial values are 
+        // reordered by bins (the bins ordered by event rate).
+        if self.is_ordered {
+            bins.sort_by(|(a, _), (b, _)| {
+                let a = self._merge_members(a);
+                let b = self._merge_members(b);
+                ((a.n1 + 1) * (b.n + 2)).cmp(&((b.n1 + 1) * (a.n + 2)))
+                    .then(a.value_min.cmp(&b.value_min))
+            });
+
+            let order: Vec<usize> = bins.iter()
+                .flat_map(|(members, _)| members.iter().cloned())
+                .collect();
+            self.values = order.iter().map(|&idx| self.values[idx]).collect();
+            self.initial_clusters = order.iter()
+                .map(|&idx| self.initial_clusters[idx].clone())
+                .collect();
+
+            let mut start = 0;
+            for (members, _) in bins.iter_mut() {
+                let end = start + members.len();
+                *members = (start..end).collect();
+                start = end;
+            }
+        }
+
+        self.clusterizer.initialize(&self.initial_clusters);
+
+        for (members, locked) in bins.iter() {
+            let mut cluster_id = members[0];
+            for &member in members[1..].iter() {
+                cluster_id = self.clusterizer.merge(cluster_id, member)
+                    .unwrap();
+            }
+            if *locked {
+                self.clusterizer.set_locked(cluster_id, true);
+            }
+        }
+
+        self.desirable_bins_num = bins.len();
+        self.edited_bins_num = Some(bins.len());
+        self.linked_bins_num = None;
+        self.greedy_iv_total = None;
+        self.clusters = Some(self._sort_clusters(self._get_bin_order()));
+    }
+
+    fn _merge_linked(&mut self) {
+        // Must-link groups and the rare values are merged beforehand. 
+        // Groups sharing a value are joined, so the clusters to merge are 
+        // the connected components of values.
+        let components = self._linked_components(None);
+
+        let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (member, &label) in components.iter().enumerate() {
+            groups.entry(label).or_default().push(member);
+        }
+
+        let mut is_merged = false;
+        for members in groups.values().filter(|members| members.len() > 1) {
+            // Checked in set_must_link and set_cannot_link, the rare 
+            // values are neither must-link nor cannot-link
+            let cluster = self._merge_members(members);
+            assert!(
+                !cluster.cannot_link.windows(2)
+                    .any(|tags| tags[0] ^ 1 == tags[1]),
+                "Must-link values are cannot-link"
+            );
+
+            let mut cluster_id = members[0];
+            for &member in members[1..].iter() {
+                cluster_id = self.clusterizer.merge(cluster_id, member)
+                    .unwrap();
+            }
+            is_merged = true;
+        }
+
+        // The merges are never reverted by set_bins_num
+        self.linked_bins_num = is_merged.then(|| 
+            self.clusterizer.cluster_count()
+        );
+    }
+
+    fn _linked_components(&self, skip: Option<usize>) -> Vec<usize> {
+        // Component label of each initial cluster by the must-link groups 
+        // (except the skipped one) and the rare values
+        let mut components: Vec<usize> = (0..self.values.len()).collect();
+        let groups = self.must_link.iter().enumerate()
+            .filter(|&(k, _)| Some(k) != skip)
+            .map(|(_, group)| group)
+            .chain([&self.rare_values]);
+        for group in groups {
+            let members: Vec<usize> = group.iter()
+                .filter_map(|value| self.values.binary_search(value).ok())
+                .collect();
+            let labels: Vec<usize> = members.iter()
+                .map(|&member| components[member])
+                .collect();
+            if let Some(&label_min) = labels.iter().min() {
+                for label in components.iter_mut() {
+                    if labels.contains(label) {
+                        *label = label_min;
                     }
-                );
+                }
             }
+        }
+        components
+    }
 
-            let cluster = stat.get_mut(&value).unwrap();
-            cluster.n += 1;
-            if target[idx] {
-                cluster.n1 += 1;
-                c1 += 1;
-            } else {
-                cluster.n0 += 1;
-                c0 += 1;
+    fn _linked_components_num(&self, skip: Option<usize>) -> usize {
+        self._linked_components(skip).into_iter()
+            .collect::<BTreeSet<usize>>()
+            .len()
+    }
+
+    fn _is_linked_conflict(groups: &[Vec<usize>], 
+                           pairs: &[(usize, usize)]) -> bool {
+        // Whether a value is cannot-link to itself or to a value of 
+        // the same must-link component (groups sharing a value are joined)
+        let mut components: BTreeMap<usize, usize> = BTreeMap::new();
+        for (idx, group) in groups.iter().enumerate() {
+            let labels: BTreeSet<usize> = group.iter()
+                .filter_map(|value| components.get(value).cloned())
+                .chain([idx])
+                .collect();
+            for label in components.values_mut() {
+                if labels.contains(label) {
+                    *label = idx;
+                }
+            }
+            for &value in group.iter() {
+                components.insert(value, idx);
             }
         }
 
+        pairs.iter().any(|(value1, value2)| value1 == value2 || 
+            components.get(value1).is_some_and(|label| 
+                components.get(value2) == Some(label)
+            )
+        )
+    }
+
+    fn _merge_members(&self, members: &[usize]) -> WoeBinningCluster {
+        members[1..].iter().fold(
+            self.initial_clusters[members[0]].clone(), 
+            |cluster, &member| WoeBinningCluster::merge(
+                &cluster, &self.initial_clusters[member]
+            )
+        )
+    }
+
+    fn _collect_clusters(&mut self, counts: &ValueCounts) -> 
+                Vec<WoeBinningCluster> {
+        // Statistics for values in series (ordered by value to keep 
+        // the clustering reproducible)
+        let mut stat: BTreeMap<usize, WoeBinningCluster> = counts.counts
+            .iter()
+            .map(|(&value, &(n1, n0))| (value, WoeBinningCluster {
+                n: n1 + n0,
+                n1,
+                n0,
+                p1: 0.0,
+                p0: 0.0,
+                size: 1,
+                value_min: value,
+                smooth: self.smooth,
+                cannot_link: Vec::new(),
+            }))
+            .collect();
+
+        // Counts of 1 and 0 targets
+        let c1: usize = stat.values().map(|c| c.n1).sum();
+        let c0: usize = stat.values().map(|c| c.n0).sum();
+
         // Calculate parts goods and bads for each value (using Laplas formula)
-        for (_, cluster) in stat.iter_mut() {
+        for cluster in stat.values_mut() {
             cluster.p1 = (cluster.n1 + 1) as f64 / (c1 + 2) as f64;
             cluster.p0 = (cluster.n0 + 1) as f64 / (c0 + 2) as f64;
         }
 
+        // Values by index of initial cluster, the clusterizer keeps 
+        // only the indices. They are ordered unless initial clusters 
+        // are reordered.
+        self.values = stat.keys().cloned().collect();
+        self.initial_clusters = stat.into_values().collect();
+
         // Return clusters as a vector
-        stat.into_values().collect()
+        self.initial_clusters.clone()
+    }
+
+    fn _collect_clusters_ordered(&mut self, counts: &ValueCounts) -> 
+                Vec<WoeBinningCluster> {
+        let initial_clusters = self._collect_clusters(counts);
+
+        // Order by smoothed event rate (n1 + 1) / (n + 2), ties are 
+        // resolved by value
+        let mut order: Vec<usize> = (0..initial_clusters.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&initial_clusters[a], &initial_clusters[b]);
+            ((a.n1 + 1) * (b.n + 2)).cmp(&((b.n1 + 1) * (a.n + 2)))
+                .then(a.value_min.cmp(&b.value_min))
+        });
+
+        self.values = order.iter().map(|&idx| self.values[idx]).collect();
+        self.initial_clusters = order.into_iter()
+            .map(|idx| initial_clusters[idx].clone())
+            .collect();
+        self.initial_clusters.clone()
+    }
+
+    fn _prebin_ends(clusters: &[WoeBinningCluster], 
+                    max_prebins: usize) -> Vec<usize> {
+        // End indices of pre-bins with about equal number of rows, 
+        // a value never splits between pre-bins
+        let total: usize = clusters.iter().map(|c| c.n).sum();
+        let mut ends = Vec::new();
+        let mut acc = 0;
+
+        for (idx, cluster) in clusters.iter().enumerate() {
+            acc += cluster.n;
+            if acc * max_prebins >= total * (ends.len() + 1) || 
+                    idx == clusters.len() - 1 {
+                ends.push(idx + 1);
+            }
+        }
+
+        ends
+    }
+
+    fn _get_values(&self, cluster_id: usize) -> Vec<usize> {
+        let mut values: Vec<usize> = self.clusterizer.get_members(cluster_id)
+            .into_iter()
+            .map(|idx| self.values[idx])
+            .collect();
+        values.sort();
+        values
+    }
+
+    fn _sort_clusters(&self, bin_order: BinOrder) -> 
+                Vec<(usize, WoeBinningCluster)> {
+        let mut clusters: Vec<(usize, WoeBinningCluster)> = 
+            self.clusterizer.get_cluster_ids().into_iter()
+                .zip(self.clusterizer.get_clusters())
+                .collect();
+
+        // Ties are resolved by the smallest value, values never repeat 
+        // across bins so the order is total
+        match bin_order {
+            BinOrder::Value => clusters.sort_by_key(|(_, c)| c.value_min),
+            BinOrder::Woe => clusters.sort_by(|(_, a), (_, b)| 
+                calc_woe(a.p0, a.p1).total_cmp(&calc_woe(b.p0, b.p1))
+                    .then(a.value_min.cmp(&b.value_min))
+            ),
+            BinOrder::Count => clusters.sort_by(|(_, a), (_, b)| 
+                b.n.cmp(&a.n).then(a.value_min.cmp(&b.value_min))
+            ),
+        }
+
+        clusters
     }
 }
 
@@ -218,8 +1238,8 @@ mod tests {
             agg_sum += prob;
             cat_map.push(agg_sum);
         }
-        for idx in 0..cat_num {
-            cat_map[idx] /= agg_sum;
+        for prob in cat_map.iter_mut() {
+            *prob /= agg_sum;
         }
         cat_map
     }
@@ -233,7 +1253,7 @@ mod tests {
             prob_map.push(prob);
         }
         if sorted {
-            prob_map.sort_by(|a, b| a.partial_cmp(&b).unwrap())
+            prob_map.sort_by(|a, b| a.partial_cmp(b).unwrap())
         }
         prob_map
     }
@@ -260,23 +1280,564 @@ mod tests {
     fn test_categorial() {
         let (series, target) = create_dataset(1_000, 10, false);
 
-        let mut wbp = WoeBinningProc::new(4);
+        let mut wbp = WoeBinningProc::new(4, 1.0);
         wbp.process_categorial(&series, &target);
 
-        assert_eq!(wbp.is_done(), true);
+        assert!(wbp.is_done());
         assert_eq!(wbp.get_bins_num(), Some(4));
-        assert_eq!(wbp.get_iv_total(), Some(1.1660067107931673));
+        assert_eq!(wbp.get_iv_total(), Some(1.15316200227617));
     }
 
     #[test]
     fn test_numeric() {
         let (series, target) = create_dataset(1_000, 10, true);
 
-        let mut wbp = WoeBinningProc::new(4);
+        let mut wbp = WoeBinningProc::new(4, 1.0);
         wbp.process_numeric(&series, &target);
 
-        assert_eq!(wbp.is_done(), true);
+        assert!(wbp.is_done());
         assert_eq!(wbp.get_bins_num(), Some(4));
         assert_eq!(wbp.get_iv_total(), Some(2.3937109658539946));
     }
+
+    #[test]
+    fn test_counts() {
+        let (series, target) = create_dataset(1_000, 10, true);
+
+        let mut counts = ValueCounts::new();
+        for (chunk, chunk_target) in series.chunks(300)
+                .zip(target.chunks(300)) {
+            counts.add_chunk(chunk, chunk_target);
+        }
+        assert_eq!(counts.get_rows_num(), 1_000);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.process_numeric_counts(&counts);
+
+        assert_eq!(wbp.get_iv_total(), Some(2.3937109658539946));
+    }
+
+    #[test]
+    fn test_low_memory() {
+        let (series, target) = create_dataset(1_000, 10, false);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.set_low_memory(true);
+        wbp.process_categorial(&series, &target);
+
+        assert_eq!(wbp.get_bins_num(), Some(4));
+        assert_eq!(wbp.get_iv_total(), Some(1.15316200227617));
+    }
+
+    #[test]
+    fn test_connectivity() {
+        let (series, target) = create_dataset(1_000, 10, false);
+
+        // Values form a chain 0-1-...-9, so all bins are intervals
+        let edges: Vec<(usize, usize)> = (1..10).map(|v| (v - 1, v)).collect();
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.set_connectivity(&edges);
+        wbp.set_bin_order(BinOrder::Value);
+        wbp.process_categorial(&series, &target);
+
+        assert_eq!(wbp.get_bins_num(), Some(4));
+        for values in wbp.get_bins_array().unwrap() {
+            assert_eq!(values.len(), values[values.len() - 1] - values[0] + 1);
+        }
+    }
+
+    #[test]
+    fn test_constraints() {
+        let (series, target) = create_dataset(1_000, 10, false);
+
+        let mut wbp = WoeBinningProc::new(3, 1.0);
+        wbp.set_must_link(&[vec![0, 9], vec![9, 5], vec![100, 1]]);
+        wbp.set_cannot_link(&[(0, 3), (3, 7), (2, 200), (7, 9)]);
+        wbp.process_categorial(&series, &target);
+
+        assert_eq!(wbp.get_bins_num(), Some(3));
+        assert_eq!(wbp.get_blocking_constraints(), Some(vec![]));
+
+        let bins = wbp.get_bins_array().unwrap();
+        let bin_of = |value: usize| 
+            bins.iter().position(|values| values.contains(&value)).unwrap();
+        assert_eq!(bin_of(0), bin_of(9));
+        assert_eq!(bin_of(0), bin_of(5));
+        assert_ne!(bin_of(0), bin_of(3));
+        assert_ne!(bin_of(3), bin_of(7));
+
+        // Bins of 0, 3 and 7 are pairwise cannot-link
+        wbp.set_bins_num(2);
+        assert_eq!(wbp.get_bins_num(), Some(3));
+        assert_eq!(wbp.get_blocking_constraints(), 
+                   Some(vec![Constraint::CannotLink(0), 
+                             Constraint::CannotLink(1), 
+                             Constraint::CannotLink(3)]));
+
+        // Must-link merges are not reverted
+        wbp.set_bins_num(10);
+        assert_eq!(wbp.get_bins_num(), Some(8));
+        let bins = wbp.get_bins_array().unwrap();
+        let bin_of = |value: usize| 
+            bins.iter().position(|values| values.contains(&value)).unwrap();
+        assert_eq!(bin_of(0), bin_of(9));
+        assert_eq!(bin_of(0), bin_of(5));
+        assert_eq!(wbp.get_blocking_constraints(), 
+                   Some(vec![Constraint::MustLink(0), 
+                             Constraint::MustLink(1)]));
+    }
+
+    #[test]
+    fn test_blocking_constraints() {
+        let (series, target) = create_dataset(1_000, 10, false);
+
+        // Any two of the first three groups join 0, 1 and 2, so none of 
+        // them alone leaves fewer bins
+        let mut wbp = WoeBinningProc::new(3, 1.0);
+        wbp.set_must_link(&[vec![0, 1], vec![1, 2], vec![0, 2], vec![3, 4]]);
+        wbp.process_categorial(&series, &target);
+        wbp.set_bins_num(10);
+        assert_eq!(wbp.get_bins_num(), Some(7));
+        assert_eq!(wbp.get_blocking_constraints(), 
+                   Some(vec![Constraint::MustLink(3)]));
+
+        // The bin of 7 is locked, so only the pair of 0 and 3 blocks
+        let mut wbp = WoeBinningProc::new(3, 1.0);
+        wbp.set_cannot_link(&[(0, 3), (3, 7), (7, 0)]);
+        wbp.process_categorial(&series, &target);
+        let bins = wbp.get_bins_array().unwrap();
+        let bin_7 = bins.iter().position(|values| values.contains(&7))
+            .unwrap();
+        assert!(wbp.set_bin_locked(bin_7, true));
+        wbp.set_bins_num(1);
+        assert_eq!(wbp.get_bins_num(), Some(3));
+        assert_eq!(wbp.get_blocking_constraints(), 
+                   Some(vec![Constraint::CannotLink(0)]));
+    }
+
+    #[test]
+    fn test_constraints_conflict() {
+        let (series, target) = create_dataset(1_000, 10, false);
+
+        let mut wbp = WoeBinningProc::new(3, 1.0);
+        assert!(wbp.set_must_link(&[vec![0, 1], vec![1, 2]]));
+        assert!(!wbp.set_cannot_link(&[(2, 0)]));
+        assert!(!wbp.set_cannot_link(&[(3, 3)]));
+        assert!(wbp.set_cannot_link(&[(3, 0)]));
+        assert!(!wbp.set_must_link(&[vec![0, 4], vec![4, 3]]));
+        wbp.process_categorial(&series, &target);
+
+        // The previous constraints are kept
+        let bins = wbp.get_bins_array().unwrap();
+        let bin_of = |value: usize| 
+            bins.iter().position(|values| values.contains(&value)).unwrap();
+        assert_eq!(bin_of(0), bin_of(2));
+        assert_ne!(bin_of(0), bin_of(3));
+    }
+
+    #[test]
+    fn test_rare_level_must_link() {
+        // Rare values of a must-link group are not pooled, so the pooling 
+        // does not join the group with other values
+        let (mut series, mut target) = create_dataset(1_000, 10, false);
+        series.extend([10, 11, 12]);
+        target.extend([true, false, true]);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.set_rare_level(2, 0.0);
+        assert!(wbp.set_must_link(&[vec![10, 0], vec![11, 1]]));
+        assert!(wbp.set_cannot_link(&[(0, 1)]));
+        wbp.process_categorial(&series, &target);
+
+        assert_eq!(wbp.get_rare_values(), &[12]);
+        let bins = wbp.get_bins_array().unwrap();
+        let bin_of = |value: usize| 
+            bins.iter().position(|values| values.contains(&value)).unwrap();
+        assert_eq!(bin_of(10), bin_of(0));
+        assert_eq!(bin_of(11), bin_of(1));
+        assert_ne!(bin_of(0), bin_of(1));
+    }
+
+    #[test]
+    fn test_rare_level() {
+        let (mut series, mut target) = create_dataset(1_000, 10, false);
+        series.extend([10, 11, 11, 12, 12, 12]);
+        target.extend([true, true, false, false, false, true]);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.set_rare_level(2, 0.002);
+        wbp.process_categorial(&series, &target);
+
+        assert_eq!(wbp.get_rare_values(), &[10, 11]);
+        assert_eq!(wbp.get_bins_num(), Some(4));
+
+        let other = wbp.get_other_bin().unwrap();
+        let values = wbp.get_bin_values(other).unwrap();
+        assert!(values.contains(&10) && values.contains(&11));
+        assert_eq!(wbp.transform(&[10, 100]), 
+                   vec![wbp.get_woe_array().unwrap()[other]; 2]);
+
+        // The rare values are never split
+        wbp.set_bins_num(20);
+        assert_eq!(wbp.get_bins_num(), Some(12));
+        assert_eq!(wbp.get_bin_values(wbp.get_other_bin().unwrap()), 
+                   Some(vec![10, 11]));
+    }
+
+    #[test]
+    fn test_cross_fitting() {
+        let (series, target) = create_dataset(1_000, 10, false);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.set_cross_fitting(5, 1);
+        wbp.process_categorial(&series, &target);
+
+        let oof_woe = wbp.get_oof_woe_array().unwrap();
+        assert_eq!(oof_woe.len(), 1_000);
+        assert_ne!(oof_woe, wbp.get_fit_woe_array().unwrap());
+        assert_eq!(wbp.get_fit_woe_array().unwrap(), wbp.transform(&series));
+
+        let mut wbp_same = WoeBinningProc::new(4, 1.0);
+        wbp_same.set_cross_fitting(5, 1);
+        wbp_same.process_categorial(&series, &target);
+        assert_eq!(wbp_same.get_oof_woe_array(), Some(oof_woe));
+
+        // Values of single rows are never seen by the binning of their 
+        // fold, while the full fit knows the target of each of them
+        let series: Vec<usize> = (0..100).collect();
+        let target: Vec<bool> = (0..100).map(|i| i % 3 == 0).collect();
+
+        let mut wbp = WoeBinningProc::new(100, 1.0);
+        wbp.set_cross_fitting(4, 0);
+        wbp.process_categorial(&series, &target);
+        assert_eq!(wbp.get_oof_woe_array(), Some(vec![0.0; 100]));
+        assert!(wbp.get_fit_woe_array().unwrap().iter().zip(&target)
+            .all(|(&woe, &t)| (woe < 0.0) == t));
+    }
+
+    #[test]
+    fn test_cross_fitting_bins_num() {
+        // Both WOE arrays keep the bins at processing
+        let (series, target) = create_dataset(1_000, 10, false);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.set_cross_fitting(5, 1);
+        wbp.process_categorial(&series, &target);
+        let oof_woe = wbp.get_oof_woe_array();
+        let fit_woe = wbp.get_fit_woe_array();
+
+        wbp.set_bins_num(2);
+        assert_eq!(wbp.get_oof_woe_array(), oof_woe);
+        assert_eq!(wbp.get_fit_woe_array(), fit_woe);
+        assert_ne!(wbp.get_fit_woe_array().unwrap(), wbp.transform(&series));
+    }
+
+    #[test]
+    fn test_cross_fitting_few_rows() {
+        // More folds than rows
+        let mut wbp = WoeBinningProc::new(2, 1.0);
+        wbp.set_cross_fitting(5, 0);
+        wbp.process_categorial(&[0, 0, 1], &[true, false, true]);
+        assert_eq!(wbp.get_oof_woe_array().unwrap().len(), 3);
+
+        let mut wbp = WoeBinningProc::new(2, 1.0);
+        wbp.set_cross_fitting(5, 0);
+        wbp.process_categorial(&[0], &[true]);
+        assert_eq!(wbp.get_oof_woe_array(), Some(vec![0.0]));
+    }
+
+    #[test]
+    fn test_numeric_exact() {
+        let (series, target) = create_dataset(1_000, 30, true);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.process_numeric_exact(&series, &target);
+
+        assert_eq!(wbp.get_bins_num(), Some(4));
+        assert!(wbp.get_iv_gap().unwrap() >= 0.0);
+
+        let mut wbp = WoeBinningProc::new(6, 1.0);
+        wbp.set_min_bin_size(100);
+        wbp.set_trend(Some(Trend::Ascending));
+        wbp.set_max_prebins(10);
+        wbp.process_numeric_exact(&series, &target);
+
+        let woe_array = wbp.get_woe_array().unwrap();
+        assert!(woe_array.windows(2).all(|w| w[0] < w[1]));
+        assert!(wbp.get_bins_num().unwrap() <= 6);
+        assert!(wbp.is_feasible());
+    }
+
+    #[test]
+    fn test_numeric_exact_infeasible() {
+        // No bin can have more rows than the series, so all values go
+        // to one bin and it is reported
+        let (series, target) = create_dataset(1_000, 30, true);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.process_numeric(&series, &target);
+        assert!(wbp.is_feasible());
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.set_min_bin_size(2_000);
+        wbp.process_numeric_exact(&series, &target);
+        assert!(!wbp.is_feasible());
+        assert_eq!(wbp.get_bins_num(), Some(1));
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.set_min_bin_size(2_000);
+        wbp.set_auto_trend(true);
+        wbp.process_numeric_exact(&series, &target);
+        assert!(!wbp.is_feasible());
+        assert_eq!(wbp.get_trend(), None);
+        assert!(wbp.get_trend_ivs().unwrap().iter().all(|(_, iv)| iv.is_none()));
+        assert_eq!(wbp.get_bins_num(), Some(1));
+    }
+
+    #[test]
+    fn test_numeric_exact_bins_num() {
+        // The exact binning is found again, not merged greedily
+        let (series, target) = create_dataset(1_000, 30, true);
+
+        let mut wbp = WoeBinningProc::new(6, 1.0);
+        wbp.set_min_bin_size(150);
+        wbp.set_trend(Some(Trend::Ascending));
+        wbp.process_numeric_exact(&series, &target);
+
+        let mut wbp_3 = WoeBinningProc::new(3, 1.0);
+        wbp_3.set_min_bin_size(150);
+        wbp_3.set_trend(Some(Trend::Ascending));
+        wbp_3.process_numeric_exact(&series, &target);
+
+        wbp.set_bins_num(3);
+        assert_eq!(wbp.get_bins_array(), wbp_3.get_bins_array());
+        assert_eq!(wbp.get_iv_total(), wbp_3.get_iv_total());
+        assert_eq!(wbp.get_iv_gap(), wbp_3.get_iv_gap());
+        assert!(wbp.is_feasible());
+
+        // Feasibility follows the number of bins
+        let mut wbp = WoeBinningProc::new(1, 1.0);
+        wbp.set_min_bin_size(600);
+        wbp.process_numeric_exact(&series, &target);
+        assert!(wbp.is_feasible());
+
+        wbp.set_min_bin_size(1_200);
+        wbp.set_bins_num(2);
+        assert!(!wbp.is_feasible());
+        assert_eq!(wbp.get_bins_num(), Some(1));
+    }
+
+    #[test]
+    fn test_auto_trend() {
+        // Event rate is high at both ends, so WOE makes a peak
+        let series: Vec<usize> = (0..3_000).map(|i| i % 30).collect();
+        let target: Vec<bool> = (0..3_000)
+            .map(|i| {
+                let x = (i % 30) as f64 / 15.0 - 1.0;
+                ((i / 30) as f64) < 100.0 * (0.1 + 0.6 * x * x)
+            })
+            .collect();
+
+        let mut wbp = WoeBinningProc::new(5, 1.0);
+        wbp.set_auto_trend(true);
+        wbp.process_numeric_exact(&series, &target);
+
+        assert_eq!(wbp.get_trend(), Some(Trend::Peak));
+
+        let trend_ivs = wbp.get_trend_ivs().unwrap();
+        assert_eq!(trend_ivs.iter().map(|(trend, _)| *trend)
+                       .collect::<Vec<Trend>>(), Trend::ALL.to_vec());
+        let iv_peak = trend_ivs[2].1.unwrap();
+        assert!((iv_peak - wbp.get_iv_total().unwrap()).abs() < 1e-12);
+        assert!(trend_ivs.iter()
+            .all(|(_, iv)| iv.is_none_or(|iv| iv <= iv_peak)));
+        assert_eq!(trend_ivs[3].1, None);
+
+        let woe_array = wbp.get_woe_array().unwrap();
+        let top = woe_array.iter().enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+        assert!(woe_array[..=top].windows(2).all(|w| w[0] < w[1]));
+        assert!(woe_array[top..].windows(2).all(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn test_categorial_ordered() {
+        let (series, target) = create_dataset(1_000, 30, false);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.process_categorial_ordered(&series, &target);
+        assert_eq!(wbp.get_bins_num(), Some(4));
+
+        let mut wbp_exact = WoeBinningProc::new(4, 1.0);
+        wbp_exact.process_categorial_ordered_exact(&series, &target);
+        assert_eq!(wbp_exact.get_bins_num(), Some(4));
+
+        assert!(wbp_exact.get_iv_total() >= wbp.get_iv_total());
+        assert!(wbp_exact.get_iv_gap().unwrap() >= 0.0);
+
+        // Each value is in exactly one bin
+        let mut values: Vec<usize> = wbp.get_bins_array().unwrap()
+            .into_iter().flatten().collect();
+        values.sort();
+        let mut expected = series.clone();
+        expected.sort();
+        expected.dedup();
+        assert_eq!(values, expected);
+    }
+
+    #[test]
+    fn test_set_bins_num() {
+        let (series, target) = create_dataset(1_000, 10, true);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.process_numeric(&series, &target);
+        let bins_array = wbp.get_bins_array();
+
+        wbp.set_bins_num(6);
+        assert_eq!(wbp.get_bins_num(), Some(6));
+
+        let mut wbp6 = WoeBinningProc::new(6, 1.0);
+        wbp6.process_numeric(&series, &target);
+        assert_eq!(wbp.get_bins_array(), wbp6.get_bins_array());
+
+        wbp.set_bins_num(4);
+        assert_eq!(wbp.get_bins_array(), bins_array);
+        assert_eq!(wbp.get_iv_total(), Some(2.3937109658539946));
+    }
+
+    #[test]
+    fn test_edit_numeric() {
+        let (series, target) = create_dataset(1_000, 10, true);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.process_numeric(&series, &target);
+        let bins_array = wbp.get_bins_array().unwrap();
+        let iv_total = wbp.get_iv_total().unwrap();
+
+        // Split the first bin of several values at its second value
+        let idx = bins_array.iter().position(|v| v.len() > 1).unwrap();
+        let cut = bins_array[idx][1];
+        assert!(!wbp.split_bin(idx, bins_array[idx][0]));
+        assert!(wbp.split_bin(idx, cut));
+        assert_eq!(wbp.get_bins_num(), Some(5));
+        assert_eq!(wbp.get_bin_values(idx + 1).unwrap()[0], cut);
+
+        // Merge it back
+        assert!(!wbp.merge_bins(idx, idx + 2));
+        assert!(wbp.merge_bins(idx, idx + 1));
+        assert_eq!(wbp.get_bins_array().unwrap(), bins_array);
+        assert!((wbp.get_iv_total().unwrap() - iv_total).abs() < 1e-12);
+
+        // The locked bin is neither merged nor reverted
+        assert!(wbp.set_bin_locked(0, true));
+        wbp.set_bins_num(1);
+        assert_eq!(wbp.get_bins_num(), Some(2));
+        assert_eq!(wbp.get_bin_values(0), Some(bins_array[0].clone()));
+        assert_eq!(wbp.get_locked_array(), Some(vec![true, false]));
+        wbp.set_bins_num(10);
+        assert_eq!(wbp.get_bins_num(), Some(4));
+
+        assert!(!wbp.move_value(bins_array[0][0], 1));
+    }
+
+    #[test]
+    fn test_edit_categorial() {
+        let (series, target) = create_dataset(1_000, 10, false);
+
+        for ordered in [false, true] {
+            let mut wbp = WoeBinningProc::new(4, 1.0);
+            if ordered {
+                wbp.process_categorial_ordered(&series, &target);
+            } else {
+                wbp.process_categorial(&series, &target);
+            }
+            let size_total: usize = wbp.get_size_array().unwrap().iter().sum();
+
+            // Move a value from the first bin to the last one
+            let value = wbp.get_bin_values(0).unwrap()[0];
+            let values_last = wbp.get_bin_values(3).unwrap();
+            assert!(!wbp.move_value(1_000, 3));
+            assert!(wbp.move_value(value, 3));
+
+            let bins_array = wbp.get_bins_array().unwrap();
+            let idx = bins_array.iter().position(|v| v.contains(&value))
+                .unwrap();
+            assert!(values_last.iter().all(|v| bins_array[idx].contains(v)));
+            assert_eq!(wbp.get_size_array().unwrap().iter().sum::<usize>(), 
+                       size_total);
+
+            // Statistics are the same as with merging the values 
+            // from the start
+            let mut wbp_merged = WoeBinningProc::new(3, 1.0);
+            wbp_merged.process_categorial(&series, &target);
+            while wbp.get_bins_num() > Some(1) {
+                assert!(wbp.merge_bins(0, 1));
+            }
+            assert!(!wbp.merge_bins(0, 1));
+            wbp_merged.set_bins_num(1);
+            assert!((wbp.get_iv_total().unwrap() - 
+                     wbp_merged.get_iv_total().unwrap()).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_transform() {
+        let (series, target) = create_dataset(1_000, 10, true);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.process_numeric(&series, &target);
+        let bins_array = wbp.get_bins_array().unwrap();
+        let woe_array = wbp.get_woe_array().unwrap();
+
+        let values = [bins_array[1][0], bins_array[3][0], 1_000];
+        assert_eq!(wbp.transform(&values), 
+                   vec![woe_array[1], woe_array[3], woe_array[3]]);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.process_categorial(&series, &target);
+        let bins_array = wbp.get_bins_array().unwrap();
+        let woe_array = wbp.get_woe_array().unwrap();
+
+        let values = [bins_array[2][0], 1_000];
+        assert_eq!(wbp.transform(&values), vec![woe_array[2], 0.0]);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let (series, target) = create_dataset(1_000, 10, true);
+
+        let mut wbp = WoeBinningProc::new(4, 1.0);
+        wbp.process_numeric(&series, &target);
+
+        let metrics = wbp.get_metrics().unwrap();
+        let metrics_on = wbp.get_metrics_on(&series, &target);
+        assert!((metrics.auc - metrics_on.auc).abs() < 1e-12);
+        assert!((metrics.ks - metrics_on.ks).abs() < 1e-12);
+        assert!((metrics.divergence - metrics_on.divergence).abs() < 1e-12);
+        assert!(metrics.gini > 0.0);
+    }
+
+    #[test]
+    fn test_bin_order() {
+        let (series, target) = create_dataset(1_000, 10, false);
+
+        let mut wbp1 = WoeBinningProc::new(4, 1.0);
+        wbp1.process_categorial(&series, &target);
+
+        let mut wbp2 = WoeBinningProc::new(4, 1.0);
+        wbp2.process_categorial(&series, &target);
+
+        assert_eq!(wbp1.get_bins_array(), wbp2.get_bins_array());
+
+        let woe_array = wbp1.get_woe_array().unwrap();
+        assert!(woe_array.windows(2).all(|w| w[0] <= w[1]));
+
+        let mut wbp3 = WoeBinningProc::new(4, 1.0);
+        wbp3.set_bin_order(BinOrder::Value);
+        wbp3.process_categorial(&series, &target);
+
+        let bins_array = wbp3.get_bins_array().unwrap();
+        assert!(bins_array.windows(2).all(|w| w[0][0] < w[1][0]));
+    }
 }
@@ -1,31 +1,197 @@
-use crate::woe_binning_proc::WoeBinningProc;
+use std::ffi::c_void;
+
+use crate::hierarchical_clusterizer::StepInfo;
+use crate::metrics::Metrics;
+use crate::optimal_binning::Trend;
+use crate::woe_binning_proc::{BinOrder, Constraint, WoeBinningProc};
+
+
+// Arguments: user data, merged cluster ids, distance, new cluster id and 
+// remaining cluster count. Returns true to stop.
+type ObserverCallback = extern "C" fn(*mut c_void, usize, usize, f64, 
+                                      usize, usize) -> bool;
+
+
+// User data of the observer callback, owned by the caller and only passed 
+// back to the callback
+struct UserData(*mut c_void);
+
+impl UserData {
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+// SAFETY: the observer must be Send + Sync to keep WoeBinningProc so. 
+// The library never dereferences the pointer, and the callback with it 
+// is only called by the binning methods, which take &mut self and thus 
+// run on one thread at a time. The caller guarantees that the callback 
+// and its user data may be used on the thread that calls those methods 
+// (the Python binding calls them with the GIL).
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
 
 
 #[repr(C)]
-struct BinInfo {
+pub(crate) struct BinInfo {
     woe: f64,
     iv: f64,
     size: usize,
 }
 
 
-struct WoeBinningProcWrapper {
-    wbp: WoeBinningProc,
+#[repr(C)]
+pub(crate) struct MetricsInfo {
+    auc: f64,
+    gini: f64,
+    ks: f64,
+    divergence: f64,
+}
+
+
+impl From<Metrics> for MetricsInfo {
+    fn from(metrics: Metrics) -> Self {
+        Self {
+            auc: metrics.auc,
+            gini: metrics.gini,
+            ks: metrics.ks,
+            divergence: metrics.divergence,
+        }
+    }
+}
+
+
+pub(crate) struct WoeBinningProcWrapper {
+    pub(crate) wbp: WoeBinningProc,
 }
 
 
 impl WoeBinningProcWrapper {
-    #[no_mangle]
     #[export_name="wbp_new"]
-    pub extern fn new(desirable_bins_num: usize, smooth: f64) -> Box<Self> {
+    pub extern "C" fn new(desirable_bins_num: usize, smooth: f64) -> Box<Self> {
         Box::new(Self {
             wbp: WoeBinningProc::new(desirable_bins_num, smooth)
         })
     }
 
-    #[no_mangle]
+    #[export_name="wbp_set_bin_order"]
+    pub extern "C" fn set_bin_order(&mut self, bin_order: usize) -> bool {
+        // False for an unknown code, the order is kept
+        let bin_order = match bin_order {
+            0 => BinOrder::Value,
+            1 => BinOrder::Woe,
+            2 => BinOrder::Count,
+            _ => return false,
+        };
+        self.wbp.set_bin_order(bin_order);
+        true
+    }
+
+    #[export_name="wbp_set_low_memory"]
+    pub extern "C" fn set_low_memory(&mut self, low_memory: bool) {
+        self.wbp.set_low_memory(low_memory);
+    }
+
+    #[export_name="wbp_set_connectivity"]
+    pub extern "C" fn set_connectivity(&mut self, size: usize, 
+                                       values1: *const usize, 
+                                       values2: *const usize) {
+        let (values1_buff, values2_buff) = unsafe {
+            (Self::_prepare_array(size, values1), 
+             Self::_prepare_array(size, values2))
+        };
+        let edges: Vec<(usize, usize)> = values1_buff.into_iter()
+            .zip(values2_buff)
+            .collect();
+        self.wbp.set_connectivity(&edges);
+    }
+
+    #[export_name="wbp_set_must_link"]
+    pub extern "C" fn set_must_link(&mut self, size: usize, 
+                                    values: *const usize, 
+                                    groups: *const usize) -> bool {
+        // Values with group indices 0, 1, ..., false on a conflict 
+        // with the cannot-link pairs
+        let (values_buff, groups_buff) = unsafe {
+            (Self::_prepare_array(size, values), 
+             Self::_prepare_array(size, groups))
+        };
+        let groups_num = groups_buff.iter().max().map_or(0, |g| g + 1);
+        let mut must_link: Vec<Vec<usize>> = vec![Vec::new(); groups_num];
+        for (value, group) in values_buff.into_iter().zip(groups_buff) {
+            must_link[group].push(value);
+        }
+        self.wbp.set_must_link(&must_link)
+    }
+
+    #[export_name="wbp_set_cannot_link"]
+    pub extern "C" fn set_cannot_link(&mut self, size: usize, 
+                                      values1: *const usize, 
+                                      values2: *const usize) -> bool {
+        let (values1_buff, values2_buff) = unsafe {
+            (Self::_prepare_array(size, values1), 
+             Self::_prepare_array(size, values2))
+        };
+        let pairs: Vec<(usize, usize)> = values1_buff.into_iter()
+            .zip(values2_buff)
+            .collect();
+        self.wbp.set_cannot_link(&pairs)
+    }
+
+    #[export_name="wbp_set_rare_level"]
+    pub extern "C" fn set_rare_level(&mut self, min_count: usize, 
+                                     min_share: f64) {
+        self.wbp.set_rare_level(min_count, min_share);
+    }
+
+    #[export_name="wbp_set_cross_fitting"]
+    pub extern "C" fn set_cross_fitting(&mut self, folds: usize, seed: u64) {
+        self.wbp.set_cross_fitting(folds, seed);
+    }
+
+    #[export_name="wbp_set_observer"]
+    pub extern "C" fn set_observer(&mut self, 
+                                   callback: Option<ObserverCallback>, 
+                                   user_data: *mut c_void) {
+        // See UserData for the thread safety
+        let user_data = UserData(user_data);
+        self.wbp.set_observer(callback.map(|callback| {
+            Box::new(move |info: &StepInfo| callback(
+                user_data.get(), info.cluster_ids.0, info.cluster_ids.1, 
+                info.distance, info.cluster_new_id, info.cluster_count
+            )) as _
+        }));
+    }
+
+    #[export_name="wbp_set_min_bin_size"]
+    pub extern "C" fn set_min_bin_size(&mut self, min_bin_size: usize) {
+        self.wbp.set_min_bin_size(min_bin_size);
+    }
+
+    #[export_name="wbp_set_trend"]
+    pub extern "C" fn set_trend(&mut self, trend: usize) -> bool {
+        // False for an unknown code, the trend is kept
+        match Self::_trend_from_code(trend) {
+            Some(trend) => {
+                self.wbp.set_trend(trend);
+                true
+            },
+            None => false,
+        }
+    }
+
+    #[export_name="wbp_set_auto_trend"]
+    pub extern "C" fn set_auto_trend(&mut self, auto_trend: bool) {
+        self.wbp.set_auto_trend(auto_trend);
+    }
+
+    #[export_name="wbp_set_max_prebins"]
+    pub extern "C" fn set_max_prebins(&mut self, max_prebins: usize) {
+        self.wbp.set_max_prebins(max_prebins);
+    }
+
     #[export_name="wbp_process_categorial"]
-    pub extern fn process_categorial(&mut self, size: usize, series: *const usize, 
+    pub extern "C" fn process_categorial(&mut self, size: usize, series: *const usize, 
                                      target: *const bool) {
         let (series_buff, target_buff) = unsafe {
             Self::_prepare_dataset(size, series, target)
@@ -33,9 +199,8 @@ impl WoeBinningProcWrapper {
         self.wbp.process_categorial(&series_buff, &target_buff);
     }
 
-    #[no_mangle]
     #[export_name="wbp_process_numeric"]
-    pub extern fn process_numeric(&mut self, size: usize, series: *const usize, 
+    pub extern "C" fn process_numeric(&mut self, size: usize, series: *const usize, 
                                   target: *const bool) {
         let (series_buff, target_buff) = unsafe {
             Self::_prepare_dataset(size, series, target)
@@ -43,21 +208,121 @@ impl WoeBinningProcWrapper {
         self.wbp.process_numeric(&series_buff, &target_buff);
     }
 
-    #[no_mangle]
+    #[export_name="wbp_process_numeric_exact"]
+    pub extern "C" fn process_numeric_exact(&mut self, size: usize, 
+                                            series: *const usize, 
+                                            target: *const bool) {
+        let (series_buff, target_buff) = unsafe {
+            Self::_prepare_dataset(size, series, target)
+        };
+        self.wbp.process_numeric_exact(&series_buff, &target_buff);
+    }
+
+    #[export_name="wbp_process_categorial_ordered"]
+    pub extern "C" fn process_categorial_ordered(&mut self, size: usize, 
+                                                 series: *const usize, 
+                                                 target: *const bool) {
+        let (series_buff, target_buff) = unsafe {
+            Self::_prepare_dataset(size, series, target)
+        };
+        self.wbp.process_categorial_ordered(&series_buff, &target_buff);
+    }
+
+    #[export_name="wbp_process_categorial_ordered_exact"]
+    pub extern "C" fn process_categorial_ordered_exact(&mut self, size: usize, 
+                                                       series: *const usize, 
+                                                       target: *const bool) {
+        let (series_buff, target_buff) = unsafe {
+            Self::_prepare_dataset(size, series, target)
+        };
+        self.wbp.process_categorial_ordered_exact(&series_buff, &target_buff);
+    }
+
+    #[export_name="wbp_get_trend"]
+    pub extern "C" fn get_trend(&self) -> usize {
+        match self.wbp.get_trend() {
+            None => 0,
+            Some(Trend::Ascending) => 1,
+            Some(Trend::Descending) => 2,
+            Some(Trend::Peak) => 3,
+            Some(Trend::Valley) => 4,
+        }
+    }
+
+    #[export_name="wbp_get_trend_iv"]
+    pub extern "C" fn get_trend_iv(&self, trend: usize) -> f64 {
+        // NaN for a trend without a feasible binning, without 
+        // detection of the trend or for an unknown code
+        let trend = match Self::_trend_from_code(trend) {
+            Some(trend) => trend,
+            None => return f64::NAN,
+        };
+        self.wbp.get_trend_ivs()
+            .and_then(|trend_ivs| trend_ivs.into_iter()
+                .find(|(t, _)| Some(*t) == trend)
+                .and_then(|(_, iv)| iv)
+            )
+            .unwrap_or(f64::NAN)
+    }
+
+    #[export_name="wbp_is_feasible"]
+    pub extern "C" fn is_feasible(&self) -> bool {
+        self.wbp.is_feasible()
+    }
+
+    #[export_name="wbp_get_iv_gap"]
+    pub extern "C" fn get_iv_gap(&self) -> f64 {
+        self.wbp.get_iv_gap().unwrap_or(f64::NAN)
+    }
+
+    #[export_name="wbp_set_bins_num"]
+    pub extern "C" fn set_bins_num(&mut self, bins_num: usize) {
+        self.wbp.set_bins_num(bins_num);
+    }
+
+    #[export_name="wbp_merge_bins"]
+    pub extern "C" fn merge_bins(&mut self, idx1: usize, idx2: usize) -> bool {
+        self.wbp.merge_bins(idx1, idx2)
+    }
+
+    #[export_name="wbp_split_bin"]
+    pub extern "C" fn split_bin(&mut self, idx: usize, cut: usize) -> bool {
+        self.wbp.split_bin(idx, cut)
+    }
+
+    #[export_name="wbp_move_value"]
+    pub extern "C" fn move_value(&mut self, value: usize, idx: usize) -> bool {
+        self.wbp.move_value(value, idx)
+    }
+
+    #[export_name="wbp_set_bin_locked"]
+    pub extern "C" fn set_bin_locked(&mut self, idx: usize, 
+                                     locked: bool) -> bool {
+        self.wbp.set_bin_locked(idx, locked)
+    }
+
+    #[export_name="wbp_is_bin_locked"]
+    pub extern "C" fn is_bin_locked(&self, idx: usize) -> bool {
+        self.wbp.get_locked_array().unwrap()[idx]
+    }
+
+    #[export_name="wbp_is_other_bin"]
+    pub extern "C" fn is_other_bin(&self, idx: usize) -> bool {
+        self.wbp.get_other_bin() == Some(idx)
+    }
+
     #[export_name="wbp_is_done"]
-    pub extern fn is_done(&self) -> bool {
+    pub extern "C" fn is_done(&self) -> bool {
         self.wbp.is_done()
     }
 
-    #[no_mangle]
     #[export_name="wbp_get_bins_num"]
-    pub extern fn get_bins_num(&self) -> usize {
+    pub extern "C" fn get_bins_num(&self) -> usize {
         self.wbp.get_bins_num().unwrap()
     }
 
-    #[no_mangle]
     #[export_name="wbp_get_bins_info"]
-    pub extern fn get_bins_info(&self, bins_num: usize, 
+    pub extern "C" fn get_bins_info(&self, bins_num: usize, 
                                 bin_info_array: *mut BinInfo) {
         let woe_vec = self.wbp.get_woe_array().unwrap();
         let iv_vec = self.wbp.get_iv_array().unwrap();
@@ -72,9 +337,81 @@ impl WoeBinningProcWrapper {
         }
     }
 
-    #[no_mangle]
+    #[export_name="wbp_get_blocking_constraints_num"]
+    pub extern "C" fn get_blocking_constraints_num(&self) -> usize {
+        self.wbp.get_blocking_constraints().unwrap().len()
+    }
+
+    #[export_name="wbp_get_blocking_constraints"]
+    pub extern "C" fn get_blocking_constraints(&self, size: usize, 
+                                               kinds_array: *mut usize, 
+                                               indices_array: *mut usize) {
+        // Kinds are 0 for must-link groups and 1 for cannot-link pairs
+        let (kinds, indices): (Vec<usize>, Vec<usize>) = 
+            self.wbp.get_blocking_constraints().unwrap().into_iter()
+                .map(|constraint| match constraint {
+                    Constraint::MustLink(idx) => (0, idx),
+                    Constraint::CannotLink(idx) => (1, idx),
+                })
+                .unzip();
+        unsafe {
+            kinds.as_ptr().copy_to(kinds_array, size);
+            indices.as_ptr().copy_to(indices_array, size);
+        }
+    }
+
+    #[export_name="wbp_get_metrics"]
+    pub extern "C" fn get_metrics(&self, metrics_info: *mut MetricsInfo) {
+        let metrics: MetricsInfo = self.wbp.get_metrics().unwrap().into();
+        unsafe {
+            metrics_info.write(metrics);
+        }
+    }
+
+    #[export_name="wbp_get_metrics_on"]
+    pub extern "C" fn get_metrics_on(&self, size: usize, series: *const usize, 
+                                     target: *const bool, 
+                                     metrics_info: *mut MetricsInfo) {
+        let (series_buff, target_buff) = unsafe {
+            Self::_prepare_dataset(size, series, target)
+        };
+        let metrics: MetricsInfo = 
+            self.wbp.get_metrics_on(&series_buff, &target_buff).into();
+        unsafe {
+            metrics_info.write(metrics);
+        }
+    }
+
+    #[export_name="wbp_transform"]
+    pub extern "C" fn transform(&self, size: usize, series: *const usize, 
+                                woe_array: *mut f64) {
+        let series_buff = unsafe {
+            Self::_prepare_array(size, series)
+        };
+        let woe = self.wbp.transform(&series_buff);
+        unsafe {
+            woe.as_ptr().copy_to(woe_array, size);
+        }
+    }
+
+    #[export_name="wbp_get_oof_woe"]
+    pub extern "C" fn get_oof_woe(&self, size: usize, woe_array: *mut f64) {
+        let woe = self.wbp.get_oof_woe_array().unwrap();
+        unsafe {
+            woe.as_ptr().copy_to(woe_array, size);
+        }
+    }
+
+    #[export_name="wbp_get_fit_woe"]
+    pub extern "C" fn get_fit_woe(&self, size: usize, woe_array: *mut f64) {
+        let woe = self.wbp.get_fit_woe_array().unwrap();
+        unsafe {
+            woe.as_ptr().copy_to(woe_array, size);
+        }
+    }
+
     #[export_name="wbp_get_bin_values"]
-    pub extern fn get_bin_values(&self, bin_idx: usize, size: usize, 
+    pub extern "C" fn get_bin_values(&self, bin_idx: usize, size: usize, 
                                  values_array: *mut usize) {
         let values = self.wbp.get_bin_values(bin_idx).unwrap();
         unsafe {
@@ -82,6 +419,29 @@ impl WoeBinningProcWrapper {
         }
     }
 
+    fn _trend_from_code(trend: usize) -> Option<Option<Trend>> {
+        // None for an unknown code
+        match trend {
+            0 => Some(None),
+            1 => Some(Some(Trend::Ascending)),
+            2 => Some(Some(Trend::Descending)),
+            3 => Some(Some(Trend::Peak)),
+            4 => Some(Some(Trend::Valley)),
+            _ => None,
+        }
+    }
+
+    unsafe fn _prepare_array(size: usize, values: *const usize) -> 
+                Vec<usize> {
+        let mut values_buff: Vec<usize> = vec![0; size];
+
+        unsafe {
+            values.copy_to(values_buff.as_mut_ptr(), size);
+        }
+
+        values_buff
+    }
+
     unsafe fn _prepare_dataset(
                 size: usize, series: *const usize, target: *const bool) -> 
                 (Vec<usize>, Vec<bool>) {
@@ -0,0 +1,241 @@
+# Tests of the native module for NumPy and pandas, run from the repository
+# root after
+#   cargo +nightly build --release --features python
+#   cp target/release/libwoebin.so woebin/_woebin.so
+#   python3 -m unittest discover tests   (or python3 -m pytest tests)
+# They are skipped without NumPy, pandas or the native module.
+
+import math
+import threading
+import time
+import unittest
+
+try:
+    import numpy as np
+    import pandas as pd
+except ImportError:
+    np = pd = None
+
+from woebin import NumpyWoeBinningProc
+
+
+def create_dataset(size, cat_num, seed=0):
+    rng = np.random.default_rng(seed)
+    probs = rng.random(cat_num)
+    series = rng.integers(0, cat_num, size)
+    target = rng.random(size) < probs[series]
+    return series, target
+
+
+def find_woe(woe_map, value):
+    # NaN keys are not found by dict lookup
+    for key, woe in woe_map.items():
+        if key is value or key == value or \
+                (isinstance(key, float) and isinstance(value, float)
+                 and math.isnan(key) and math.isnan(value)):
+            return woe
+    raise KeyError(value)
+
+
+@unittest.skipIf(np is None or NumpyWoeBinningProc is None,
+                 "NumPy, pandas or the native module is not available")
+class TestNumpyWoeBinningProc(unittest.TestCase):
+    def assert_round_trip(self, wbp, series):
+        # WOE of transform is the one of the bin of the value
+        woe_map = wbp.get_woe_map()
+        woe = wbp.transform(series)
+        self.assertIsInstance(woe, np.ndarray)
+        self.assertEqual(woe.dtype, np.float64)
+        self.assertEqual(len(woe), len(series))
+        for value, w in zip(series.tolist(), woe):
+            self.assertEqual(w, find_woe(woe_map, value))
+
+    def test_uint64(self):
+        series, target = create_dataset(2000, 12)
+        series = series.astype(np.uint64)
+
+        wbp = NumpyWoeBinningProc()
+        wbp.process(series, target, bins=4)
+
+        bins_info = wbp.get_bins_info()
+        self.assertEqual(len(bins_info), 4)
+        self.assertEqual(
+            sorted(v for info in bins_info for v in info['values']),
+            list(range(12))
+        )
+        self.assert_round_trip(wbp, series)
+
+        # The array is not kept borrowed after the call
+        series[0] = 1
+        self.assertTrue(series.flags.writeable)
+
+    def test_zero_copy(self):
+        # An uint64 array is read in place, so a read-only one works and is
+        # left as it is
+        series, target = create_dataset(1000, 8)
+        series = series.astype(np.uint64)
+        series.flags.writeable = False
+
+        wbp = NumpyWoeBinningProc()
+        wbp.process(series, target, bins=3)
+        self.assertFalse(series.flags.writeable)
+        self.assert_round_trip(wbp, series)
+
+    def test_zero_copy_int64_float64(self):
+        # Non-negative int64 and float64 arrays are read in place as well
+        series, target = create_dataset(1000, 8)
+        for dtype in [np.int64, np.float64]:
+            values = series.astype(dtype)
+            values.flags.writeable = False
+
+            wbp = NumpyWoeBinningProc()
+            wbp.process(values, target, bins=3)
+            self.assertFalse(values.flags.writeable)
+            self.assert_round_trip(wbp, values)
+
+    def test_int64_negative(self):
+        series, target = create_dataset(2000, 10)
+        series = series.astype(np.int64) - 5
+
+        wbp = NumpyWoeBinningProc()
+        wbp.process(series, target, bins=4)
+
+        values = sorted(v for info in wbp.get_bins_info()
+                        for v in info['values'])
+        self.assertEqual(values, list(range(-5, 5)))
+        self.assert_round_trip(wbp, series)
+
+        # Unseen categorial values get zero WOE
+        woe = wbp.transform(np.array([-100, 100], dtype=np.int64))
+        self.assertEqual(woe.tolist(), [0.0, 0.0])
+
+    def test_int64_numeric_unseen(self):
+        series, target = create_dataset(2000, 5)
+        series = series.astype(np.int64) * 10 - 20
+
+        wbp = NumpyWoeBinningProc()
+        wbp.process(series, target, bins=3, is_numeric=True)
+        woe_map = wbp.get_woe_map()
+        self.assertEqual(sorted(woe_map), [-20, -10, 0, 10, 20])
+
+        # Values between the known ones take the smaller one, less than all
+        # take the first, greater than all take the last
+        woe = wbp.transform(np.array([-15, 5, -100, 100], dtype=np.int64))
+        self.assertEqual(woe.tolist(), [
+            woe_map[-20], woe_map[0], woe_map[-20], woe_map[20]
+        ])
+
+        # Non-negative uint64 values are ranked by the same distinct values
+        woe = wbp.transform(np.array([0, 15], dtype=np.uint64))
+        self.assertEqual(woe.tolist(), [woe_map[0], woe_map[10]])
+
+    def test_float_nan(self):
+        series, target = create_dataset(2000, 6)
+        series = series.astype(np.float64) / 2
+        series[series == 1.0] = np.nan
+
+        wbp = NumpyWoeBinningProc()
+        wbp.process(series, target, bins=3)
+
+        values = [v for info in wbp.get_bins_info() for v in info['values']]
+        self.assertEqual(len(values), 6)
+        self.assertEqual(sum(math.isnan(v) for v in values), 1)
+        self.assert_round_trip(wbp, series)
+
+        woe = wbp.transform(np.array([np.nan, 0.25]))
+        self.assertEqual(woe[0], find_woe(wbp.get_woe_map(), math.nan))
+        self.assertEqual(woe[1], 0.0)
+
+        # NaN is not numeric
+        with self.assertRaises(TypeError):
+            NumpyWoeBinningProc().process(series, target, is_numeric=True)
+
+    def test_categorical(self):
+        series, target = create_dataset(2000, 4)
+        labels = np.array(['a', 'b', 'c', None], dtype=object)[series]
+        categorical = pd.Series(labels).astype('category')
+
+        wbp = NumpyWoeBinningProc()
+        wbp.process(categorical, target, bins=3)
+
+        woe_map = wbp.get_woe_map()
+        self.assertEqual(set(woe_map), {'a', 'b', 'c', None})
+
+        woe = wbp.transform(categorical)
+        self.assertEqual(woe.tolist(),
+                         [woe_map[label] for label in labels])
+
+    def test_categorical_codes(self):
+        # Codes of another categorical are mapped by categories, unseen
+        # categories get zero WOE
+        series, target = create_dataset(2000, 3)
+        labels = np.array(['a', 'b', 'c'], dtype=object)[series]
+
+        wbp = NumpyWoeBinningProc()
+        wbp.process(pd.Categorical(labels), target, bins=3)
+        woe_map = wbp.get_woe_map()
+
+        other = pd.Categorical(['c', 'z', 'a', None, 'b'],
+                               categories=['z', 'c', 'b', 'a'])
+        woe = wbp.transform(other)
+        self.assertEqual(woe.tolist(), [
+            woe_map['c'], 0.0, woe_map['a'], 0.0, woe_map['b']
+        ])
+
+        # A categorical is not an array of another type
+        with self.assertRaises(TypeError):
+            wbp.transform(np.array([0, 1], dtype=np.uint64))
+
+    def test_auto_trend_requires_exact(self):
+        series, target = create_dataset(1000, 10)
+
+        wbp = NumpyWoeBinningProc()
+        with self.assertRaises(ValueError):
+            wbp.process(series, target, bins=4, is_numeric=True,
+                        trend='auto')
+
+        wbp.process(series, target, bins=4, is_numeric=True, exact=True,
+                    trend='auto')
+        self.assertIsNotNone(wbp.get_trend())
+        self.assertTrue(wbp.is_feasible())
+
+        # No bin can have more rows than the series
+        wbp.process(series, target, bins=4, is_numeric=True, exact=True,
+                    min_bin_size=2000, trend='auto')
+        self.assertFalse(wbp.is_feasible())
+        self.assertEqual(len(wbp.get_bins_info()), 1)
+
+    def test_gil_released(self):
+        # The main thread keeps running Python code while the other one
+        # is binning
+        series, target = create_dataset(3_000_000, 2000)
+        series = series.astype(np.uint64)
+        wbp = NumpyWoeBinningProc()
+
+        started = threading.Event()
+        duration = [0.0]
+
+        def run():
+            started.set()
+            start = time.perf_counter()
+            wbp.process(series, target, bins=5)
+            duration[0] = time.perf_counter() - start
+
+        thread = threading.Thread(target=run)
+        thread.start()
+        started.wait()
+
+        max_gap = 0.0
+        last = time.perf_counter()
+        while thread.is_alive():
+            now = time.perf_counter()
+            max_gap = max(max_gap, now - last)
+            last = now
+        thread.join()
+
+        self.assertEqual(len(wbp.get_bins_info()), 5)
+        self.assertLess(max_gap, duration[0] / 2)
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,198 @@
+# Tests of the Python bindings, run from the repository root after
+# cargo +nightly build --release:
+#   python3 -m unittest discover tests   (or python3 -m pytest tests)
+
+import csv
+import gc
+import io
+import math
+import random
+import unittest
+
+from woebin import Scorecard, WoeBinningProc, linkage
+
+
+def create_dataset(size, cat_num, seed=0):
+    rng = random.Random(seed)
+    probs = [rng.random() for _ in range(cat_num)]
+    series = [rng.randrange(cat_num) for _ in range(size)]
+    target = [int(rng.random() < probs[value]) for value in series]
+    return series, target
+
+
+class TestObserver(unittest.TestCase):
+    def test_observer_after_process(self):
+        series, target = create_dataset(1000, 10)
+        infos = []
+
+        wbp = WoeBinningProc()
+        wbp.process(series, target, bins=6,
+                    observer=lambda info: infos.append(info))
+        self.assertEqual(len(infos), 4)
+
+        # The callback is called by later merges, after process returned
+        gc.collect()
+        wbp.set_bins_num(3)
+        self.assertEqual(len(infos), 7)
+        self.assertEqual(infos[-1]['count'], 3)
+        self.assertTrue(wbp.merge_bins(0, 1))
+
+        # A new processing drops the old callback
+        count = len(infos)
+        wbp.process(series, target, bins=3)
+        gc.collect()
+        wbp.set_bins_num(2)
+        self.assertEqual(len(infos), count)
+
+
+class TestConstraints(unittest.TestCase):
+    def test_conflict(self):
+        series, target = create_dataset(1000, 10)
+
+        wbp = WoeBinningProc()
+        with self.assertRaises(AssertionError):
+            wbp.process(series, target, bins=3, must_link=[[0, 1], [1, 2]],
+                        cannot_link=[(2, 0)])
+        with self.assertRaises(AssertionError):
+            wbp.process(series, target, bins=3, cannot_link=[(3, 3)])
+
+    def test_unseen_values(self):
+        # Values missing in series are ignored, distinct ones do not
+        # conflict with each other
+        series, target = create_dataset(1000, 10)
+
+        wbp = WoeBinningProc()
+        wbp.process(series, target, bins=3, must_link=[[0, 100], [5, 9]],
+                    cannot_link=[(100, 200), (0, 5)])
+        bin_of = {v: idx for idx, info in enumerate(wbp.get_bins_info())
+                  for v in info['values']}
+        self.assertEqual(bin_of[5], bin_of[9])
+        self.assertNotEqual(bin_of[0], bin_of[5])
+
+
+class TestOtherBin(unittest.TestCase):
+    def test_exports(self):
+        # Unseen categories go to the bin of the rare ones in transform,
+        # SQL and PMML instead of the default
+        series, target = create_dataset(1000, 10)
+        series += ['rare_a', 'rare_b']
+        target += [1, 0]
+
+        wbp = WoeBinningProc()
+        wbp.process(series, target, bins=4, rare_min_count=2)
+        other = [info for info in wbp.get_bins_info() if info['other']]
+        self.assertEqual(len(other), 1)
+        woe = other[0]['woe']
+
+        self.assertEqual(wbp.transform(['unseen']), [woe])
+
+        sql = wbp.to_sql('c', default=0.5)
+        else_line = sql.splitlines()[-2]
+        self.assertEqual(float(else_line.split('ELSE ')[1]), woe)
+
+        pmml = wbp.to_pmml('c', default=0.5)
+        default = pmml.split('defaultValue="')[1].split('"')[0]
+        self.assertEqual(float(default), woe)
+
+        sc = Scorecard({'c': wbp}, {'c': -1.0}, intercept=-1.0)
+        self.assertIsNotNone(sc.score({'c': 'unseen'}))
+        self.assertEqual(sc.score({'c': 'unseen'}), sc.score({'c': 'rare_a'}))
+
+
+class TestNumericFloat(unittest.TestCase):
+    def test_unseen_values(self):
+        # Values between the known ones take the nearest smaller one, less
+        # than all take the first
+        series, target = create_dataset(1000, 20)
+        series = [value / 4 for value in series]
+
+        wbp = WoeBinningProc()
+        wbp.process(series, target, bins=4, is_numeric=True)
+        woe_map = wbp.get_woe_map()
+        known = sorted(woe_map)
+
+        values = [-1.0, 0.0, 0.1, 2.3, 4.75, 100.0]
+        expected = [
+            woe_map[max([k for k in known if k <= v] or [known[0]])]
+            for v in values
+        ]
+        self.assertEqual(wbp.transform(values), expected)
+
+    def test_split_bin(self):
+        series, target = create_dataset(1000, 20)
+        series = [value / 4 for value in series]
+
+        wbp = WoeBinningProc()
+        wbp.process(series, target, bins=2, is_numeric=True)
+        values = sorted(wbp.get_bins_info()[0]['values'])
+        cut = (values[1] + values[2]) / 2
+
+        self.assertTrue(wbp.split_bin(0, cut))
+        bins_info = wbp.get_bins_info()
+        self.assertEqual(len(bins_info), 3)
+        self.assertEqual(sorted(bins_info[0]['values']), values[:2])
+
+
+class TestLinkage(unittest.TestCase):
+    def test_nan_distances(self):
+        # Point 2 cannot be merged, so there are fewer rows than points
+        # minus one
+        matrix = linkage([1.0, math.nan, math.nan], method='single')
+        self.assertEqual(matrix, [[0.0, 1.0, 1.0, 2.0]])
+
+        matrix = linkage([1.0, 3.0, 2.0], method='complete')
+        self.assertEqual(len(matrix), 2)
+
+
+class TestScorecardCsv(unittest.TestCase):
+    def test_quoting(self):
+        # Names and values with commas, quotes and line breaks are read
+        # back by a CSV reader
+        series, target = create_dataset(1000, 4)
+        labels = ['a,b', 'say "hi"', 'line\nbreak', 'plain']
+        series = [labels[value] for value in series]
+
+        wbp = WoeBinningProc()
+        wbp.process(series, target, bins=2)
+        sc = Scorecard({'x, "y"': wbp}, {'x, "y"': -1.0}, intercept=-1.0)
+
+        rows = list(csv.reader(io.StringIO(sc.to_csv())))
+        self.assertEqual(rows[0], ['feature', 'bin', 'values', 'woe',
+                                   'points'])
+        self.assertEqual(len(rows), 4)
+        self.assertTrue(all(len(row) == 5 for row in rows))
+        self.assertTrue(all(row[0] == 'x, "y"' for row in rows[2:]))
+        values = ' '.join(row[2] for row in rows[2:])
+        for label in labels:
+            self.assertIn(label, values)
+
+
+class TestTrend(unittest.TestCase):
+    def test_auto_requires_exact(self):
+        series, target = create_dataset(1000, 10)
+
+        wbp = WoeBinningProc()
+        with self.assertRaises(AssertionError):
+            wbp.process(series, target, bins=4, is_numeric=True,
+                        trend='auto')
+
+        wbp.process(series, target, bins=4, is_numeric=True, exact=True,
+                    trend='auto')
+        self.assertIn(wbp.get_trend(), ['ascending', 'descending', 'peak',
+                                        'valley'])
+        self.assertTrue(wbp.is_feasible())
+
+    def test_infeasible(self):
+        # No bin can have more rows than the series
+        series, target = create_dataset(1000, 10)
+
+        wbp = WoeBinningProc()
+        wbp.process(series, target, bins=4, is_numeric=True, exact=True,
+                    min_bin_size=2000, trend='auto')
+        self.assertFalse(wbp.is_feasible())
+        self.assertEqual(len(wbp.get_bins_info()), 1)
+        self.assertIsNone(wbp.get_trend())
+
+
+if __name__ == '__main__':
+    unittest.main()
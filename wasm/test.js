@@ -0,0 +1,29 @@
+// Smoke test of the WebAssembly build under Node, see wasm/test.sh:
+//   wasm-pack build --target nodejs -- --features wasm
+//   node wasm/test.js
+const assert = require('node:assert');
+const { WoeBinningProc } = require('../pkg/woebin.js');
+
+const series = Float64Array.from({ length: 400 }, (_, i) => (i % 20) / 2);
+const target = Uint8Array.from(series, (x) => (x >= 5 ? 1 : 0));
+
+const wbp = new WoeBinningProc(2, 1.0);
+wbp.fit(series, target, 'numeric', false);
+
+assert.strictEqual(wbp.binsNum(), 2);
+assert.deepStrictEqual(Array.from(wbp.counts()), [200, 200]);
+assert.deepStrictEqual(Array.from(wbp.events()), [0, 200]);
+assert.strictEqual(wbp.binValues(1)[0], 5);
+
+const woe = wbp.woe();
+assert.deepStrictEqual(Array.from(wbp.transform(Float64Array.of(-1, 5))),
+                       [woe[0], woe[1]]);
+
+const table = JSON.parse(wbp.table());
+assert.strictEqual(table.length, 2);
+assert.strictEqual(table[1].events, 200);
+
+assert.throws(() => wbp.fit(series, target, 'other', false));
+
+wbp.free();
+console.log('ok');
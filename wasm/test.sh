@@ -0,0 +1,14 @@
+#!/bin/sh
+# Builds the WebAssembly package and runs the smoke test under Node, from the
+# repository root: sh wasm/test.sh
+# Needs Node and wasm-bindgen-cli of the version of the wasm-bindgen crate in
+# Cargo.lock (cargo install wasm-bindgen-cli --version <version>), the same
+# as wasm-pack build --target nodejs -- --features wasm.
+set -e
+
+rustup +nightly target add wasm32-unknown-unknown
+cargo +nightly build --lib --release --target wasm32-unknown-unknown \
+    --features wasm
+wasm-bindgen --target nodejs --out-dir pkg \
+    target/wasm32-unknown-unknown/release/woebin.wasm
+node wasm/test.js
@@ -1,7 +1,10 @@
 import sys
 import os
+import csv
+import io
 import ctypes
 import math
+import bisect
 
 import sys
 import os
@@ -18,6 +21,44 @@ ALLOWED_SERIES_TYPES = [str, float, int, bool, NoneType]
 
 DEV_DLL_DIR = "./target/release"
 
+# Codes of bin orders in the library
+BIN_ORDERS = {
+    'value': 0,
+    'woe': 1,
+    'count': 2,
+}
+
+# Observer callback: user data, merged cluster ids, distance, new cluster id
+# and remaining cluster count. Returns True to stop.
+ObserverCallback = ctypes.CFUNCTYPE(
+    ctypes.c_bool, ctypes.c_void_p, ctypes.c_uint64, ctypes.c_uint64, 
+    ctypes.c_double, ctypes.c_uint64, ctypes.c_uint64,
+)
+
+# Codes of WOE trends in the library
+TRENDS = {
+    None: 0,
+    'ascending': 1,
+    'descending': 2,
+    'peak': 3,
+    'valley': 4,
+}
+
+# Codes of SQL dialects in the library
+SQL_DIALECTS = {
+    'ansi': 0,
+    'mysql': 1,
+    'sqlserver': 2,
+}
+
+# Codes of linkage methods in the library
+LINKAGES = {
+    'single': 0,
+    'complete': 1,
+    'average': 2,
+    'ward': 3,
+}
+
 
 def get_dll_path():
     dll_name = None
@@ -47,6 +88,70 @@ def load_dll(dll_path):
     dll.wbp_is_done.argtypes = [ctypes.c_void_p]
     dll.wbp_is_done.restype = ctypes.c_bool
     dll.wbp_get_bins_num.argtypes = [ctypes.c_void_p]
+    dll.wbp_get_blocking_constraints_num.argtypes = [ctypes.c_void_p]
+    dll.wbp_set_bin_order.restype = ctypes.c_bool
+    dll.wbp_set_trend.restype = ctypes.c_bool
+    dll.wbp_set_must_link.restype = ctypes.c_bool
+    dll.wbp_set_cannot_link.restype = ctypes.c_bool
+    dll.wbp_is_feasible.argtypes = [ctypes.c_void_p]
+    dll.wbp_is_feasible.restype = ctypes.c_bool
+    dll.wbp_get_iv_gap.argtypes = [ctypes.c_void_p]
+    dll.wbp_get_iv_gap.restype = ctypes.c_double
+    dll.wbp_get_trend.argtypes = [ctypes.c_void_p]
+    dll.wbp_get_trend_iv.argtypes = [ctypes.c_void_p, ctypes.c_uint64]
+    dll.wbp_get_trend_iv.restype = ctypes.c_double
+
+    for name in ['wbp_merge_bins', 'wbp_split_bin', 'wbp_move_value', 
+                 'wbp_set_bin_locked']:
+        getattr(dll, name).argtypes = [
+            ctypes.c_void_p, ctypes.c_uint64, ctypes.c_uint64,
+        ]
+        getattr(dll, name).restype = ctypes.c_bool
+    dll.wbp_set_bin_locked.argtypes = [
+        ctypes.c_void_p, ctypes.c_uint64, ctypes.c_bool,
+    ]
+    dll.wbp_is_bin_locked.argtypes = [ctypes.c_void_p, ctypes.c_uint64]
+    dll.wbp_is_bin_locked.restype = ctypes.c_bool
+    dll.wbp_is_other_bin.argtypes = [ctypes.c_void_p, ctypes.c_uint64]
+    dll.wbp_is_other_bin.restype = ctypes.c_bool
+    dll.wbp_set_rare_level.argtypes = [
+        ctypes.c_void_p, ctypes.c_uint64, ctypes.c_double,
+    ]
+    dll.wbp_set_cross_fitting.argtypes = [
+        ctypes.c_void_p, ctypes.c_uint64, ctypes.c_uint64,
+    ]
+    dll.sc_new.argtypes = [ctypes.c_double]
+    dll.sc_new.restype = ctypes.c_void_p
+    dll.sc_set_scaling.argtypes = [
+        ctypes.c_void_p, ctypes.c_double, ctypes.c_double, ctypes.c_double,
+    ]
+    dll.sc_add_feature.argtypes = [
+        ctypes.c_void_p, ctypes.c_char_p, ctypes.c_void_p, ctypes.c_double,
+    ]
+    dll.sc_get_base_points.argtypes = [ctypes.c_void_p]
+    dll.sc_get_base_points.restype = ctypes.c_double
+    dll.sc_score.restype = ctypes.c_double
+    dll.sql_new.restype = ctypes.c_void_p
+    dll.sql_set_default.argtypes = [ctypes.c_void_p, ctypes.c_double]
+    dll.sql_set_null.argtypes = [ctypes.c_void_p, ctypes.c_bool, ctypes.c_double]
+    dll.sql_render_woe.restype = ctypes.c_void_p
+    dll.sql_render_scorecard.restype = ctypes.c_void_p
+    dll.sql_free_string.argtypes = [ctypes.c_void_p]
+    dll.pmml_new.restype = ctypes.c_void_p
+    dll.pmml_set_default.argtypes = [ctypes.c_void_p, ctypes.c_double]
+    dll.pmml_set_null.argtypes = [ctypes.c_void_p, ctypes.c_bool, ctypes.c_double]
+    dll.pmml_render_binnings.restype = ctypes.c_void_p
+    dll.pmml_render_scorecard.restype = ctypes.c_void_p
+    dll.pmml_free_string.argtypes = [ctypes.c_void_p]
+    dll.lr_new.restype = ctypes.c_void_p
+    dll.ibp_new.argtypes = [ctypes.c_uint64, ctypes.c_double]
+    dll.ibp_new.restype = ctypes.c_void_p
+    dll.ibp_get_regions_num.argtypes = [ctypes.c_void_p]
+    dll.ibp_get_x_cuts_num.argtypes = [ctypes.c_void_p]
+    dll.ibp_get_y_cuts_num.argtypes = [ctypes.c_void_p]
+    dll.lr_set_l2.argtypes = [ctypes.c_void_p, ctypes.c_double]
+    dll.lr_fit_woe.restype = ctypes.c_bool
+    dll.hc_linkage_matrix.restype = ctypes.c_ssize_t
 
     return dll
 
@@ -55,18 +160,60 @@ dll_path = get_dll_path()
 dll = load_dll(dll_path)
 
 
+# Native module for NumPy arrays and pandas categoricals (the library built 
+# with --features python and placed as woebin/_woebin.so). Its 
+# WoeBinningProc has a subset of the methods: process (without 
+# constraints, rare pooling, cross-fitting and observer), set_bins_num, 
+# get_bins_info, get_woe_map, get_iv_total, get_trend, get_trend_ivs, 
+# is_feasible, get_iv_gap, transform (returns an array) and get_metrics. 
+# Edits of bins, OOF WOE and exports are in this module only.
+try:
+    from ._woebin import WoeBinningProc as NumpyWoeBinningProc
+except ImportError:
+    NumpyWoeBinningProc = None
+
+
 class WoeBinningProc:
     def __init__(self):
         self._wbp = None
         self._value_map = None
         self._value_map_back = None
+        self._value_map_hashed = None
+        self._values_hashed = None
+        self._values_sorted = None
+        self._observer_cb = None
 
     def reset(self):
+        # The library keeps a pointer to the observer callback, so it is 
+        # unset before the callback object is dropped
+        if self._wbp is not None and self._observer_cb is not None:
+            dll.wbp_set_observer(ctypes.c_void_p(self._wbp), None, None)
+        self._observer_cb = None
         self._wbp = None
         self._value_map = {}
         self._value_map_back = {}
+        self._value_map_hashed = set()
+        self._values_hashed = []
+        self._values_sorted = []
+        self._must_link = []
+        self._cannot_link = []
+        self._unknown_links = {}
+        self._size = None
+        self._is_numeric = False
+        self._series_type = None
+
+    def process(self, series, target, bins=10, is_numeric=False, smooth=1.0,
+                bin_order='woe', low_memory=False, connectivity=None,
+                observer=None, exact=False, min_bin_size=0, trend=None,
+                max_prebins=100, ordered=False, must_link=None, 
+                cannot_link=None, rare_min_count=0, rare_min_share=0.0, 
+                folds=0, seed=0):
+        assert bin_order in BIN_ORDERS, f"Unknown bin order: {bin_order}"
+        assert trend in TRENDS or trend == 'auto', f"Unknown trend: {trend}"
+        assert not exact or is_numeric or ordered, \
+            "Exact binning is for numeric or ordered categorial only"
+        assert exact or trend != 'auto', "trend='auto' requires exact=True"
 
-    def process(self, series, target, bins=10, is_numeric=False, smooth=1.0):
         assert len(series) == len(target)
 
         size = len(series)
@@ -75,13 +222,134 @@ class WoeBinningProc:
 
         self.reset()
 
+        self._is_numeric = is_numeric
         series_hashed = self._preproc_series(series, is_numeric)
+        self._value_map_hashed = set(series_hashed)
+        # Converted values in ascending order and the values themselves, 
+        # numeric values keep their order after conversion
+        self._values_hashed = sorted(self._value_map_hashed)
+        self._values_sorted = [
+            self._value_map.get(h, h) for h in self._values_hashed
+        ]
 
         size = len(series)
         self._wbp = dll.wbp_new(bins, smooth)
 
-        process_method = dll.wbp_process_numeric if is_numeric \
-            else dll.wbp_process_categorial
+        is_set = dll.wbp_set_bin_order(
+            ctypes.c_void_p(self._wbp),
+            ctypes.c_uint64(BIN_ORDERS[bin_order]),
+        )
+        assert is_set, f"Unknown bin order: {bin_order}"
+        dll.wbp_set_low_memory(
+            ctypes.c_void_p(self._wbp),
+            ctypes.c_bool(low_memory),
+        )
+
+        if exact:
+            dll.wbp_set_min_bin_size(
+                ctypes.c_void_p(self._wbp),
+                ctypes.c_uint64(min_bin_size),
+            )
+            # 'auto' takes the trend with maximal IV, see get_trend_ivs
+            is_set = dll.wbp_set_trend(
+                ctypes.c_void_p(self._wbp),
+                ctypes.c_uint64(TRENDS.get(trend, 0)),
+            )
+            assert is_set, f"Unknown trend: {trend}"
+            dll.wbp_set_auto_trend(
+                ctypes.c_void_p(self._wbp),
+                ctypes.c_bool(trend == 'auto'),
+            )
+            dll.wbp_set_max_prebins(
+                ctypes.c_void_p(self._wbp),
+                ctypes.c_uint64(max_prebins),
+            )
+
+        if connectivity is not None:
+            edges = self._convert_edges(connectivity)
+            dll.wbp_set_connectivity(
+                ctypes.c_void_p(self._wbp),
+                ctypes.c_uint64(len(edges)),
+                (ctypes.c_uint64 * len(edges))(*(e[0] for e in edges)),
+                (ctypes.c_uint64 * len(edges))(*(e[1] for e in edges)),
+            )
+
+        # Categorial only: must_link is a list of groups of values that 
+        # always share a bin, cannot_link is a list of pairs of values 
+        # that never do
+        self._must_link = list(map(list, must_link or []))
+        self._cannot_link = list(map(tuple, cannot_link or []))
+
+        if self._must_link:
+            values = [v for group in self._must_link for v in group]
+            groups = [
+                idx for idx, group in enumerate(self._must_link) 
+                for _ in group
+            ]
+            is_set = dll.wbp_set_must_link(
+                ctypes.c_void_p(self._wbp),
+                ctypes.c_uint64(len(values)),
+                (ctypes.c_uint64 * len(values))(
+                    *self._convert_link_values(values)
+                ),
+                (ctypes.c_uint64 * len(groups))(*groups),
+            )
+            assert is_set, "Must-link groups conflict with cannot-link pairs"
+
+        if self._cannot_link:
+            pairs_num = len(self._cannot_link)
+            is_set = dll.wbp_set_cannot_link(
+                ctypes.c_void_p(self._wbp),
+                ctypes.c_uint64(pairs_num),
+                (ctypes.c_uint64 * pairs_num)(*self._convert_link_values(
+                    [pair[0] for pair in self._cannot_link]
+                )),
+                (ctypes.c_uint64 * pairs_num)(*self._convert_link_values(
+                    [pair[1] for pair in self._cannot_link]
+                )),
+            )
+            assert is_set, \
+                "Cannot-link pairs must not be a value with itself or " \
+                "values of joined must-link groups"
+
+        # Categorial only: values with fewer rows than rare_min_count or 
+        # a smaller share than rare_min_share are pooled into the bin 
+        # marked 'other', it also takes unseen values in transform
+        dll.wbp_set_rare_level(self._wbp, rare_min_count, rare_min_share)
+
+        # Cross-fitting: with folds > 1 the rows also get out-of-fold WOE 
+        # (see get_oof_woe), folds are random and reproducible by seed
+        dll.wbp_set_cross_fitting(self._wbp, folds, seed)
+        self._size = size if folds > 1 else None
+
+        # observer(info) is called on every merge of bins, info is a dict
+        # with keys 'ids', 'distance', 'new_id' and 'count', a true
+        # result stops the binning
+        if observer is not None:
+            callback = ObserverCallback(
+                lambda _, id1, id2, distance, new_id, count: bool(observer({
+                    'ids': (id1, id2),
+                    'distance': distance,
+                    'new_id': new_id,
+                    'count': count,
+                }))
+            )
+            dll.wbp_set_observer(ctypes.c_void_p(self._wbp), callback, None)
+
+            # Merges after processing (set_bins_num, manual edits) call it 
+            # too, so it lives as long as the binning
+            self._observer_cb = callback
+
+        if is_numeric and exact:
+            process_method = dll.wbp_process_numeric_exact
+        elif is_numeric:
+            process_method = dll.wbp_process_numeric
+        elif ordered and exact:
+            process_method = dll.wbp_process_categorial_ordered_exact
+        elif ordered:
+            process_method = dll.wbp_process_categorial_ordered
+        else:
+            process_method = dll.wbp_process_categorial
 
         process_method(
             ctypes.c_void_p(self._wbp),
@@ -90,6 +358,36 @@ class WoeBinningProc:
             (ctypes.c_bool * size)(*target),
         )
 
+    def set_bins_num(self, bins):
+        # Change the number of bins without processing again, exact binning 
+        # is found again for the new number
+        dll.wbp_set_bins_num(
+            ctypes.c_void_p(self._wbp), 
+            ctypes.c_uint64(bins),
+        )
+
+    # Manual edits of bins, indices are the ones in get_bins_info and 
+    # they change after each edit. Return False if the edit is impossible.
+
+    def merge_bins(self, idx1, idx2):
+        # Numeric bins must be neighbours
+        return dll.wbp_merge_bins(self._wbp, idx1, idx2)
+
+    def split_bin(self, idx, cut):
+        # Numeric only: values less than cut go to the first bin
+        return dll.wbp_split_bin(self._wbp, idx, self._convert_cut(cut))
+
+    def move_value(self, value, idx):
+        # Categorial only
+        value_hashed = self._value_map_back.get(value, value)
+        if value_hashed not in self._value_map_hashed:
+            return False
+        return dll.wbp_move_value(self._wbp, value_hashed, idx)
+
+    def lock_bin(self, idx, locked=True):
+        # Locked bins are not changed by set_bins_num
+        return dll.wbp_set_bin_locked(self._wbp, idx, locked)
+
     def get_bins_info(self):
         # Get number of bins
         bins_num = dll.wbp_get_bins_num(self._wbp)
@@ -121,10 +419,49 @@ class WoeBinningProc:
                 'woe': bin_info.woe,
                 'iv': bin_info.iv,
                 'values': bin_values,
+                'locked': dll.wbp_is_bin_locked(self._wbp, idx),
+                'other': dll.wbp_is_other_bin(self._wbp, idx),
             })
 
         return bins_info_list
 
+    def get_blocking_constraints(self):
+        # Constraints that keep the binning from the desirable number of 
+        # bins, as pairs ('must_link', group) or ('cannot_link', pair): 
+        # groups without which there would be more bins, pairs between 
+        # remaining bins that could be merged otherwise. Empty if the 
+        # number is reached.
+        size = dll.wbp_get_blocking_constraints_num(self._wbp)
+        kinds = (ctypes.c_uint64 * size)()
+        indices = (ctypes.c_uint64 * size)()
+        dll.wbp_get_blocking_constraints(
+            ctypes.c_void_p(self._wbp), ctypes.c_uint64(size), kinds, indices,
+        )
+        return [
+            ('must_link', self._must_link[idx]) if kind == 0 
+            else ('cannot_link', self._cannot_link[idx])
+            for kind, idx in zip(kinds, indices)
+        ]
+
+    def get_oof_woe(self):
+        # WOE of the processed rows, each of them by the binning fitted 
+        # on the other folds, to train a model without target leakage
+        woe_array = (ctypes.c_double * self._size)()
+        dll.wbp_get_oof_woe(
+            ctypes.c_void_p(self._wbp), ctypes.c_uint64(self._size), woe_array,
+        )
+        return list(woe_array)
+
+    def get_fit_woe(self):
+        # WOE of the processed rows by the binning fitted on all of them, 
+        # with the bins at processing as get_oof_woe (transform gives WOE 
+        # of the current bins after set_bins_num or merges)
+        woe_array = (ctypes.c_double * self._size)()
+        dll.wbp_get_fit_woe(
+            ctypes.c_void_p(self._wbp), ctypes.c_uint64(self._size), woe_array,
+        )
+        return list(woe_array)
+
     def get_woe_map(self):
         woe_map = {}
 
@@ -134,14 +471,196 @@ class WoeBinningProc:
 
         return woe_map
 
+    def get_trend(self):
+        # Trend of exact binning, the detected one for trend='auto'
+        code = dll.wbp_get_trend(ctypes.c_void_p(self._wbp))
+        return {code: name for name, code in TRENDS.items()}[code]
+
+    def get_trend_ivs(self):
+        # IV of exact binning for each trend with trend='auto', None for 
+        # a trend without a feasible binning
+        ivs = {}
+        for name, code in TRENDS.items():
+            if name is not None:
+                iv = dll.wbp_get_trend_iv(
+                    ctypes.c_void_p(self._wbp), ctypes.c_uint64(code),
+                )
+                ivs[name] = None if math.isnan(iv) else iv
+        return ivs
+
+    def is_feasible(self):
+        # False if no exact binning satisfies min_bin_size and the trend, 
+        # then all values are in one bin
+        return dll.wbp_is_feasible(ctypes.c_void_p(self._wbp))
+
+    def get_iv_gap(self):
+        # IV of exact binning minus IV of greedy one, NaN for greedy binning
+        return dll.wbp_get_iv_gap(ctypes.c_void_p(self._wbp))
+
+    def transform(self, series):
+        # WOE of values, numeric values between bins go to the left one, 
+        # unknown categorial values go to the 'other' bin or get zero
+        size = len(series)
+        woe_array = (ctypes.c_double * size)()
+        dll.wbp_transform(
+            ctypes.c_void_p(self._wbp), 
+            ctypes.c_uint64(size), 
+            (ctypes.c_uint64 * size)(*self._convert_values(series)), 
+            woe_array,
+        )
+        return list(woe_array)
+
+    def get_metrics(self, series=None, target=None):
+        # AUC, Gini, KS and divergence of WOE on the processed data or 
+        # on the given sample
+        metrics = MetricsInfo()
+        if series is None:
+            dll.wbp_get_metrics(ctypes.c_void_p(self._wbp), ctypes.byref(metrics))
+        else:
+            assert len(series) == len(target)
+            size = len(series)
+            dll.wbp_get_metrics_on(
+                ctypes.c_void_p(self._wbp), 
+                ctypes.c_uint64(size), 
+                (ctypes.c_uint64 * size)(*self._convert_values(series)), 
+                (ctypes.c_bool * size)(*map(bool, target)), 
+                ctypes.byref(metrics),
+            )
+        return {
+            'auc': metrics.auc,
+            'gini': metrics.gini,
+            'ks': metrics.ks,
+            'divergence': metrics.divergence,
+        }
+
     def get_iv_total(self):
         iv_total = 0.0
         for info in self.get_bins_info():
             iv_total += info['iv']
         return iv_total
 
+    def _convert_edges(self, edges):
+        # Values missing in series are dropped along with their edges
+        edges_hashed = []
+        for value1, value2 in edges:
+            value1_hashed = self._value_map_back.get(value1, value1)
+            value2_hashed = self._value_map_back.get(value2, value2)
+            if value1_hashed in self._value_map_hashed and \
+                    value2_hashed in self._value_map_hashed:
+                edges_hashed.append((value1_hashed, value2_hashed))
+        return edges_hashed
+
+    def _convert_link_values(self, values):
+        # Values missing in series get distinct values missing in bins, 
+        # the library ignores them
+        unknown = self._values_hashed[-1] + 1
+        converted = []
+        for value in values:
+            value_hashed = self._value_map_back.get(value, value)
+            if value_hashed not in self._value_map_hashed:
+                value_hashed = self._unknown_links.setdefault(
+                    value, unknown + len(self._unknown_links)
+                )
+            converted.append(value_hashed)
+        return converted
+
+    def _convert_value(self, value):
+        # Converted value for scoring: numeric values between the ones 
+        # in series take the nearest smaller one, None for unknown 
+        # categorial values
+        if value in self._value_map_back:
+            return self._value_map_back[value]
+        if self._series_type in (int, bool):
+            value_hashed = int(value)
+            if self._is_numeric or value_hashed in self._value_map_hashed:
+                return value_hashed
+            return None
+        if not self._is_numeric:
+            return None
+        idx = bisect.bisect_right(self._values_sorted, value) - 1
+        return self._values_hashed[max(idx, 0)]
+
+    def to_sql(self, column, dialect='ansi', default=0.0, null=None, 
+               in_lists=True, special_values=()):
+        # SQL CASE expression of WOE. default is for unseen categories 
+        # (they go to the 'other' bin if there is one), null is for NULL 
+        # if no bin has it (default if None), special numeric values are 
+        # checked before the ranges.
+        export = create_sql_export(dialect, default, null, in_lists)
+        self._add_sql_literals(export, column, special_values)
+        return render_sql(dll.sql_render_woe(
+            ctypes.c_void_p(export), column.encode(), ctypes.c_void_p(self._wbp),
+        ))
+
+    def _add_sql_literals(self, export, column, special_values):
+        values_hashed = self._values_hashed
+        literals = self._values_sorted
+        size = len(values_hashed)
+        dll.sql_add_literals(
+            ctypes.c_void_p(export), 
+            column.encode(), 
+            ctypes.c_uint64(size), 
+            (ctypes.c_uint64 * size)(*values_hashed), 
+            (ctypes.c_uint64 * size)(*(
+                0 if l is None else 2 if isinstance(l, str) else 1 
+                for l in literals
+            )), 
+            (ctypes.c_double * size)(*(
+                float(l) if isinstance(l, (int, float)) else 0.0 
+                for l in literals
+            )), 
+            (ctypes.c_char_p * size)(*(
+                l.encode() if isinstance(l, str) else None 
+                for l in literals
+            )),
+        )
+        special_hashed = self._convert_values(special_values)
+        dll.sql_set_special_values(
+            ctypes.c_void_p(export), 
+            column.encode(), 
+            ctypes.c_uint64(len(special_hashed)), 
+            (ctypes.c_uint64 * len(special_hashed))(*special_hashed),
+        )
+
+    def to_pmml(self, field, default=0.0, null=None):
+        # PMML document with the derived field {field}_woe, unseen 
+        # categories go to the 'other' bin if there is one
+        return binnings_to_pmml({field: self}, default, null)
+
+    def _add_pmml_values(self, export, field):
+        values_hashed = self._values_hashed
+        values = self._values_sorted
+        size = len(values_hashed)
+        dll.pmml_add_values(
+            ctypes.c_void_p(export), 
+            field.encode(), 
+            ctypes.c_uint64(size), 
+            (ctypes.c_uint64 * size)(*values_hashed), 
+            (ctypes.c_char_p * size)(*(
+                None if v is None else str(v).encode() for v in values
+            )),
+        )
+
+    def _convert_values(self, series):
+        # Converted values for the library, unknown categorial values get 
+        # a value missing in bins
+        unknown = self._values_hashed[-1] + 1
+        return [
+            unknown if value is None else value
+            for value in map(self._convert_value, series)
+        ]
+
+    def _convert_cut(self, cut):
+        # The smallest converted value not less than cut, numeric values 
+        # keep their order after conversion
+        idx = bisect.bisect_left(self._values_sorted, cut)
+        if idx < len(self._values_hashed):
+            return self._values_hashed[idx]
+        return self._values_hashed[-1] + 1
+
     def _preproc_series(self, series, is_numeric):
         series_type = detect_series_type(series, raise_on_not_numeric=is_numeric)
+        self._series_type = series_type
         series_hashed = self._convert_series(series, series_type)
         return series_hashed
 
@@ -177,12 +696,20 @@ class WoeBinningProc:
             ))
 
         elif series_type is str:
-            converted = list(map(
-                lambda x: unsigned_hash(str(x)) 
+            # Strings are numbered in sorted order instead of hashing, 
+            # because Python string hashes change between processes
+            keys = list(map(
+                lambda x: str(x) 
                     if not (isinstance(x, float) and math.isnan(x)) and x is not None 
-                    else none_hash,
+                    else None,
                 series
             ))
+            unique = sorted(set(filter(lambda k: k is not None, keys)))
+            key_map = dict(zip(unique, range(1, 1 + len(unique))))
+            converted = list(map(
+                lambda k: key_map[k] if k is not None else none_hash,
+                keys
+            ))
             self._value_map = dict(zip(converted, series))
             self._value_map_back = dict(zip(series, converted))
 
@@ -204,6 +731,377 @@ class WoeBinningProc:
         return converted
 
 
+class InteractionBinningProc:
+    """
+    Joint binning of two numeric series: each of them is split into 
+    pre-bins of about equal number of rows, the cells of the grid are 
+    merged with their neighbours into connected or rectangular regions.
+    """
+    def __init__(self):
+        self._ibp = None
+        self._x_values = None
+        self._y_values = None
+
+    def process(self, x, y, target, bins=10, smooth=1.0, max_prebins=10, 
+                rectangular=False):
+        assert len(x) == len(target)
+        assert len(y) == len(target)
+
+        size = len(target)
+
+        self._x_values = sorted(set(x))
+        self._y_values = sorted(set(y))
+
+        self._ibp = dll.ibp_new(bins, smooth)
+        dll.ibp_set_max_prebins(
+            ctypes.c_void_p(self._ibp),
+            ctypes.c_uint64(max_prebins),
+        )
+        dll.ibp_set_rectangular(
+            ctypes.c_void_p(self._ibp),
+            ctypes.c_bool(rectangular),
+        )
+        dll.ibp_process(
+            ctypes.c_void_p(self._ibp),
+            ctypes.c_uint64(size),
+            (ctypes.c_uint64 * size)(*self._convert_values(x, self._x_values)),
+            (ctypes.c_uint64 * size)(*self._convert_values(y, self._y_values)),
+            (ctypes.c_bool * size)(*map(bool, target)),
+        )
+
+    def set_bins_num(self, bins):
+        # Change the number of regions without processing again
+        dll.ibp_set_bins_num(
+            ctypes.c_void_p(self._ibp),
+            ctypes.c_uint64(bins),
+        )
+
+    def get_regions_info(self):
+        # Regions in ascending order of WOE, cells are pairs of x and y 
+        # ranges (min, max) of pre-bins as they were in the series
+        x_ranges = self._get_ranges(dll.ibp_get_x_cuts_num, 
+                                    dll.ibp_get_x_cuts, self._x_values)
+        y_ranges = self._get_ranges(dll.ibp_get_y_cuts_num, 
+                                    dll.ibp_get_y_cuts, self._y_values)
+
+        regions_num = dll.ibp_get_regions_num(self._ibp)
+        regions_info = (RegionInfo * regions_num)()
+        dll.ibp_get_regions_info(
+            ctypes.c_void_p(self._ibp),
+            ctypes.c_uint64(regions_num),
+            regions_info,
+        )
+
+        regions_info_list = []
+
+        for idx, region_info in enumerate(regions_info):
+            rows = (ctypes.c_uint64 * region_info.cells)()
+            cols = (ctypes.c_uint64 * region_info.cells)()
+            dll.ibp_get_region_cells(
+                ctypes.c_void_p(self._ibp),
+                ctypes.c_uint64(idx),
+                ctypes.c_uint64(region_info.cells),
+                rows,
+                cols,
+            )
+            regions_info_list.append({
+                'woe': region_info.woe,
+                'iv': region_info.iv,
+                'count': region_info.count,
+                'cells': [
+                    (x_ranges[row], y_ranges[col]) 
+                    for row, col in zip(rows, cols)
+                ],
+            })
+
+        return regions_info_list
+
+    def get_iv_total(self):
+        return sum(info['iv'] for info in self.get_regions_info())
+
+    def transform(self, x, y):
+        # WOE of pairs, values between pre-bins go to the left one on 
+        # each axis
+        assert len(x) == len(y)
+        size = len(x)
+        woe_array = (ctypes.c_double * size)()
+        dll.ibp_transform(
+            ctypes.c_void_p(self._ibp),
+            ctypes.c_uint64(size),
+            (ctypes.c_uint64 * size)(*self._convert_values(x, self._x_values)),
+            (ctypes.c_uint64 * size)(*self._convert_values(y, self._y_values)),
+            woe_array,
+        )
+        return list(woe_array)
+
+    def _get_ranges(self, cuts_num_method, cuts_method, values):
+        # Smallest and largest values of pre-bins of an axis
+        cuts_num = cuts_num_method(self._ibp)
+        cuts = (ctypes.c_uint64 * cuts_num)()
+        cuts_method(
+            ctypes.c_void_p(self._ibp), ctypes.c_uint64(cuts_num), cuts,
+        )
+        ends = list(cuts[1:]) + [len(values)]
+        return [
+            (values[start], values[end - 1]) 
+            for start, end in zip(cuts, ends)
+        ]
+
+    @staticmethod
+    def _convert_values(series, values):
+        # Ranks of values among the processed ones, a value between them 
+        # gets the rank of the nearest smaller one
+        return [
+            max(bisect.bisect_right(values, value) - 1, 0) 
+            for value in series
+        ]
+
+
+class Scorecard:
+    """
+    Scorecard points of fitted binnings. features is a dict of 
+    WoeBinningProc by feature name, coefficients is a dict of logistic 
+    regression coefficients on WOE by feature name (target 1 is bad). 
+    The score is base_score at good to bad odds base_odds and the odds 
+    double every pdo points.
+    """
+    def __init__(self, features, coefficients, intercept, base_score=600, 
+                 base_odds=50, pdo=20):
+        self._features = dict(features)
+        self._sc = dll.sc_new(intercept)
+        dll.sc_set_scaling(self._sc, base_score, base_odds, pdo)
+        for name, wbp in self._features.items():
+            dll.sc_add_feature(
+                self._sc, name.encode(), wbp._wbp, coefficients[name],
+            )
+
+    def get_base_points(self):
+        # Points added to every score
+        return dll.sc_get_base_points(self._sc)
+
+    def get_table(self):
+        table = []
+        for feature_idx, (name, wbp) in enumerate(self._features.items()):
+            bins_info = wbp.get_bins_info()
+            points = (ctypes.c_double * len(bins_info))()
+            dll.sc_get_points(
+                ctypes.c_void_p(self._sc), 
+                ctypes.c_uint64(feature_idx), 
+                ctypes.c_uint64(len(bins_info)), 
+                points,
+            )
+            for idx, info in enumerate(bins_info):
+                table.append({
+                    'feature': name,
+                    'bin': idx,
+                    'values': info['values'],
+                    'woe': info['woe'],
+                    'points': points[idx],
+                })
+        return table
+
+    def score(self, record):
+        # record is a dict of values by feature name, None if 
+        # a categorial value is unknown and there is no 'other' bin
+        values = [
+            wbp._convert_values([record[name]])[0] 
+            for name, wbp in self._features.items()
+        ]
+        score = dll.sc_score(
+            ctypes.c_void_p(self._sc), 
+            ctypes.c_uint64(len(values)), 
+            (ctypes.c_uint64 * len(values))(*values),
+        )
+        return None if math.isnan(score) else score
+
+    def to_sql(self, dialect='ansi', default=0.0, null=None, in_lists=True, 
+               special_values=None):
+        # SQL expression of the score: base points plus CASE expressions 
+        # of points, columns are the feature names. special_values is 
+        # a dict of lists by feature name.
+        export = create_sql_export(dialect, default, null, in_lists)
+        for name, wbp in self._features.items():
+            wbp._add_sql_literals(export, name, 
+                                  (special_values or {}).get(name, ()))
+        names = list(self._features)
+        return render_sql(dll.sql_render_scorecard(
+            ctypes.c_void_p(export), 
+            ctypes.c_void_p(self._sc), 
+            ctypes.c_uint64(len(names)), 
+            (ctypes.c_char_p * len(names))(*(n.encode() for n in names)),
+        ))
+
+    def to_pmml(self, default=0.0, null=None):
+        # PMML Scorecard model with reason codes, fields are the feature 
+        # names
+        export = create_pmml_export(default, null)
+        for name, wbp in self._features.items():
+            wbp._add_pmml_values(export, name)
+        return render_pmml(dll.pmml_render_scorecard(
+            ctypes.c_void_p(export), ctypes.c_void_p(self._sc),
+        ))
+
+    def to_csv(self):
+        # The first row has the base points, numeric bins are written 
+        # as ranges min..max, fields are quoted as in RFC 4180
+        text = io.StringIO()
+        writer = csv.writer(text, lineterminator='\n')
+        writer.writerow(['feature', 'bin', 'values', 'woe', 'points'])
+        writer.writerow(['', '', '', '', self.get_base_points()])
+        for row in self.get_table():
+            if self._features[row['feature']]._is_numeric:
+                values = f"{min(row['values'])}..{max(row['values'])}"
+            else:
+                values = ' '.join(map(str, row['values']))
+            writer.writerow([row['feature'], row['bin'], values, 
+                             row['woe'], row['points']])
+        return text.getvalue()
+
+
+class LogisticRegression:
+    """
+    Logistic regression (Newton iterations with optional L2 penalty) on 
+    WOE of fitted binnings. Since WOE is ln(good share / bad share) with 
+    target 1 as bad, coefficients are expected to be negative.
+    """
+    def __init__(self, l2=0.0, max_iter=100):
+        self._lr = dll.lr_new()
+        self._features = []
+        dll.lr_set_l2(self._lr, l2)
+        dll.lr_set_max_iter(ctypes.c_void_p(self._lr), ctypes.c_uint64(max_iter))
+
+    def fit(self, features, data, target):
+        # features is a dict of WoeBinningProc by feature name, data is 
+        # a dict of series by feature name. Returns whether Newton 
+        # iterations converged.
+        self._features = list(features)
+        size = len(target)
+        series = []
+        for name in self._features:
+            assert len(data[name]) == size
+            series.extend(features[name]._convert_values(data[name]))
+
+        features_num = len(self._features)
+        return dll.lr_fit_woe(
+            ctypes.c_void_p(self._lr),
+            ctypes.c_uint64(features_num),
+            (ctypes.c_void_p * features_num)(
+                *(features[name]._wbp for name in self._features)
+            ),
+            ctypes.c_uint64(size),
+            (ctypes.c_uint64 * len(series))(*series),
+            (ctypes.c_bool * size)(*map(bool, target)),
+        )
+
+    def summary(self):
+        # The intercept goes first with feature None, sign_ok is False 
+        # for a positive coefficient
+        size = len(self._features) + 1
+        info_array = (CoefficientInfo * size)()
+        dll.lr_get_coefficients_info(
+            ctypes.c_void_p(self._lr), ctypes.c_uint64(size), info_array,
+        )
+        return [
+            {
+                'feature': name,
+                'coefficient': info.coefficient,
+                'std_error': info.std_error,
+                'p_value': info.p_value,
+                'sign_ok': info.sign_ok,
+            }
+            for name, info in zip([None] + self._features, info_array)
+        ]
+
+    def get_intercept(self):
+        return self.summary()[0]['coefficient']
+
+    def get_coefficients(self):
+        # Coefficients by feature name, they can be passed to Scorecard
+        return {
+            row['feature']: row['coefficient'] for row in self.summary()[1:]
+        }
+
+
+def create_sql_export(dialect, default, null, in_lists):
+    assert dialect in SQL_DIALECTS, f"Unknown SQL dialect: {dialect}"
+    export = dll.sql_new(ctypes.c_uint64(SQL_DIALECTS[dialect]))
+    dll.sql_set_default(export, default)
+    dll.sql_set_null(export, null is not None, null or 0.0)
+    dll.sql_set_in_lists(ctypes.c_void_p(export), ctypes.c_bool(in_lists))
+    return export
+
+
+def render_sql(sql_ptr):
+    # Copy and release a string of the library, null if the SQL has 
+    # a NUL byte
+    assert sql_ptr, "SQL has a NUL byte"
+    sql = ctypes.string_at(sql_ptr).decode()
+    dll.sql_free_string(sql_ptr)
+    return sql
+
+
+def binnings_to_pmml(binnings, default=0.0, null=None):
+    """
+    PMML document of several binnings given as a dict of WoeBinningProc 
+    by field name: a derived field {field}_woe for each one (Discretize 
+    for numeric binnings and MapValues for categorial ones). default is 
+    for unseen categories of binnings without the 'other' bin, null is for 
+    missing values if no bin has them.
+    """
+    export = create_pmml_export(default, null)
+    for field, wbp in binnings.items():
+        wbp._add_pmml_values(export, field)
+    fields = list(binnings)
+    return render_pmml(dll.pmml_render_binnings(
+        ctypes.c_void_p(export), 
+        ctypes.c_uint64(len(fields)), 
+        (ctypes.c_char_p * len(fields))(*(f.encode() for f in fields)), 
+        (ctypes.c_void_p * len(fields))(*(binnings[f]._wbp for f in fields)),
+    ))
+
+
+def create_pmml_export(default, null):
+    export = dll.pmml_new()
+    dll.pmml_set_default(export, default)
+    dll.pmml_set_null(export, null is not None, null or 0.0)
+    return export
+
+
+def render_pmml(pmml_ptr):
+    # Copy and release a string of the library, null if the PMML has 
+    # a NUL byte
+    assert pmml_ptr, "PMML has a NUL byte"
+    pmml = ctypes.string_at(pmml_ptr).decode()
+    dll.pmml_free_string(pmml_ptr)
+    return pmml
+
+
+def linkage(distances, method='single'):
+    """
+    Hierarchical clustering by a condensed distance matrix (as returned by 
+    scipy.spatial.distance.pdist). Returns the linkage matrix in SciPy 
+    format: a list of rows [idx1, idx2, distance, count].
+    """
+    assert method in LINKAGES, f"Unknown linkage: {method}"
+
+    size = len(distances)
+    points_num = int(round((1 + math.sqrt(1 + 8 * size)) / 2))
+    assert points_num * (points_num - 1) // 2 == size, \
+        "Invalid condensed matrix length"
+
+    # Fewer rows if NaN distances block merges
+    matrix = (ctypes.c_double * (4 * (points_num - 1)))()
+    rows_num = dll.hc_linkage_matrix(
+        ctypes.c_uint64(size),
+        (ctypes.c_double * size)(*distances),
+        ctypes.c_uint64(LINKAGES[method]),
+        matrix,
+    )
+    assert rows_num >= 0, f"Unknown linkage: {method}"
+
+    return [list(matrix[4 * idx:4 * idx + 4]) for idx in range(rows_num)]
+
+
 def detect_series_type(series, raise_on_not_numeric=False):
     # Get unique types
     types = set(map(type, series))
@@ -237,6 +1135,33 @@ def unsigned_hash(x):
     return ctypes.c_uint64(hash(x)).value
 
 
+class MetricsInfo(ctypes.Structure):
+    _fields_ = [
+        ('auc', ctypes.c_double),
+        ('gini', ctypes.c_double),
+        ('ks', ctypes.c_double),
+        ('divergence', ctypes.c_double),
+    ]
+
+
+class CoefficientInfo(ctypes.Structure):
+    _fields_ = [
+        ('coefficient', ctypes.c_double),
+        ('std_error', ctypes.c_double),
+        ('p_value', ctypes.c_double),
+        ('sign_ok', ctypes.c_bool),
+    ]
+
+
+class RegionInfo(ctypes.Structure):
+    _fields_ = [
+        ('woe', ctypes.c_double),
+        ('iv', ctypes.c_double),
+        ('count', ctypes.c_uint64),
+        ('cells', ctypes.c_uint64),
+    ]
+
+
 class BinInfo(ctypes.Structure):
     _fields_ = [
         ('woe', ctypes.c_double),